@@ -0,0 +1,87 @@
+//! Resolving and connecting to literal addresses, whose results are kept inline, against
+//! results read from a `Vec` as those of longer answers are, in time and heap allocations.
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    hint::black_box,
+    io,
+    net::{SocketAddr, TcpListener},
+    sync::atomic::{AtomicUsize, Ordering},
+    time::{Duration, Instant},
+};
+
+use to_socket_addrs_timeout::{connect_each, Budget, ResolvedAddrs, ToSocketAddrsTimeout};
+
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+fn bench(name: &str, iters: u32, mut f: impl FnMut()) {
+    for _ in 0..iters / 10 {
+        f();
+    }
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+    let start = Instant::now();
+    for _ in 0..iters {
+        f();
+    }
+    let per_iter = start.elapsed() / iters;
+    let allocations = (ALLOCATIONS.load(Ordering::Relaxed) - allocations) as f64 / iters as f64;
+    println!(
+        "{name:<32} {:>8} ns/iter {allocations:>6.2} allocations/iter",
+        per_iter.as_nanos()
+    );
+}
+
+fn main() -> io::Result<()> {
+    let addr: SocketAddr = "192.0.2.1:443".parse().unwrap();
+    let addrs: [SocketAddr; 4] = [
+        addr,
+        "[2001:db8::1]:443".parse().unwrap(),
+        "192.0.2.2:443".parse().unwrap(),
+        "[2001:db8::2]:443".parse().unwrap(),
+    ];
+    let timeout = Duration::from_secs(1);
+
+    bench("literal lookup", 1_000_000, || {
+        let addrs = black_box("192.0.2.1:443").to_socket_addrs_timeout(timeout);
+        black_box(addrs.unwrap().next());
+    });
+    bench("one address, inline", 1_000_000, || {
+        black_box(ResolvedAddrs::from(black_box(addr)).next());
+    });
+    bench("one address, from a Vec", 1_000_000, || {
+        black_box(ResolvedAddrs::from(vec![black_box(addr)]).next());
+    });
+    bench("four addresses, inline", 1_000_000, || {
+        let addrs: ResolvedAddrs = black_box(addrs).into_iter().collect();
+        black_box(addrs.interleave().dedup().next());
+    });
+    bench("four addresses, from a Vec", 1_000_000, || {
+        let addrs = ResolvedAddrs::from(black_box(addrs).to_vec());
+        black_box(addrs.interleave().dedup().next());
+    });
+
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let local = listener.local_addr()?;
+    bench("connect_each to loopback", 2_000, || {
+        let connected = connect_each([local], &Budget::new(timeout)).unwrap();
+        black_box(connected.stream);
+        drop(listener.accept());
+    });
+    Ok(())
+}
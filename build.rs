@@ -0,0 +1,81 @@
+//! Selects the resolver backend, exposed to the crate as `cfg(backend = "...")`.
+
+use std::env;
+
+const BACKENDS: &[&str] = &[
+    "android",
+    "apple",
+    "bsd",
+    "c_ares",
+    "fallback",
+    "illumos",
+    "linux_glibc",
+    "stub",
+    "wasi",
+    "wasm",
+    "windows",
+];
+
+fn main() {
+    println!("cargo::rerun-if-changed=build.rs");
+    println!(
+        "cargo::rustc-check-cfg=cfg(backend, values({}))",
+        BACKENDS
+            .iter()
+            .map(|b| format!("\"{b}\""))
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+
+    let target = |key: &str| env::var(format!("CARGO_CFG_TARGET_{key}")).unwrap_or_default();
+    let feature = |name: &str| env::var_os(format!("CARGO_FEATURE_{name}")).is_some();
+    let (os, target_env, vendor, arch) = (
+        target("OS"),
+        target("ENV"),
+        target("VENDOR"),
+        target("ARCH"),
+    );
+    let unix = target("FAMILY").split(',').any(|f| f == "unix");
+    let glibc = os == "linux" && target_env == "gnu";
+
+    // in order of precedence, so that `--all-features` still builds
+    let forced: Vec<_> = [
+        ("FORCE_FALLBACK", "fallback"),
+        ("FORCE_STUB", "stub"),
+        ("FORCE_GLIBC_ASYNC", "linux_glibc"),
+    ]
+    .into_iter()
+    .filter(|&(name, _)| feature(name))
+    .filter(|&(_, backend)| {
+        let supported = backend != "linux_glibc" || glibc;
+        if !supported {
+            println!("cargo::warning=`force-glibc-async` ignored on a target without glibc");
+        }
+        supported
+    })
+    .map(|(_, backend)| backend)
+    .collect();
+    if forced.len() > 1 {
+        println!(
+            "cargo::warning=several `force-*` features enabled, using the `{}` backend",
+            forced[0]
+        );
+    }
+
+    let backend = match forced.first() {
+        Some(backend) => backend,
+        None if feature("C_ARES") && unix && vendor != "apple" => "c_ares",
+        None if os == "android" => "android",
+        None if vendor == "apple" => "apple",
+        None if matches!(&*os, "freebsd" | "openbsd" | "netbsd" | "dragonfly") => "bsd",
+        None if matches!(&*os, "illumos" | "solaris") => "illumos",
+        None if glibc => "linux_glibc",
+        None if os == "linux" && target_env == "musl" => "stub",
+        None if os == "wasi" && target_env == "p2" => "wasi",
+        None if arch == "wasm32" && os == "unknown" => "wasm",
+        None if os == "windows" => "windows",
+        None => "fallback",
+    };
+    println!("cargo::rustc-cfg=backend=\"{backend}\"");
+    println!("cargo::rustc-env=TO_SOCKET_ADDRS_TIMEOUT_BACKEND={backend}");
+}
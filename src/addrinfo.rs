@@ -0,0 +1,466 @@
+use std::{
+    io,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    time::Duration,
+};
+
+use crate::{
+    services, Family, HintFlags, Hints, Protocol, ResolveError, ResolverBackend, SocketType,
+};
+
+/// One `addrinfo` entry of a lookup, with the metadata flattening to [`SocketAddr`] loses.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AddrInfo {
+    /// [`Family::Ipv4`] or [`Family::Ipv6`], matching `addr`.
+    pub family: Family,
+    pub socktype: SocketType,
+    pub protocol: Protocol,
+    pub flags: HintFlags,
+    /// The canonical name of the host, on the first entry only and when asked for with
+    /// [`HintFlags::CANONNAME`].
+    pub canonical_name: Option<String>,
+    pub addr: SocketAddr,
+}
+
+/// Addresses read on demand off the list the platform resolver returned, which is freed once
+/// the iterator is dropped.
+///
+/// Backends that do not return such a list hand out their addresses from a `Vec` instead, or
+/// from the iterator itself for the few addresses most lookups return.
+pub struct AddrInfoIter {
+    entries: Entries,
+}
+
+/// Addresses collected into an [`AddrInfoIter`] stay inline up to this many.
+pub(crate) const INLINE: usize = 4;
+
+/// Filler of the inline slots not holding an address.
+const UNUSED: SocketAddr = SocketAddr::V4(std::net::SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 0));
+
+enum Entries {
+    #[cfg(any(backend = "linux_glibc", backend = "windows"))]
+    Native(crate::sys::LookupHost),
+    /// The addresses in `addrs[next..len]`.
+    Inline {
+        addrs: [SocketAddr; INLINE],
+        next: u8,
+        len: u8,
+    },
+    Listed(std::vec::IntoIter<SocketAddr>),
+}
+
+impl AddrInfoIter {
+    #[cfg(any(backend = "linux_glibc", backend = "windows"))]
+    pub(crate) fn native(lh: crate::sys::LookupHost) -> Self {
+        Self {
+            entries: Entries::Native(lh),
+        }
+    }
+
+    fn inline(addrs: [SocketAddr; INLINE], len: usize) -> Self {
+        Self {
+            entries: Entries::Inline {
+                addrs,
+                next: 0,
+                len: len as u8,
+            },
+        }
+    }
+
+    /// The next address, without taking it.
+    pub(crate) fn peek(&self) -> Option<SocketAddr> {
+        match &self.entries {
+            #[cfg(any(backend = "linux_glibc", backend = "windows"))]
+            Entries::Native(lh) => lh.remaining().next(),
+            Entries::Inline { addrs, next, len } => {
+                addrs[*next as usize..*len as usize].first().copied()
+            }
+            Entries::Listed(addrs) => addrs.as_slice().first().copied(),
+        }
+    }
+
+    /// The addresses not returned yet, copied off the platform resolver's list first if need be.
+    pub(crate) fn owned_mut(&mut self) -> &mut [SocketAddr] {
+        #[cfg(any(backend = "linux_glibc", backend = "windows"))]
+        if let Entries::Native(lh) = &self.entries {
+            *self = lh.remaining().collect();
+        }
+        match &mut self.entries {
+            #[cfg(any(backend = "linux_glibc", backend = "windows"))]
+            Entries::Native(_) => unreachable!(),
+            Entries::Inline { addrs, next, len } => &mut addrs[*next as usize..*len as usize],
+            Entries::Listed(addrs) => addrs.as_mut_slice(),
+        }
+    }
+
+    /// Keeps the first `len` of the addresses not returned yet, inline if they fit.
+    pub(crate) fn truncate(&mut self, len: usize) {
+        if let Entries::Inline { next, len: end, .. } = &mut self.entries {
+            *end = (*next as usize + len).min(*end as usize) as u8;
+        } else {
+            let addrs = self.owned_mut();
+            *self = addrs[..len.min(addrs.len())].iter().copied().collect();
+        }
+    }
+}
+
+impl From<SocketAddr> for AddrInfoIter {
+    fn from(addr: SocketAddr) -> Self {
+        std::iter::once(addr).collect()
+    }
+}
+
+/// Keeps up to [`INLINE`] addresses in the iterator itself, the rest are collected in a `Vec`.
+impl FromIterator<SocketAddr> for AddrInfoIter {
+    fn from_iter<I: IntoIterator<Item = SocketAddr>>(iter: I) -> Self {
+        let mut iter = iter.into_iter();
+        let mut addrs = [UNUSED; INLINE];
+        for len in 0..INLINE {
+            match iter.next() {
+                Some(addr) => addrs[len] = addr,
+                None => return Self::inline(addrs, len),
+            }
+        }
+        match iter.next() {
+            None => Self::inline(addrs, INLINE),
+            Some(addr) => {
+                let mut listed = Vec::with_capacity(INLINE + 1 + iter.size_hint().0);
+                listed.extend(addrs);
+                listed.push(addr);
+                listed.extend(iter);
+                listed.into_iter().into()
+            }
+        }
+    }
+}
+
+impl From<std::vec::IntoIter<SocketAddr>> for AddrInfoIter {
+    fn from(addrs: std::vec::IntoIter<SocketAddr>) -> Self {
+        Self {
+            entries: Entries::Listed(addrs),
+        }
+    }
+}
+
+impl Iterator for AddrInfoIter {
+    type Item = SocketAddr;
+
+    fn next(&mut self) -> Option<SocketAddr> {
+        match &mut self.entries {
+            #[cfg(any(backend = "linux_glibc", backend = "windows"))]
+            Entries::Native(lh) => lh.next(),
+            Entries::Inline { addrs, next, len } => {
+                let addr = addrs[..*len as usize].get(*next as usize).copied()?;
+                *next += 1;
+                Some(addr)
+            }
+            Entries::Listed(addrs) => addrs.next(),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match &self.entries {
+            #[cfg(any(backend = "linux_glibc", backend = "windows"))]
+            Entries::Native(lh) => {
+                let len = lh.remaining().count();
+                (len, Some(len))
+            }
+            Entries::Inline { next, len, .. } => {
+                let len = usize::from(len - next);
+                (len, Some(len))
+            }
+            Entries::Listed(addrs) => addrs.size_hint(),
+        }
+    }
+}
+
+impl ExactSizeIterator for AddrInfoIter {}
+
+impl std::iter::FusedIterator for AddrInfoIter {}
+
+/// Copies the addresses not returned yet, off the platform resolver's list for the copy.
+impl Clone for AddrInfoIter {
+    fn clone(&self) -> Self {
+        match &self.entries {
+            #[cfg(any(backend = "linux_glibc", backend = "windows"))]
+            Entries::Native(lh) => lh.remaining().collect(),
+            &Entries::Inline { addrs, next, len } => Self {
+                entries: Entries::Inline { addrs, next, len },
+            },
+            Entries::Listed(addrs) => addrs.clone().into(),
+        }
+    }
+}
+
+impl std::fmt::Debug for AddrInfoIter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut list = f.debug_list();
+        match &self.entries {
+            #[cfg(any(backend = "linux_glibc", backend = "windows"))]
+            Entries::Native(lh) => list.entries(lh.remaining()),
+            Entries::Inline { addrs, next, len } => {
+                list.entries(&addrs[*next as usize..*len as usize])
+            }
+            Entries::Listed(addrs) => list.entries(addrs.as_slice()),
+        };
+        list.finish()
+    }
+}
+
+/// A failure code of the platform's `getaddrinfo`, `GetAddrInfoExW` on Windows, carried by the
+/// [`io::Error`] of the lookups that use it, which [`io::Error::get_ref`] downcasts to.
+///
+/// The same failures are told apart on every platform by [`is_not_found`](Self::is_not_found)
+/// and [`is_temporary`](Self::is_temporary), and read into a [`ResolveError`].
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct AddressInfoError(i32);
+
+/// `getaddrinfo` codes glibc reports on top of those of POSIX.
+#[cfg(all(target_os = "linux", target_env = "gnu"))]
+mod glibc {
+    pub(super) const EAI_ADDRFAMILY: i32 = -9;
+    pub(super) const EAI_CANCELED: i32 = -101;
+    pub(super) const EAI_INTR: i32 = -104;
+    pub(super) const EAI_IDN_ENCODE: i32 = -105;
+}
+
+impl AddressInfoError {
+    /// The error for `code`, an `EAI_*` value or a `WSA*` one on Windows.
+    pub const fn from_raw_code(code: i32) -> Self {
+        Self(code)
+    }
+
+    /// The code the platform resolver reported, an `EAI_*` value or a `WSA*` one on Windows.
+    pub const fn raw_code(self) -> i32 {
+        self.0
+    }
+
+    /// Whether the name does not exist or has no address: `EAI_NONAME` and `EAI_NODATA`,
+    /// `WSAHOST_NOT_FOUND` and `WSANO_DATA` on Windows.
+    pub fn is_not_found(self) -> bool {
+        matches!(
+            self.resolve_error(),
+            Some(ResolveError::HostNotFound | ResolveError::NoAddresses)
+        )
+    }
+
+    /// Whether the same lookup may succeed if tried again: `EAI_AGAIN`, `WSATRY_AGAIN` on
+    /// Windows.
+    pub fn is_temporary(self) -> bool {
+        matches!(self.resolve_error(), Some(ResolveError::TemporaryFailure))
+    }
+
+    /// The failure as a [`ResolveError`], for those it has a variant for.
+    pub(crate) fn resolve_error(self) -> Option<ResolveError> {
+        #[cfg(unix)]
+        match self.0 {
+            libc::EAI_NONAME => return Some(ResolveError::HostNotFound),
+            #[cfg(not(any(target_os = "freebsd", target_os = "dragonfly")))]
+            libc::EAI_NODATA => return Some(ResolveError::NoAddresses),
+            #[cfg(all(target_os = "linux", target_env = "gnu"))]
+            glibc::EAI_ADDRFAMILY => return Some(ResolveError::NoAddresses),
+            libc::EAI_AGAIN => return Some(ResolveError::TemporaryFailure),
+            _ => (),
+        }
+        #[cfg(windows)]
+        {
+            use windows::Win32::Networking::WinSock::{
+                WSAHOST_NOT_FOUND, WSANO_DATA, WSATRY_AGAIN, WSA_ERROR,
+            };
+
+            match WSA_ERROR(self.0) {
+                WSAHOST_NOT_FOUND => return Some(ResolveError::HostNotFound),
+                WSANO_DATA => return Some(ResolveError::NoAddresses),
+                WSATRY_AGAIN => return Some(ResolveError::TemporaryFailure),
+                _ => (),
+            }
+        }
+        None
+    }
+
+    fn kind(self) -> io::ErrorKind {
+        match self.resolve_error() {
+            Some(ResolveError::TemporaryFailure) => io::ErrorKind::ResourceBusy,
+            Some(_) => io::ErrorKind::NotFound,
+            None => native_kind(self.0),
+        }
+    }
+}
+
+#[cfg(unix)]
+fn native_kind(code: i32) -> io::ErrorKind {
+    match code {
+        libc::EAI_BADFLAGS => io::ErrorKind::InvalidInput,
+        libc::EAI_FAMILY | libc::EAI_SOCKTYPE | libc::EAI_SERVICE => io::ErrorKind::Unsupported,
+        libc::EAI_MEMORY => io::ErrorKind::OutOfMemory,
+        #[cfg(all(target_os = "linux", target_env = "gnu"))]
+        glibc::EAI_IDN_ENCODE => io::ErrorKind::InvalidInput,
+        #[cfg(all(target_os = "linux", target_env = "gnu"))]
+        glibc::EAI_INTR | glibc::EAI_CANCELED => io::ErrorKind::Interrupted,
+        _ => io::ErrorKind::Other,
+    }
+}
+
+#[cfg(windows)]
+fn native_kind(code: i32) -> io::ErrorKind {
+    use windows::Win32::Networking::WinSock::{
+        WSAEAFNOSUPPORT, WSAESOCKTNOSUPPORT, WSATYPE_NOT_FOUND, WSA_ERROR,
+    };
+
+    match WSA_ERROR(code) {
+        WSAEAFNOSUPPORT | WSAESOCKTNOSUPPORT | WSATYPE_NOT_FOUND => io::ErrorKind::Unsupported,
+        _ => io::Error::from_raw_os_error(code).kind(),
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+fn native_kind(_code: i32) -> io::ErrorKind {
+    io::ErrorKind::Other
+}
+
+impl core::fmt::Display for AddressInfoError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        #[cfg(unix)]
+        unsafe {
+            let ptr = libc::gai_strerror(self.0);
+            if !ptr.is_null() {
+                return core::fmt::Display::fmt(
+                    &::core::ffi::CStr::from_ptr(ptr).to_string_lossy(),
+                    f,
+                );
+            }
+        }
+        #[cfg(windows)]
+        return core::fmt::Display::fmt(&io::Error::from_raw_os_error(self.0), f);
+        #[cfg(not(windows))]
+        write!(f, "address lookup error {}", self.0)
+    }
+}
+
+impl core::fmt::Debug for AddressInfoError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "AddressInfoError({})", self.0)
+    }
+}
+
+impl std::error::Error for AddressInfoError {}
+
+/// An error of the kind matching the code, carrying `value`.
+impl From<AddressInfoError> for io::Error {
+    fn from(value: AddressInfoError) -> Self {
+        io::Error::new(value.kind(), value)
+    }
+}
+
+/// `getaddrinfo` in full: the entries for `host` and `service`, either of which may be left out,
+/// as the platform resolver reports them.
+///
+/// Without a host the entries are the wildcard addresses with [`HintFlags::PASSIVE`] and the
+/// loopback addresses otherwise; without a service the port is 0. Backends that only give
+/// addresses emulate the rest, reporting each address once per socket type the hints allow.
+pub fn lookup_addrinfo(
+    host: Option<&str>,
+    service: Option<&str>,
+    hints: &Hints,
+    timeout: Duration,
+) -> io::Result<Vec<AddrInfo>> {
+    if host.is_none() && service.is_none() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "neither host nor service given",
+        ));
+    }
+
+    #[cfg(backend = "windows")]
+    return crate::sys::lookup_addrinfo(host, service, hints, timeout);
+
+    #[cfg(not(backend = "windows"))]
+    {
+        #[cfg(backend = "linux_glibc")]
+        if let Some(res) = crate::sys::lookup_addrinfo(host, service, hints, timeout) {
+            return res;
+        }
+
+        emulate(host, service, hints, timeout)
+    }
+}
+
+#[cfg_attr(backend = "windows", allow(dead_code))]
+fn emulate(
+    host: Option<&str>,
+    service: Option<&str>,
+    hints: &Hints,
+    timeout: Duration,
+) -> io::Result<Vec<AddrInfo>> {
+    let port = match service {
+        None => 0,
+        Some(service) => match service.parse() {
+            Ok(port) => port,
+            Err(_) if hints.flags.contains(HintFlags::NUMERICSERV) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "service is not a port number",
+                ))
+            }
+            Err(_) => {
+                services::port(service, hints.service_proto()).ok_or_else(services::unknown)?
+            }
+        },
+    };
+
+    let (mut name, addrs): (Option<String>, Vec<_>) = match host {
+        None => {
+            let (v4, v6) = if hints.flags.contains(HintFlags::PASSIVE) {
+                (Ipv4Addr::UNSPECIFIED, Ipv6Addr::UNSPECIFIED)
+            } else {
+                (Ipv4Addr::LOCALHOST, Ipv6Addr::LOCALHOST)
+            };
+            let addrs = [IpAddr::from(v4), IpAddr::from(v6)]
+                .map(|ip| SocketAddr::new(ip, port))
+                .to_vec();
+            (None, hints.filter(addrs.into_iter()).collect())
+        }
+        Some(host) if hints.flags.contains(HintFlags::CANONNAME) => {
+            let res = crate::sys::SystemResolver.resolve_canonical(host, port, hints, timeout)?;
+            (Some(res.name), res.addrs)
+        }
+        Some(host) => (
+            None,
+            crate::sys::SystemResolver
+                .resolve_with_hints(host, port, hints, timeout)?
+                .collect(),
+        ),
+    };
+
+    let kinds: &[_] = match (hints.socktype, hints.protocol) {
+        (SocketType::Any, Protocol::Any) => &[
+            (SocketType::Stream, Protocol::Tcp),
+            (SocketType::Datagram, Protocol::Udp),
+            (SocketType::Raw, Protocol::Any),
+        ],
+        (SocketType::Any | SocketType::Stream, Protocol::Tcp)
+        | (SocketType::Stream, Protocol::Any) => &[(SocketType::Stream, Protocol::Tcp)],
+        (SocketType::Any | SocketType::Datagram, Protocol::Udp)
+        | (SocketType::Datagram, Protocol::Any) => &[(SocketType::Datagram, Protocol::Udp)],
+        (socktype, protocol) => &[(socktype, protocol)],
+    };
+    Ok(addrs
+        .into_iter()
+        .flat_map(|addr| kinds.iter().map(move |&kind| (addr, kind)))
+        .map(|(addr, (socktype, protocol))| AddrInfo {
+            family: family_of(&addr),
+            socktype,
+            protocol,
+            flags: hints.flags,
+            canonical_name: name.take(),
+            addr,
+        })
+        .collect())
+}
+
+pub(crate) fn family_of(addr: &SocketAddr) -> Family {
+    match addr {
+        SocketAddr::V4(_) => Family::Ipv4,
+        SocketAddr::V6(_) => Family::Ipv6,
+    }
+}
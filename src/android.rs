@@ -0,0 +1,504 @@
+use std::{
+    collections::HashMap,
+    ffi::CString,
+    future::Future,
+    io,
+    net::{IpAddr, SocketAddr},
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, OnceLock,
+    },
+    task::{ready, Context, Poll},
+    thread,
+    time::Duration,
+};
+
+use crate::{
+    completion::Completion,
+    dns::{Message, Rcode, Record, CLASS_IN, TYPE_A, TYPE_AAAA},
+    fallback,
+    future::block_on_deadline,
+    hosts, ResolveError, ResolverBackend,
+};
+
+#[allow(non_camel_case_types)]
+type net_handle_t = u64;
+
+/// The default network, `NETWORK_UNSPECIFIED`.
+const NETWORK_UNSPECIFIED: net_handle_t = 0;
+
+/// Largest answer read back, `MAXPACKET` in bionic.
+const MAX_ANSWER: usize = 8192;
+
+type NqueryFn = unsafe extern "C" fn(
+    network: net_handle_t,
+    dname: *const ::core::ffi::c_char,
+    ns_class: ::core::ffi::c_int,
+    ns_type: ::core::ffi::c_int,
+    flags: u32,
+) -> ::core::ffi::c_int;
+
+type NresultFn = unsafe extern "C" fn(
+    fd: ::core::ffi::c_int,
+    rcode: *mut ::core::ffi::c_int,
+    answer: *mut u8,
+    anslen: usize,
+) -> ::core::ffi::c_int;
+
+type CancelFn = unsafe extern "C" fn(fd: ::core::ffi::c_int);
+
+/// The `android/multinetwork.h` resolver functions, added in API level 29 and looked up at
+/// runtime so that older releases fall back to the thread based lookup.
+struct Api {
+    nquery: NqueryFn,
+    nresult: NresultFn,
+    cancel: CancelFn,
+}
+
+fn api() -> Option<&'static Api> {
+    static API: OnceLock<Option<Api>> = OnceLock::new();
+    API.get_or_init(|| unsafe {
+        let lib = libc::dlopen(c"libandroid.so".as_ptr(), libc::RTLD_NOW);
+        if lib.is_null() {
+            return None;
+        }
+        let nquery = libc::dlsym(lib, c"android_res_nquery".as_ptr());
+        let nresult = libc::dlsym(lib, c"android_res_nresult".as_ptr());
+        let cancel = libc::dlsym(lib, c"android_res_cancel".as_ptr());
+        if nquery.is_null() || nresult.is_null() || cancel.is_null() {
+            libc::dlclose(lib);
+            return None;
+        }
+        // the library stays loaded for the symbols to remain valid
+        Some(Api {
+            nquery: core::mem::transmute::<*mut ::core::ffi::c_void, NqueryFn>(nquery),
+            nresult: core::mem::transmute::<*mut ::core::ffi::c_void, NresultFn>(nresult),
+            cancel: core::mem::transmute::<*mut ::core::ffi::c_void, CancelFn>(cancel),
+        })
+    })
+    .as_ref()
+}
+
+/// Whether the asynchronous resolver functions are there, lookups running on worker threads
+/// otherwise.
+pub(crate) fn has_async_api() -> bool {
+    api().is_some()
+}
+
+/// State shared between a [`Lookup`] and the event loop.
+struct Query {
+    port: u16,
+    completion: Completion<io::Result<std::vec::IntoIter<SocketAddr>>>,
+}
+
+/// A query being waited on by the event loop.
+struct Active {
+    query: Arc<Query>,
+    /// Query descriptors, with their type, until their answer is read.
+    fds: Vec<(::core::ffi::c_int, u16)>,
+    /// Answers by query type, in the order the queries were sent.
+    answers: Vec<(u16, Option<io::Result<Vec<IpAddr>>>)>,
+}
+
+impl Active {
+    fn finish(self) {
+        let mut addrs = Vec::new();
+        let mut err = None;
+        for (_, answer) in self.answers {
+            match answer {
+                Some(Ok(found)) => addrs.extend(found),
+                Some(Err(e)) => {
+                    err.get_or_insert(e);
+                }
+                None => (),
+            }
+        }
+        let port = self.query.port;
+        let result = if !addrs.is_empty() {
+            Ok(addrs
+                .into_iter()
+                .map(|ip| SocketAddr::new(ip, port))
+                .collect::<Vec<_>>()
+                .into_iter())
+        } else {
+            Err(err.unwrap_or_else(|| ResolveError::NoAddresses.into()))
+        };
+        self.query.completion.complete(result);
+    }
+}
+
+enum Command {
+    Start(u64, Active),
+    Cancel(u64),
+}
+
+/// Event loop thread polling the query descriptors of every lookup.
+struct Driver {
+    api: &'static Api,
+    commands: Mutex<Vec<Command>>,
+    wake: ::core::ffi::c_int,
+    next_id: AtomicU64,
+}
+
+/// The event loop, started by the first lookup, or `None` where the API is missing or the loop
+/// cannot start.
+fn driver() -> Option<&'static Driver> {
+    static DRIVER: OnceLock<Option<&'static Driver>> = OnceLock::new();
+    *DRIVER.get_or_init(|| {
+        let api = api()?;
+
+        let mut pipe = [0; 2];
+        if unsafe { libc::pipe(pipe.as_mut_ptr()) } != 0 {
+            return None;
+        }
+        for fd in pipe {
+            unsafe {
+                libc::fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC);
+                libc::fcntl(fd, libc::F_SETFL, libc::O_NONBLOCK);
+            }
+        }
+
+        let driver: &'static Driver = Box::leak(Box::new(Driver {
+            api,
+            commands: Mutex::new(Vec::new()),
+            wake: pipe[1],
+            next_id: AtomicU64::new(0),
+        }));
+        let spawned = thread::Builder::new()
+            .name("to_socket_addrs_timeout-android".into())
+            .spawn(move || driver.run(pipe[0]));
+        if spawned.is_err() {
+            // the closure never ran and is gone
+            unsafe {
+                libc::close(pipe[0]);
+                libc::close(pipe[1]);
+                drop(Box::from_raw(driver as *const Driver as *mut Driver));
+            }
+            return None;
+        }
+        Some(driver)
+    })
+}
+
+impl Driver {
+    fn submit(&self, command: Command) {
+        self.commands
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(command);
+        _ = unsafe { libc::write(self.wake, [0u8].as_ptr().cast(), 1) };
+    }
+
+    /// Sends a query of each of `qtypes` for `name` on `network`.
+    fn start(
+        &'static self,
+        network: net_handle_t,
+        name: &CString,
+        qtypes: &[u16],
+        port: u16,
+    ) -> io::Result<Lookup> {
+        let mut fds = Vec::with_capacity(qtypes.len());
+        for &qtype in qtypes {
+            let fd =
+                unsafe { (self.api.nquery)(network, name.as_ptr(), CLASS_IN as _, qtype as _, 0) };
+            if fd < 0 {
+                for (fd, _) in fds {
+                    unsafe { (self.api.cancel)(fd) };
+                }
+                return Err(io::Error::from_raw_os_error(-fd));
+            }
+            fds.push((fd, qtype));
+        }
+
+        let query = Arc::new(Query {
+            port,
+            completion: Completion::new(),
+        });
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.submit(Command::Start(
+            id,
+            Active {
+                query: query.clone(),
+                answers: qtypes.iter().map(|&qtype| (qtype, None)).collect(),
+                fds,
+            },
+        ));
+        Ok(Lookup(Inner::Native {
+            driver: self,
+            query,
+            id,
+            done: false,
+        }))
+    }
+
+    fn run(&self, wake: ::core::ffi::c_int) {
+        let mut active: HashMap<u64, Active> = HashMap::new();
+        let mut fds: Vec<libc::pollfd> = Vec::new();
+        let mut owners: Vec<u64> = Vec::new();
+        let mut buf = vec![0u8; MAX_ANSWER];
+
+        loop {
+            let commands =
+                core::mem::take(&mut *self.commands.lock().unwrap_or_else(|e| e.into_inner()));
+            for command in commands {
+                match command {
+                    Command::Start(id, query) => {
+                        active.insert(id, query);
+                    }
+                    Command::Cancel(id) => {
+                        if let Some(query) = active.remove(&id) {
+                            for (fd, _) in query.fds {
+                                unsafe { (self.api.cancel)(fd) };
+                            }
+                        }
+                    }
+                }
+            }
+
+            fds.clear();
+            owners.clear();
+            fds.push(libc::pollfd {
+                fd: wake,
+                events: libc::POLLIN,
+                revents: 0,
+            });
+            owners.push(u64::MAX);
+            for (&id, query) in &active {
+                for &(fd, _) in &query.fds {
+                    fds.push(libc::pollfd {
+                        fd,
+                        events: libc::POLLIN,
+                        revents: 0,
+                    });
+                    owners.push(id);
+                }
+            }
+
+            if unsafe { libc::poll(fds.as_mut_ptr(), fds.len() as _, -1) } < 0 {
+                continue;
+            }
+
+            if fds[0].revents != 0 {
+                let mut buf = [0u8; 64];
+                while unsafe { libc::read(wake, buf.as_mut_ptr().cast(), buf.len()) } > 0 {}
+            }
+            for (pollfd, id) in fds.iter().zip(&owners).skip(1) {
+                if pollfd.revents == 0 {
+                    continue;
+                }
+                let Some(query) = active.get_mut(id) else {
+                    continue;
+                };
+                let Some(pos) = query.fds.iter().position(|&(fd, _)| fd == pollfd.fd) else {
+                    continue;
+                };
+                let (fd, qtype) = query.fds.swap_remove(pos);
+
+                // reading the result also closes the descriptor
+                let mut rcode = 0;
+                let len =
+                    unsafe { (self.api.nresult)(fd, &mut rcode, buf.as_mut_ptr(), buf.len()) };
+                let answer = if len < 0 {
+                    Err(io::Error::from_raw_os_error(-len))
+                } else {
+                    parse_answer(&buf[..len as usize], rcode, qtype)
+                };
+                if let Some((_, slot)) = query.answers.iter_mut().find(|(t, _)| *t == qtype) {
+                    *slot = Some(answer);
+                }
+
+                if query.fds.is_empty() {
+                    if let Some(query) = active.remove(id) {
+                        query.finish();
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// The addresses of type `qtype` in an answer.
+fn parse_answer(msg: &[u8], rcode: ::core::ffi::c_int, qtype: u16) -> io::Result<Vec<IpAddr>> {
+    match Rcode(rcode as u8) {
+        Rcode::NoError => (),
+        rcode => return Err(rcode.into()),
+    }
+    let message = Message::parse(msg)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "invalid DNS response"))?;
+    Ok(message
+        .answers
+        .iter()
+        .filter(|record| record.rtype == qtype)
+        .filter_map(Record::ip)
+        .collect())
+}
+
+/// In-flight lookup, its queries canceled when dropped.
+pub(crate) struct Lookup(Inner);
+
+enum Inner {
+    Native {
+        driver: &'static Driver,
+        query: Arc<Query>,
+        id: u64,
+        done: bool,
+    },
+    /// Answered from the hosts file.
+    Ready(Option<io::Result<std::vec::IntoIter<SocketAddr>>>),
+    /// Before API level 29.
+    Thread(fallback::Lookup),
+}
+
+impl Future for Lookup {
+    type Output = io::Result<std::vec::IntoIter<SocketAddr>>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match &mut self.get_mut().0 {
+            Inner::Native { query, done, .. } => {
+                let res = ready!(query.completion.poll(cx));
+                *done = true;
+                Poll::Ready(res)
+            }
+            Inner::Ready(res) => Poll::Ready(res.take().expect("polled after completion")),
+            Inner::Thread(lookup) => Pin::new(lookup).poll(cx),
+        }
+    }
+}
+
+impl Drop for Lookup {
+    fn drop(&mut self) {
+        if let Inner::Native {
+            driver,
+            id,
+            done: false,
+            ..
+        } = self.0
+        {
+            driver.submit(Command::Cancel(id));
+        }
+    }
+}
+
+fn hostname_to_cstring(hostname: &str) -> io::Result<CString> {
+    CString::new(hostname).map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "host name contained an unexpected NUL byte",
+        )
+    })
+}
+
+/// Starts one lookup per entry of `split`, each querying the given record types.
+fn lookup_on(
+    network: net_handle_t,
+    hostname: &str,
+    port: u16,
+    split: &[&[u16]],
+) -> io::Result<Vec<Lookup>> {
+    let listed = hosts::system();
+    let listed = listed.lookup(hostname);
+    if !listed.is_empty() {
+        let addrs: Vec<_> = listed.iter().map(|&ip| SocketAddr::new(ip, port)).collect();
+        return Ok(vec![Lookup(Inner::Ready(Some(Ok(addrs.into_iter()))))]);
+    }
+
+    let Some(driver) = driver() else {
+        let lookups = if split.len() > 1 {
+            fallback::lookup_split(hostname, port, None)?
+        } else {
+            vec![fallback::lookup(hostname, port, None)?]
+        };
+        return Ok(lookups
+            .into_iter()
+            .map(|lookup| Lookup(Inner::Thread(lookup)))
+            .collect());
+    };
+    let name = hostname_to_cstring(hostname)?;
+    split
+        .iter()
+        .map(|qtypes| driver.start(network, &name, qtypes, port))
+        .collect()
+}
+
+pub(crate) fn lookup(hostname: &str, port: u16, _timeout: Option<Duration>) -> io::Result<Lookup> {
+    let mut lookups = lookup_on(NETWORK_UNSPECIFIED, hostname, port, &[&[TYPE_AAAA, TYPE_A]])?;
+    Ok(lookups.remove(0))
+}
+
+/// Starts separate IPv6 and IPv4 lookups.
+pub(crate) fn lookup_split(
+    hostname: &str,
+    port: u16,
+    _timeout: Option<Duration>,
+) -> io::Result<Vec<Lookup>> {
+    lookup_on(
+        NETWORK_UNSPECIFIED,
+        hostname,
+        port,
+        &[&[TYPE_AAAA], &[TYPE_A]],
+    )
+}
+
+fn resolve_on(
+    network: net_handle_t,
+    host: &str,
+    port: u16,
+    timeout: Duration,
+) -> io::Result<std::vec::IntoIter<SocketAddr>> {
+    if api().is_none() {
+        return fallback::SystemResolver.resolve(host, port, timeout);
+    }
+    let mut lookup = lookup_on(network, host, port, &[&[TYPE_AAAA, TYPE_A]])?.remove(0);
+    block_on_deadline(&mut lookup, crate::deadline_after(timeout))
+        .unwrap_or_else(|| Err(io::ErrorKind::TimedOut.into()))
+}
+
+/// The Android resolver: `android_res_nquery` on the default network, canceled on timeout.
+///
+/// Before API level 29 lookups run `getaddrinfo` on a pool of worker threads instead.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemResolver;
+
+impl ResolverBackend for SystemResolver {
+    fn resolve(
+        &self,
+        host: &str,
+        port: u16,
+        timeout: Duration,
+    ) -> io::Result<std::vec::IntoIter<SocketAddr>> {
+        resolve_on(NETWORK_UNSPECIFIED, host, port, timeout)
+    }
+}
+
+/// Resolver bound to a specific network, like [`SystemResolver`] on the default one.
+///
+/// Queries fall back to the default network before API level 29.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NetworkResolver {
+    network: net_handle_t,
+}
+
+impl NetworkResolver {
+    /// Resolver for the network identified by `handle`, as returned by
+    /// `android.net.Network.getNetworkHandle()`.
+    pub const fn new(handle: u64) -> Self {
+        Self { network: handle }
+    }
+
+    /// The network handle.
+    pub const fn handle(&self) -> u64 {
+        self.network
+    }
+}
+
+impl ResolverBackend for NetworkResolver {
+    fn resolve(
+        &self,
+        host: &str,
+        port: u16,
+        timeout: Duration,
+    ) -> io::Result<std::vec::IntoIter<SocketAddr>> {
+        resolve_on(self.network, host, port, timeout)
+    }
+}
@@ -0,0 +1,387 @@
+use std::{
+    future::Future,
+    net::{Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6},
+    pin::Pin,
+    sync::Arc,
+    task::{Context as TaskContext, Poll},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{completion::Completion, ResolveError, ResolverBackend};
+
+#[allow(non_camel_case_types)]
+type DNSServiceRef = *mut ::core::ffi::c_void;
+
+#[allow(non_camel_case_types)]
+type DNSServiceGetAddrInfoReply = unsafe extern "C" fn(
+    sd_ref: DNSServiceRef,
+    flags: u32,
+    interface_index: u32,
+    error_code: DNSServiceError,
+    hostname: *const ::core::ffi::c_char,
+    address: *const libc::sockaddr,
+    ttl: u32,
+    context: *mut ::core::ffi::c_void,
+);
+
+#[link(name = "System", kind = "dylib")]
+extern "C" {
+    fn DNSServiceGetAddrInfo(
+        sd_ref: *mut DNSServiceRef,
+        flags: u32,
+        interface_index: u32,
+        protocol: u32,
+        hostname: *const ::core::ffi::c_char,
+        callback: DNSServiceGetAddrInfoReply,
+        context: *mut ::core::ffi::c_void,
+    ) -> DNSServiceError;
+
+    fn DNSServiceRefSockFD(sd_ref: DNSServiceRef) -> ::core::ffi::c_int;
+
+    fn DNSServiceProcessResult(sd_ref: DNSServiceRef) -> DNSServiceError;
+
+    fn DNSServiceRefDeallocate(sd_ref: DNSServiceRef);
+}
+
+const K_DNS_SERVICE_FLAGS_MORE_COMING: u32 = 0x1;
+const K_DNS_SERVICE_FLAGS_TIMEOUT: u32 = 0x10000;
+const K_DNS_SERVICE_PROTOCOL_IPV4: u32 = 0x1;
+const K_DNS_SERVICE_PROTOCOL_IPV6: u32 = 0x2;
+
+#[repr(transparent)]
+#[must_use]
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct DNSServiceError(i32);
+
+#[allow(non_upper_case_globals)]
+impl DNSServiceError {
+    /// Success.
+    pub const NoError: Self = Self(0);
+    /// Unexpected failure.
+    pub const Unknown: Self = Self(-65537);
+    /// Name does not exist.
+    pub const NoSuchName: Self = Self(-65538);
+    /// Memory allocation failure.
+    pub const NoMemory: Self = Self(-65539);
+    /// Invalid argument.
+    pub const BadParam: Self = Self(-65540);
+    /// Background daemon not running.
+    pub const ServiceNotRunning: Self = Self(-65563);
+    /// Name exists but has no record of the requested type.
+    pub const NoSuchRecord: Self = Self(-65554);
+    /// Connection to the daemon was closed.
+    pub const DefunctConnection: Self = Self(-65569);
+    /// Query timed out.
+    pub const Timeout: Self = Self(-65568);
+}
+
+impl core::fmt::Display for DNSServiceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            Self::NoError => f.write_str("no error"),
+            Self::NoSuchName => f.write_str("name does not exist"),
+            Self::NoMemory => f.write_str("out of memory"),
+            Self::BadParam => f.write_str("invalid parameter"),
+            Self::ServiceNotRunning => f.write_str("mDNSResponder is not running"),
+            Self::NoSuchRecord => f.write_str("no address associated with name"),
+            Self::DefunctConnection => f.write_str("connection to mDNSResponder lost"),
+            Self::Timeout => f.write_str("operation timed out"),
+            _ => write!(f, "unknown error ({})", self.0),
+        }
+    }
+}
+
+impl core::fmt::Debug for DNSServiceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            Self::NoError => f.write_str("DNSServiceError::NoError"),
+            Self::Unknown => f.write_str("DNSServiceError::Unknown"),
+            Self::NoSuchName => f.write_str("DNSServiceError::NoSuchName"),
+            Self::NoMemory => f.write_str("DNSServiceError::NoMemory"),
+            Self::BadParam => f.write_str("DNSServiceError::BadParam"),
+            Self::ServiceNotRunning => f.write_str("DNSServiceError::ServiceNotRunning"),
+            Self::NoSuchRecord => f.write_str("DNSServiceError::NoSuchRecord"),
+            Self::DefunctConnection => f.write_str("DNSServiceError::DefunctConnection"),
+            Self::Timeout => f.write_str("DNSServiceError::Timeout"),
+            _ => f.write_str("DNSServiceError::Unknown"),
+        }
+    }
+}
+
+impl std::error::Error for DNSServiceError {}
+
+impl From<DNSServiceError> for std::io::Error {
+    fn from(value: DNSServiceError) -> Self {
+        use std::io::ErrorKind;
+
+        match value {
+            DNSServiceError::Timeout => ErrorKind::TimedOut.into(),
+            DNSServiceError::NoSuchName => ResolveError::HostNotFound.into(),
+            DNSServiceError::NoSuchRecord => ResolveError::NoAddresses.into(),
+            DNSServiceError::ServiceNotRunning | DNSServiceError::DefunctConnection => {
+                ResolveError::TemporaryFailure.into()
+            }
+            DNSServiceError::BadParam => std::io::Error::new(ErrorKind::InvalidInput, value),
+            DNSServiceError::NoMemory => std::io::Error::new(ErrorKind::OutOfMemory, value),
+            _ => std::io::Error::other(value),
+        }
+    }
+}
+
+fn sockaddr_to_addr(addr: *const libc::sockaddr, port: u16) -> Option<SocketAddr> {
+    let addr = unsafe { addr.as_ref()? };
+    match addr.sa_family as ::core::ffi::c_int {
+        libc::AF_INET => {
+            let addr = unsafe { &*(addr as *const libc::sockaddr as *const libc::sockaddr_in) };
+            Some(SocketAddr::V4(SocketAddrV4::new(
+                Ipv4Addr::from(addr.sin_addr.s_addr.to_ne_bytes()),
+                port,
+            )))
+        }
+        libc::AF_INET6 => {
+            let addr = unsafe { &*(addr as *const libc::sockaddr as *const libc::sockaddr_in6) };
+            Some(SocketAddr::V6(SocketAddrV6::new(
+                Ipv6Addr::from(addr.sin6_addr.s6_addr),
+                port,
+                addr.sin6_flowinfo,
+                addr.sin6_scope_id,
+            )))
+        }
+        _ => None,
+    }
+}
+
+/// Reply state shared with `query_callback`.
+struct Context {
+    port: u16,
+    addrs: Vec<SocketAddr>,
+    v4_done: bool,
+    v6_done: bool,
+    more_coming: bool,
+    error: Option<DNSServiceError>,
+}
+
+impl Context {
+    fn is_done(&self) -> bool {
+        self.error.is_some() || (self.v4_done && self.v6_done && !self.more_coming)
+    }
+}
+
+unsafe extern "C" fn query_callback(
+    _sd_ref: DNSServiceRef,
+    flags: u32,
+    _interface_index: u32,
+    error_code: DNSServiceError,
+    _hostname: *const ::core::ffi::c_char,
+    address: *const libc::sockaddr,
+    _ttl: u32,
+    context: *mut ::core::ffi::c_void,
+) {
+    let ctx = &mut *context.cast::<Context>();
+    ctx.more_coming = flags & K_DNS_SERVICE_FLAGS_MORE_COMING != 0;
+
+    if error_code != DNSServiceError::NoError && error_code != DNSServiceError::NoSuchRecord {
+        ctx.error = Some(error_code);
+        return;
+    }
+
+    // negative answers still carry a sockaddr telling which family they are for
+    match address.as_ref().map(|a| a.sa_family as ::core::ffi::c_int) {
+        Some(libc::AF_INET) => ctx.v4_done = true,
+        Some(libc::AF_INET6) => ctx.v6_done = true,
+        _ => (),
+    }
+
+    if error_code == DNSServiceError::NoError {
+        if let Some(addr) = sockaddr_to_addr(address, ctx.port) {
+            ctx.addrs.push(addr);
+        }
+    }
+}
+
+struct ServiceGuard(DNSServiceRef);
+
+impl Drop for ServiceGuard {
+    fn drop(&mut self) {
+        // deallocating the reference also cancels the query in mDNSResponder
+        unsafe { DNSServiceRefDeallocate(self.0) };
+    }
+}
+
+fn getaddrinfo_timeout(
+    hostname: &::core::ffi::CStr,
+    protocol: u32,
+    port: u16,
+    timeout: Option<Duration>,
+) -> std::io::Result<Vec<SocketAddr>> {
+    let mut ctx = Context {
+        port,
+        addrs: Vec::new(),
+        v4_done: protocol & K_DNS_SERVICE_PROTOCOL_IPV4 == 0,
+        v6_done: protocol & K_DNS_SERVICE_PROTOCOL_IPV6 == 0,
+        more_coming: false,
+        error: None,
+    };
+
+    let mut sd_ref: DNSServiceRef = core::ptr::null_mut();
+    let ret = unsafe {
+        DNSServiceGetAddrInfo(
+            &mut sd_ref,
+            K_DNS_SERVICE_FLAGS_TIMEOUT,
+            0,
+            protocol,
+            hostname.as_ptr(),
+            query_callback,
+            (&mut ctx as *mut Context).cast(),
+        )
+    };
+    if ret != DNSServiceError::NoError {
+        return Err(ret.into());
+    }
+    let guard = ServiceGuard(sd_ref);
+
+    let fd = unsafe { DNSServiceRefSockFD(guard.0) };
+    if fd == -1 {
+        return Err(DNSServiceError::DefunctConnection.into());
+    }
+
+    // without a deadline the query is bounded by the daemon's own timeout
+    let end = timeout.map(crate::deadline_after);
+    while !ctx.is_done() {
+        let ms = match end {
+            Some(end) => {
+                let Some(timeout) = end.checked_duration_since(Instant::now()) else {
+                    return Err(std::io::ErrorKind::TimedOut.into());
+                };
+                timeout
+                    .as_nanos()
+                    .div_ceil(1_000_000)
+                    .min(::core::ffi::c_int::MAX as u128) as ::core::ffi::c_int
+            }
+            None => -1,
+        };
+        let mut pfd = libc::pollfd {
+            fd,
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        match unsafe { libc::poll(&mut pfd, 1, ms) } {
+            -1 => {
+                let err = std::io::Error::last_os_error();
+                if err.kind() != std::io::ErrorKind::Interrupted {
+                    return Err(err);
+                }
+            }
+            0 => (),
+            _ => {
+                let ret = unsafe { DNSServiceProcessResult(guard.0) };
+                if ret != DNSServiceError::NoError {
+                    return Err(ret.into());
+                }
+            }
+        }
+    }
+    drop(guard);
+
+    match ctx.error {
+        Some(err) => Err(err.into()),
+        None if ctx.addrs.is_empty() => Err(DNSServiceError::NoSuchRecord.into()),
+        None => Ok(ctx.addrs),
+    }
+}
+
+fn resolve_timeout(
+    hostname: &str,
+    port: u16,
+    timeout: Duration,
+) -> std::io::Result<std::vec::IntoIter<SocketAddr>> {
+    let hostname = hostname_to_cstring(hostname)?;
+
+    Ok(getaddrinfo_timeout(
+        &hostname,
+        K_DNS_SERVICE_PROTOCOL_IPV4 | K_DNS_SERVICE_PROTOCOL_IPV6,
+        port,
+        Some(timeout),
+    )?
+    .into_iter())
+}
+
+/// In-flight lookup running the blocking query, bounded by its timeout, on a helper thread.
+pub(crate) struct Lookup(Arc<Completion<std::io::Result<std::vec::IntoIter<SocketAddr>>>>);
+
+impl Future for Lookup {
+    type Output = std::io::Result<std::vec::IntoIter<SocketAddr>>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut TaskContext<'_>) -> Poll<Self::Output> {
+        self.0.poll(cx)
+    }
+}
+
+fn start_lookup(
+    hostname: ::std::ffi::CString,
+    protocol: u32,
+    port: u16,
+    timeout: Option<Duration>,
+) -> Lookup {
+    let completion = Arc::new(Completion::new());
+    thread::spawn({
+        let completion = completion.clone();
+        move || {
+            completion.complete(
+                getaddrinfo_timeout(&hostname, protocol, port, timeout).map(Vec::into_iter),
+            )
+        }
+    });
+    Lookup(completion)
+}
+
+fn hostname_to_cstring(hostname: &str) -> std::io::Result<::std::ffi::CString> {
+    ::std::ffi::CString::new(hostname).map_err(|_| {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "host name contained an unexpected NUL byte",
+        )
+    })
+}
+
+pub(crate) fn lookup(
+    hostname: &str,
+    port: u16,
+    timeout: Option<Duration>,
+) -> std::io::Result<Lookup> {
+    Ok(start_lookup(
+        hostname_to_cstring(hostname)?,
+        K_DNS_SERVICE_PROTOCOL_IPV4 | K_DNS_SERVICE_PROTOCOL_IPV6,
+        port,
+        timeout,
+    ))
+}
+
+/// Starts separate IPv6 and IPv4 lookups.
+pub(crate) fn lookup_split(
+    hostname: &str,
+    port: u16,
+    timeout: Option<Duration>,
+) -> std::io::Result<Vec<Lookup>> {
+    let hostname = hostname_to_cstring(hostname)?;
+    Ok(vec![
+        start_lookup(hostname.clone(), K_DNS_SERVICE_PROTOCOL_IPV6, port, timeout),
+        start_lookup(hostname, K_DNS_SERVICE_PROTOCOL_IPV4, port, timeout),
+    ])
+}
+
+/// The platform resolver: `DNSServiceGetAddrInfo`, polled until the timeout expires.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemResolver;
+
+impl ResolverBackend for SystemResolver {
+    fn resolve(
+        &self,
+        host: &str,
+        port: u16,
+        timeout: Duration,
+    ) -> std::io::Result<std::vec::IntoIter<SocketAddr>> {
+        resolve_timeout(host, port, timeout)
+    }
+}
@@ -0,0 +1,92 @@
+/// How lookups are made, see [`backend`] and [`Resolver::backend`](crate::Resolver::backend).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum BackendKind {
+    /// glibc's asynchronous `getaddrinfo_a`.
+    GlibcAsync,
+    /// Android's `android_res_nquery`, polled by an event loop thread.
+    AndroidAsync,
+    /// `DNSServiceGetAddrInfo`, on Apple platforms.
+    AppleDnsService,
+    /// The c-ares library, with the `c-ares` feature.
+    CAres,
+    /// Windows' overlapped `GetAddrInfoExW`.
+    WindowsAsync,
+    /// `getaddrinfo` on a bounded pool of worker threads.
+    ThreadPool,
+    /// std's blocking lookup on a bounded pool of worker threads, abandoned on timeout.
+    ThreadFallback,
+    /// The stub resolver of this crate, querying the nameservers itself.
+    Stub,
+    /// `wasi:sockets/ip-name-lookup`.
+    Wasi,
+    /// The function registered with `set_wasm_provider` on `wasm32-unknown-unknown`.
+    Wasm,
+    /// A [`ResolverBackend`](crate::ResolverBackend) given to the resolver.
+    Custom,
+    /// None, only IP literals being accepted, see
+    /// [`ResolverBuilder::numeric_only`](crate::ResolverBuilder::numeric_only).
+    NumericOnly,
+}
+
+impl BackendKind {
+    /// The name of the backend, in snake case.
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::GlibcAsync => "glibc_async",
+            Self::AndroidAsync => "android_async",
+            Self::AppleDnsService => "apple_dns_service",
+            Self::CAres => "c_ares",
+            Self::WindowsAsync => "windows_async",
+            Self::ThreadPool => "thread_pool",
+            Self::ThreadFallback => "thread_fallback",
+            Self::Stub => "stub",
+            Self::Wasi => "wasi",
+            Self::Wasm => "wasm",
+            Self::Custom => "custom",
+            Self::NumericOnly => "numeric_only",
+        }
+    }
+}
+
+impl core::fmt::Display for BackendKind {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// The backend [`SystemResolver`](crate::SystemResolver) uses, as chosen at build time and,
+/// where the asynchronous resolver functions are looked up when first needed, at runtime.
+pub fn backend() -> BackendKind {
+    detect()
+}
+
+#[cfg(any(backend = "linux_glibc", backend = "android"))]
+fn detect() -> BackendKind {
+    match (crate::sys::has_async_api(), cfg!(backend = "android")) {
+        (false, _) => BackendKind::ThreadFallback,
+        (true, false) => BackendKind::GlibcAsync,
+        (true, true) => BackendKind::AndroidAsync,
+    }
+}
+
+#[cfg(not(any(backend = "linux_glibc", backend = "android")))]
+fn detect() -> BackendKind {
+    if cfg!(backend = "apple") {
+        BackendKind::AppleDnsService
+    } else if cfg!(backend = "c_ares") {
+        BackendKind::CAres
+    } else if cfg!(backend = "windows") {
+        BackendKind::WindowsAsync
+    } else if cfg!(any(backend = "bsd", backend = "illumos")) {
+        BackendKind::ThreadPool
+    } else if cfg!(backend = "stub") {
+        BackendKind::Stub
+    } else if cfg!(backend = "wasi") {
+        BackendKind::Wasi
+    } else if cfg!(backend = "wasm") {
+        BackendKind::Wasm
+    } else {
+        BackendKind::ThreadFallback
+    }
+}
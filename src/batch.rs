@@ -0,0 +1,112 @@
+use std::{io, time::Duration};
+
+use crate::{hostname, HostnameSyntax, ResolvedAddrs, ToSocketAddrsTimeout};
+
+/// Resolves each of `targets` with the platform resolver, their lookups running at once
+/// under a deadline `timeout` away, see
+/// [`start_lookup`](ToSocketAddrsTimeout::start_lookup).
+///
+/// Returns a result for each target, in the same order. Each target is looked up on its own,
+/// which with glibc is one `getaddrinfo_a` call per target: to batch host names and ports into
+/// a single call, use [`resolve_many`].
+pub fn resolve_all<I>(targets: I, timeout: Duration) -> Vec<io::Result<ResolvedAddrs>>
+where
+    I: IntoIterator,
+    I::Item: ToSocketAddrsTimeout,
+{
+    let deadline = crate::deadline_after(timeout);
+    let lookups: Vec<_> = targets
+        .into_iter()
+        .map(|target| crate::shutdown::check().map(|()| target.start_lookup()))
+        .collect();
+    lookups
+        .into_iter()
+        .map(|lookup| {
+            let left = deadline.saturating_duration_since(std::time::Instant::now());
+            lookup?.wait(left).map(Into::into)
+        })
+        .collect()
+}
+
+/// Resolves each of `targets` with the platform resolver, all at once rather than one after
+/// the other, giving up on those not resolved once `timeout` has elapsed.
+///
+/// Returns a result for each target, in the same order. With glibc the names are submitted
+/// together in a single `getaddrinfo_a` call, elsewhere their lookups are started together
+/// and waited for until the same deadline.
+pub fn resolve_many(targets: &[(&str, u16)], timeout: Duration) -> Vec<io::Result<ResolvedAddrs>> {
+    let deadline = crate::deadline_after(timeout);
+    let mut results: Vec<_> = targets.iter().map(|_| None).collect();
+    let mut hosts = Vec::new();
+    for (i, &(host, port)) in targets.iter().enumerate() {
+        if let Some(addr) = crate::literal(host, port) {
+            results[i] = Some(Ok(addr.into()));
+            continue;
+        }
+        let host = hostname::to_ascii(host);
+        let checked = crate::shutdown::check()
+            .and_then(|()| HostnameSyntax::Strict.check(&host).map_err(Into::into))
+            .and_then(|()| {
+                if timeout.is_zero() {
+                    Err(crate::resolver::would_block())
+                } else {
+                    Ok(())
+                }
+            });
+        match checked {
+            Ok(()) => hosts.push((i, host.into_owned(), port)),
+            Err(err) => results[i] = Some(Err(err)),
+        }
+    }
+
+    let names: Vec<_> = hosts
+        .iter()
+        .map(|(_, host, port)| (hostname::without_root(host), *port))
+        .collect();
+    for ((i, _, _), result) in hosts.iter().zip(lookup_all(&names, timeout, deadline)) {
+        results[*i] = Some(result);
+    }
+    results.into_iter().flatten().collect()
+}
+
+#[cfg(backend = "linux_glibc")]
+fn lookup_all(
+    names: &[(&str, u16)],
+    timeout: Duration,
+    deadline: std::time::Instant,
+) -> Vec<io::Result<ResolvedAddrs>> {
+    match crate::sys::resolve_many(names, timeout) {
+        Some(results) => results.into_iter().map(|r| r.map(Into::into)).collect(),
+        None => lookup_each(names, timeout, deadline),
+    }
+}
+
+#[cfg(not(backend = "linux_glibc"))]
+fn lookup_all(
+    names: &[(&str, u16)],
+    timeout: Duration,
+    deadline: std::time::Instant,
+) -> Vec<io::Result<ResolvedAddrs>> {
+    lookup_each(names, timeout, deadline)
+}
+
+/// Starts a lookup for each of `names`, then waits for them one after the other.
+fn lookup_each(
+    names: &[(&str, u16)],
+    timeout: Duration,
+    deadline: std::time::Instant,
+) -> Vec<io::Result<ResolvedAddrs>> {
+    let lookups: Vec<_> = names
+        .iter()
+        .map(|&(host, port)| crate::sys::lookup(host, port, Some(timeout)))
+        .collect();
+    lookups
+        .into_iter()
+        .map(|lookup| {
+            let mut lookup = lookup?;
+            crate::future::block_on_deadline(&mut lookup, deadline)
+                .unwrap_or_else(|| Err(io::ErrorKind::TimedOut.into()))
+                .map(Into::into)
+        })
+        .collect()
+}
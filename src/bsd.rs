@@ -0,0 +1,124 @@
+use std::{
+    future::Future,
+    net::{SocketAddr, ToSocketAddrs},
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU8, Ordering},
+        Arc, OnceLock,
+    },
+    task::{ready, Context, Poll},
+    time::Duration,
+};
+
+use crate::{
+    completion::Completion, future::block_on_deadline, hostaliases, orphan::Orphan, pool::Pool,
+    ResolverBackend,
+};
+
+static POOL: Pool = Pool::new("to_socket_addrs_timeout-resolver");
+
+const QUEUED: u8 = 0;
+const RUNNING: u8 = 1;
+const CANCELED: u8 = 2;
+
+/// State shared with the worker running the job, which holds a reference until it returns.
+struct Job {
+    host: String,
+    port: u16,
+    state: AtomicU8,
+    completion: Completion<std::io::Result<std::vec::IntoIter<SocketAddr>>>,
+    orphan: OnceLock<Orphan>,
+}
+
+impl Job {
+    fn run(&self) {
+        if self
+            .state
+            .compare_exchange(QUEUED, RUNNING, Ordering::AcqRel, Ordering::Acquire)
+            .is_ok()
+        {
+            self.completion
+                .complete((self.host.as_str(), self.port).to_socket_addrs());
+        }
+    }
+}
+
+/// Lookup queued on the worker pool.
+///
+/// Dropping it before a worker picks it up removes it from the pool's work, once running it can
+/// only be abandoned.
+pub(crate) struct Lookup {
+    job: Arc<Job>,
+    done: bool,
+}
+
+impl Future for Lookup {
+    type Output = std::io::Result<std::vec::IntoIter<SocketAddr>>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let res = ready!(self.job.completion.poll(cx));
+        self.done = true;
+        Poll::Ready(res)
+    }
+}
+
+impl Drop for Lookup {
+    fn drop(&mut self) {
+        if !self.done
+            && self
+                .job
+                .state
+                .compare_exchange(QUEUED, CANCELED, Ordering::AcqRel, Ordering::Acquire)
+                .is_err()
+        {
+            // the worker releases the orphan when getaddrinfo returns
+            self.job.orphan.get_or_init(Orphan::new);
+        }
+    }
+}
+
+pub(crate) fn lookup(host: &str, port: u16, timeout: Option<Duration>) -> std::io::Result<Lookup> {
+    let job = Arc::new(Job {
+        host: hostaliases::resolve(host).unwrap_or_else(|| host.to_owned()),
+        port,
+        state: AtomicU8::new(QUEUED),
+        completion: Completion::new(),
+        orphan: OnceLock::new(),
+    });
+    POOL.execute(
+        {
+            let job = job.clone();
+            move || job.run()
+        },
+        timeout,
+    )?;
+    Ok(Lookup { job, done: false })
+}
+
+/// `ToSocketAddrs` cannot select an address family, so there is nothing to split.
+pub(crate) fn lookup_split(
+    host: &str,
+    port: u16,
+    timeout: Option<Duration>,
+) -> std::io::Result<Vec<Lookup>> {
+    Ok(vec![lookup(host, port, timeout)?])
+}
+
+/// The platform resolver: `getaddrinfo` on a bounded pool of worker threads, abandoned on
+/// timeout.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemResolver;
+
+impl ResolverBackend for SystemResolver {
+    fn resolve(
+        &self,
+        host: &str,
+        port: u16,
+        timeout: Duration,
+    ) -> std::io::Result<std::vec::IntoIter<SocketAddr>> {
+        let deadline = crate::deadline_after(timeout);
+        let mut lookup = lookup(host, port, Some(timeout))?;
+        block_on_deadline(&mut lookup, deadline)
+            .unwrap_or_else(|| Err(std::io::ErrorKind::TimedOut.into()))
+    }
+}
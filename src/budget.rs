@@ -0,0 +1,54 @@
+use std::{
+    io,
+    time::{Duration, Instant},
+};
+
+/// A time budget shared by consecutive steps, such as resolving a host then connecting to it,
+/// each getting what the previous ones left.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Budget {
+    deadline: Instant,
+}
+
+impl Budget {
+    /// A budget of `total` from now, [`NO_TIMEOUT`](crate::NO_TIMEOUT) for one never spent.
+    pub fn new(total: Duration) -> Self {
+        Self::until(crate::deadline_after(total))
+    }
+
+    /// A budget spent at `deadline`.
+    pub fn until(deadline: Instant) -> Self {
+        Self { deadline }
+    }
+
+    /// When the budget is spent.
+    pub fn deadline(&self) -> Instant {
+        self.deadline
+    }
+
+    /// The time left, zero once spent.
+    pub fn remaining(&self) -> Duration {
+        self.deadline.saturating_duration_since(Instant::now())
+    }
+
+    /// Whether no time is left.
+    pub fn is_spent(&self) -> bool {
+        self.remaining().is_zero()
+    }
+
+    /// Runs `step` with the time left as its timeout.
+    ///
+    /// Once the budget is spent the step gets a zero timeout, so that whatever needs no waiting
+    /// is still answered, and [`WouldBlock`](io::ErrorKind::WouldBlock) is reported as
+    /// [`TimedOut`](io::ErrorKind::TimedOut).
+    pub(crate) fn spend<T>(&self, step: impl FnOnce(Duration) -> io::Result<T>) -> io::Result<T> {
+        let timeout = self.remaining();
+        step(timeout).map_err(|err| {
+            if timeout.is_zero() && err.kind() == io::ErrorKind::WouldBlock {
+                io::ErrorKind::TimedOut.into()
+            } else {
+                err
+            }
+        })
+    }
+}
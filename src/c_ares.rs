@@ -0,0 +1,680 @@
+use std::{
+    collections::HashMap,
+    ffi::{CStr, CString},
+    future::Future,
+    net::{Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6},
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex, OnceLock,
+    },
+    task::{ready, Context as TaskContext, Poll},
+    thread,
+    time::Duration,
+};
+
+use crate::{
+    completion::Completion, future::block_on_deadline, CanonicalAddrs, Family, HintFlags, Hints,
+    ResolveError, ResolvedAddr, ResolverBackend,
+};
+
+#[allow(non_camel_case_types)]
+type ares_channel = *mut ::core::ffi::c_void;
+
+#[allow(non_camel_case_types)]
+type ares_socket_t = ::core::ffi::c_int;
+
+#[allow(non_camel_case_types)]
+type ares_addrinfo_callback = unsafe extern "C" fn(
+    arg: *mut ::core::ffi::c_void,
+    status: AresError,
+    timeouts: ::core::ffi::c_int,
+    res: *mut ares_addrinfo,
+);
+
+const ARES_AI_CANONNAME: ::core::ffi::c_int = 1 << 0;
+const ARES_AI_NUMERICHOST: ::core::ffi::c_int = 1 << 1;
+const ARES_AI_PASSIVE: ::core::ffi::c_int = 1 << 2;
+const ARES_AI_NUMERICSERV: ::core::ffi::c_int = 1 << 3;
+const ARES_AI_V4MAPPED: ::core::ffi::c_int = 1 << 4;
+const ARES_AI_ALL: ::core::ffi::c_int = 1 << 5;
+const ARES_AI_ADDRCONFIG: ::core::ffi::c_int = 1 << 6;
+
+#[repr(C)]
+#[allow(non_camel_case_types)]
+#[derive(Clone, Copy)]
+struct ares_addrinfo_hints {
+    pub ai_flags: ::core::ffi::c_int,
+    pub ai_family: ::core::ffi::c_int,
+    pub ai_socktype: ::core::ffi::c_int,
+    pub ai_protocol: ::core::ffi::c_int,
+}
+
+#[repr(C)]
+#[allow(non_camel_case_types)]
+struct ares_addrinfo_node {
+    pub ai_ttl: ::core::ffi::c_int,
+    pub ai_flags: ::core::ffi::c_int,
+    pub ai_family: ::core::ffi::c_int,
+    pub ai_socktype: ::core::ffi::c_int,
+    pub ai_protocol: ::core::ffi::c_int,
+    pub ai_addrlen: libc::socklen_t,
+    pub ai_addr: *mut libc::sockaddr,
+    pub ai_next: *mut ares_addrinfo_node,
+}
+
+#[repr(C)]
+#[allow(non_camel_case_types)]
+struct ares_addrinfo {
+    pub cnames: *mut ::core::ffi::c_void,
+    pub nodes: *mut ares_addrinfo_node,
+    pub name: *mut ::core::ffi::c_char,
+}
+
+#[link(name = "cares")]
+extern "C" {
+    fn ares_library_init(flags: ::core::ffi::c_int) -> AresError;
+
+    fn ares_init(channel: *mut ares_channel) -> AresError;
+
+    fn ares_dup(dest: *mut ares_channel, src: ares_channel) -> AresError;
+
+    fn ares_destroy(channel: ares_channel);
+
+    fn ares_getaddrinfo(
+        channel: ares_channel,
+        name: *const ::core::ffi::c_char,
+        service: *const ::core::ffi::c_char,
+        hints: *const ares_addrinfo_hints,
+        callback: ares_addrinfo_callback,
+        arg: *mut ::core::ffi::c_void,
+    );
+
+    fn ares_freeaddrinfo(ai: *mut ares_addrinfo);
+
+    fn ares_getsock(
+        channel: ares_channel,
+        socks: *mut ares_socket_t,
+        numsocks: ::core::ffi::c_int,
+    ) -> ::core::ffi::c_int;
+
+    fn ares_timeout(
+        channel: ares_channel,
+        maxtv: *mut libc::timeval,
+        tv: *mut libc::timeval,
+    ) -> *mut libc::timeval;
+
+    fn ares_process_fd(channel: ares_channel, read_fd: ares_socket_t, write_fd: ares_socket_t);
+
+    fn ares_strerror(code: ::core::ffi::c_int) -> *const ::core::ffi::c_char;
+}
+
+const ARES_LIB_INIT_ALL: ::core::ffi::c_int = 1;
+const ARES_SOCKET_BAD: ares_socket_t = -1;
+const ARES_GETSOCK_MAXNUM: usize = 16;
+
+#[repr(transparent)]
+#[must_use]
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct AresError(::core::ffi::c_int);
+
+#[allow(non_upper_case_globals)]
+impl AresError {
+    /// Success.
+    pub const Success: Self = Self(0);
+    /// Name exists but has no address of the requested family.
+    pub const NoData: Self = Self(1);
+    /// Server claims the query was malformed.
+    pub const FormErr: Self = Self(2);
+    /// Server returned a general failure.
+    pub const ServFail: Self = Self(3);
+    /// Name does not exist.
+    pub const NotFound: Self = Self(4);
+    /// Server refused to answer.
+    pub const Refused: Self = Self(6);
+    /// Misformatted host name.
+    pub const BadName: Self = Self(8);
+    /// Unsupported address family.
+    pub const BadFamily: Self = Self(9);
+    /// Misformatted response.
+    pub const BadResp: Self = Self(10);
+    /// Could not contact any server.
+    pub const ConnRefused: Self = Self(11);
+    /// No answer within the configured timeouts.
+    pub const Timeout: Self = Self(12);
+    /// Memory allocation failure.
+    pub const NoMem: Self = Self(15);
+    /// Channel destroyed while the query was pending.
+    pub const Destruction: Self = Self(16);
+    /// Query canceled.
+    pub const Cancelled: Self = Self(24);
+}
+
+impl core::fmt::Display for AresError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        unsafe {
+            let ptr = ares_strerror(self.0);
+            if ptr.is_null() {
+                f.write_str("unknown")
+            } else {
+                core::fmt::Display::fmt(&::core::ffi::CStr::from_ptr(ptr).to_string_lossy(), f)
+            }
+        }
+    }
+}
+
+impl core::fmt::Debug for AresError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            Self::Success => f.write_str("AresError::Success"),
+            Self::NoData => f.write_str("AresError::NoData"),
+            Self::FormErr => f.write_str("AresError::FormErr"),
+            Self::ServFail => f.write_str("AresError::ServFail"),
+            Self::NotFound => f.write_str("AresError::NotFound"),
+            Self::Refused => f.write_str("AresError::Refused"),
+            Self::BadName => f.write_str("AresError::BadName"),
+            Self::BadFamily => f.write_str("AresError::BadFamily"),
+            Self::BadResp => f.write_str("AresError::BadResp"),
+            Self::ConnRefused => f.write_str("AresError::ConnRefused"),
+            Self::Timeout => f.write_str("AresError::Timeout"),
+            Self::NoMem => f.write_str("AresError::NoMem"),
+            Self::Destruction => f.write_str("AresError::Destruction"),
+            Self::Cancelled => f.write_str("AresError::Cancelled"),
+            _ => f.write_str("AresError::Unknown"),
+        }
+    }
+}
+
+impl std::error::Error for AresError {}
+
+impl From<AresError> for std::io::Error {
+    fn from(value: AresError) -> Self {
+        use std::io::ErrorKind;
+
+        match value {
+            AresError::Timeout => ErrorKind::TimedOut.into(),
+            AresError::NotFound => ResolveError::HostNotFound.into(),
+            AresError::NoData => ResolveError::NoAddresses.into(),
+            AresError::ServFail | AresError::ConnRefused => ResolveError::TemporaryFailure.into(),
+            AresError::BadName => std::io::Error::new(ErrorKind::InvalidInput, value),
+            AresError::BadFamily => std::io::Error::new(ErrorKind::Unsupported, value),
+            AresError::NoMem => std::io::Error::new(ErrorKind::OutOfMemory, value),
+            AresError::Destruction | AresError::Cancelled => {
+                std::io::Error::new(ErrorKind::Interrupted, value)
+            }
+            _ => std::io::Error::other(value),
+        }
+    }
+}
+
+fn sockaddr_to_addr(node: &ares_addrinfo_node, port: u16) -> Option<SocketAddr> {
+    let addr = unsafe { node.ai_addr.as_ref()? };
+    let len = node.ai_addrlen as usize;
+    match addr.sa_family as ::core::ffi::c_int {
+        libc::AF_INET if len >= core::mem::size_of::<libc::sockaddr_in>() => {
+            let addr = unsafe { &*(addr as *const libc::sockaddr as *const libc::sockaddr_in) };
+            Some(SocketAddr::V4(SocketAddrV4::new(
+                Ipv4Addr::from(addr.sin_addr.s_addr.to_ne_bytes()),
+                port,
+            )))
+        }
+        libc::AF_INET6 if len >= core::mem::size_of::<libc::sockaddr_in6>() => {
+            let addr = unsafe { &*(addr as *const libc::sockaddr as *const libc::sockaddr_in6) };
+            Some(SocketAddr::V6(SocketAddrV6::new(
+                Ipv6Addr::from(addr.sin6_addr.s6_addr),
+                port,
+                addr.sin6_flowinfo,
+                addr.sin6_scope_id,
+            )))
+        }
+        _ => None,
+    }
+}
+
+/// An initialized `ares_channel`, destroyed on drop.
+///
+/// Destroying a channel fails its pending queries with `ARES_EDESTRUCTION`.
+struct Channel(ares_channel);
+
+unsafe impl Send for Channel {}
+
+impl Drop for Channel {
+    fn drop(&mut self) {
+        unsafe { ares_destroy(self.0) };
+    }
+}
+
+/// Query state shared between a [`Lookup`] and the event loop, whose `ares_getaddrinfo`
+/// callback owns a reference to it until it runs.
+struct Query {
+    hostname: CString,
+    hints: ares_addrinfo_hints,
+    port: u16,
+    finished: AtomicBool,
+    completion: Completion<std::io::Result<Answer>>,
+}
+
+/// The addresses found, with their TTLs, and the canonical name.
+struct Answer {
+    name: String,
+    addrs: Vec<ResolvedAddr>,
+}
+
+unsafe extern "C" fn addrinfo_callback(
+    arg: *mut ::core::ffi::c_void,
+    status: AresError,
+    _timeouts: ::core::ffi::c_int,
+    res: *mut ares_addrinfo,
+) {
+    let query = Arc::from_raw(arg as *const Query);
+
+    let result = if status == AresError::Success {
+        let mut addrs = Vec::new();
+        let mut node = res.as_ref().map_or(core::ptr::null_mut(), |res| res.nodes);
+        while let Some(cur) = node.as_ref() {
+            addrs.extend(sockaddr_to_addr(cur, query.port).map(|addr| ResolvedAddr {
+                addr,
+                ttl: Some(Duration::from_secs(cur.ai_ttl.max(0) as u64)),
+            }));
+            node = cur.ai_next;
+        }
+        // the canonical name when asked for with ARES_AI_CANONNAME
+        let name = res
+            .as_ref()
+            .and_then(|res| res.name.as_ref())
+            .map(|name| CStr::from_ptr(name))
+            .unwrap_or(&query.hostname)
+            .to_string_lossy()
+            .into_owned();
+        Ok(Answer { name, addrs })
+    } else {
+        Err(status.into())
+    };
+    if !res.is_null() {
+        ares_freeaddrinfo(res);
+    }
+
+    query.finished.store(true, Ordering::Release);
+    query.completion.complete(result);
+}
+
+enum Command {
+    Start(u64, Arc<Query>),
+    Cancel(u64),
+}
+
+/// Event loop thread driving every query, each on its own channel so it can be canceled alone.
+struct Driver {
+    commands: Mutex<Vec<Command>>,
+    wake: ::core::ffi::c_int,
+    next_id: AtomicU64,
+}
+
+static DRIVER: OnceLock<Result<&'static Driver, std::io::Error>> = OnceLock::new();
+
+/// The event loop, started by the first lookup; should it fail to start, every lookup fails
+/// with the same error.
+fn driver() -> std::io::Result<&'static Driver> {
+    let driver = DRIVER.get_or_init(|| {
+        let mut template = core::ptr::null_mut();
+        unsafe {
+            let ret = ares_library_init(ARES_LIB_INIT_ALL);
+            if ret != AresError::Success {
+                return Err(ret.into());
+            }
+            let ret = ares_init(&mut template);
+            if ret != AresError::Success {
+                return Err(ret.into());
+            }
+        }
+        let template = Channel(template);
+
+        let mut pipe = [0; 2];
+        if unsafe { libc::pipe(pipe.as_mut_ptr()) } != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        for fd in pipe {
+            unsafe {
+                libc::fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC);
+                libc::fcntl(fd, libc::F_SETFL, libc::O_NONBLOCK);
+            }
+        }
+
+        let driver: &'static Driver = Box::leak(Box::new(Driver {
+            commands: Mutex::new(Vec::new()),
+            wake: pipe[1],
+            next_id: AtomicU64::new(0),
+        }));
+        let spawned = thread::Builder::new()
+            .name("to_socket_addrs_timeout-c-ares".into())
+            .spawn(move || driver.run(template, pipe[0]));
+        if let Err(err) = spawned {
+            // the closure never ran and is gone, its template channel destroyed with it
+            unsafe {
+                libc::close(pipe[0]);
+                libc::close(pipe[1]);
+                drop(Box::from_raw(driver as *const Driver as *mut Driver));
+            }
+            return Err(err);
+        }
+        Ok(driver)
+    });
+    match driver {
+        Ok(driver) => Ok(driver),
+        Err(err) => Err(copy_error(err)),
+    }
+}
+
+/// A copy of the error the event loop failed to start with, for another lookup.
+fn copy_error(err: &std::io::Error) -> std::io::Error {
+    if let Some(code) = err.raw_os_error() {
+        return std::io::Error::from_raw_os_error(code);
+    }
+    match err
+        .get_ref()
+        .and_then(|inner| inner.downcast_ref::<ResolveError>())
+        .and_then(ResolveError::duplicate)
+    {
+        Some(resolve) => resolve.into(),
+        None => std::io::Error::new(err.kind(), err.to_string()),
+    }
+}
+
+impl Driver {
+    fn submit(&self, command: Command) {
+        self.commands
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(command);
+        _ = unsafe { libc::write(self.wake, [0u8].as_ptr().cast(), 1) };
+    }
+
+    fn start(&'static self, hostname: CString, hints: ares_addrinfo_hints, port: u16) -> Lookup {
+        let query = Arc::new(Query {
+            hostname,
+            hints,
+            port,
+            finished: AtomicBool::new(false),
+            completion: Completion::new(),
+        });
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.submit(Command::Start(id, query.clone()));
+        Lookup {
+            driver: self,
+            query,
+            id,
+            done: false,
+        }
+    }
+
+    fn run(&self, template: Channel, wake: ::core::ffi::c_int) {
+        let mut active: HashMap<u64, (Channel, Arc<Query>)> = HashMap::new();
+        let mut fds: Vec<libc::pollfd> = Vec::new();
+        let mut owners: Vec<u64> = Vec::new();
+
+        loop {
+            let commands =
+                core::mem::take(&mut *self.commands.lock().unwrap_or_else(|e| e.into_inner()));
+            for command in commands {
+                match command {
+                    Command::Start(id, query) => {
+                        let mut channel = core::ptr::null_mut();
+                        let ret = unsafe { ares_dup(&mut channel, template.0) };
+                        if ret != AresError::Success {
+                            query.completion.complete(Err(ret.into()));
+                            continue;
+                        }
+                        let channel = Channel(channel);
+                        unsafe {
+                            ares_getaddrinfo(
+                                channel.0,
+                                query.hostname.as_ptr(),
+                                core::ptr::null(),
+                                &query.hints,
+                                addrinfo_callback,
+                                Arc::into_raw(query.clone()) as *mut _,
+                            )
+                        };
+                        active.insert(id, (channel, query));
+                    }
+                    Command::Cancel(id) => {
+                        active.remove(&id);
+                    }
+                }
+            }
+            active.retain(|_, (_, query)| !query.finished.load(Ordering::Acquire));
+
+            fds.clear();
+            owners.clear();
+            fds.push(libc::pollfd {
+                fd: wake,
+                events: libc::POLLIN,
+                revents: 0,
+            });
+            owners.push(u64::MAX);
+
+            let mut timeout: Option<Duration> = None;
+            for (&id, (channel, _)) in &active {
+                let mut socks = [ARES_SOCKET_BAD; ARES_GETSOCK_MAXNUM];
+                let bits = unsafe {
+                    ares_getsock(channel.0, socks.as_mut_ptr(), ARES_GETSOCK_MAXNUM as _)
+                };
+                for (i, &fd) in socks.iter().enumerate() {
+                    let mut events = 0;
+                    if bits & (1 << i) != 0 {
+                        events |= libc::POLLIN;
+                    }
+                    if bits & (1 << (i + ARES_GETSOCK_MAXNUM)) != 0 {
+                        events |= libc::POLLOUT;
+                    }
+                    if events != 0 {
+                        fds.push(libc::pollfd {
+                            fd,
+                            events,
+                            revents: 0,
+                        });
+                        owners.push(id);
+                    }
+                }
+
+                let mut tv: libc::timeval = unsafe { core::mem::zeroed() };
+                if let Some(tv) =
+                    unsafe { ares_timeout(channel.0, core::ptr::null_mut(), &mut tv).as_ref() }
+                {
+                    let next = Duration::new(tv.tv_sec as _, tv.tv_usec as u32 * 1000);
+                    timeout = Some(timeout.map_or(next, |t| t.min(next)));
+                }
+            }
+
+            // round up, so that c-ares finds its timeouts expired once poll returns
+            let timeout = timeout.map_or(-1, |t| {
+                (t.as_nanos().div_ceil(1_000_000)).min(::core::ffi::c_int::MAX as u128) as _
+            });
+            if unsafe { libc::poll(fds.as_mut_ptr(), fds.len() as _, timeout) } < 0 {
+                continue;
+            }
+
+            if fds[0].revents != 0 {
+                let mut buf = [0u8; 64];
+                while unsafe { libc::read(wake, buf.as_mut_ptr().cast(), buf.len()) } > 0 {}
+            }
+            for (fd, id) in fds.iter().zip(&owners).skip(1) {
+                let Some((channel, _)) = active.get(id) else {
+                    continue;
+                };
+                let readable = fd.revents & (libc::POLLIN | libc::POLLERR | libc::POLLHUP) != 0;
+                let writable = fd.revents & (libc::POLLOUT | libc::POLLERR | libc::POLLHUP) != 0;
+                if readable || writable {
+                    unsafe {
+                        ares_process_fd(
+                            channel.0,
+                            if readable { fd.fd } else { ARES_SOCKET_BAD },
+                            if writable { fd.fd } else { ARES_SOCKET_BAD },
+                        )
+                    };
+                }
+            }
+            // lets c-ares retry or fail the queries whose timeouts expired
+            for (channel, _) in active.values() {
+                unsafe { ares_process_fd(channel.0, ARES_SOCKET_BAD, ARES_SOCKET_BAD) };
+            }
+        }
+    }
+}
+
+/// In-flight `ares_getaddrinfo` query, canceled by destroying its channel when dropped.
+pub(crate) struct Lookup {
+    driver: &'static Driver,
+    query: Arc<Query>,
+    id: u64,
+    done: bool,
+}
+
+impl Lookup {
+    fn poll_answer(&mut self, cx: &mut TaskContext<'_>) -> Poll<std::io::Result<Answer>> {
+        let res = ready!(self.query.completion.poll(cx));
+        self.done = true;
+        Poll::Ready(res)
+    }
+}
+
+impl Future for Lookup {
+    type Output = std::io::Result<std::vec::IntoIter<SocketAddr>>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut TaskContext<'_>) -> Poll<Self::Output> {
+        self.poll_answer(cx).map(|res| {
+            res.map(|answer| {
+                let addrs: Vec<_> = answer.addrs.into_iter().map(|r| r.addr).collect();
+                addrs.into_iter()
+            })
+        })
+    }
+}
+
+impl Drop for Lookup {
+    fn drop(&mut self) {
+        if !self.done {
+            self.driver.submit(Command::Cancel(self.id));
+        }
+    }
+}
+
+fn hostname_to_cstring(hostname: &str) -> std::io::Result<CString> {
+    CString::new(hostname).map_err(|_| {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "host name contained an unexpected NUL byte",
+        )
+    })
+}
+
+fn ares_hints(hints: &Hints) -> ares_addrinfo_hints {
+    ares_addrinfo_hints {
+        ai_flags: hints.flags.to_native(&[
+            (HintFlags::PASSIVE, ARES_AI_PASSIVE),
+            (HintFlags::CANONNAME, ARES_AI_CANONNAME),
+            (HintFlags::NUMERICHOST, ARES_AI_NUMERICHOST),
+            (HintFlags::NUMERICSERV, ARES_AI_NUMERICSERV),
+            (HintFlags::V4MAPPED, ARES_AI_V4MAPPED),
+            (HintFlags::ALL, ARES_AI_ALL),
+            (HintFlags::ADDRCONFIG, ARES_AI_ADDRCONFIG),
+        ]),
+        ai_family: hints.libc_family(),
+        ai_socktype: hints.libc_socktype(),
+        ai_protocol: hints.libc_protocol(),
+    }
+}
+
+fn family_hints(family: Family) -> ares_addrinfo_hints {
+    ares_hints(&Hints {
+        family,
+        ..Hints::default()
+    })
+}
+
+pub(crate) fn lookup(
+    hostname: &str,
+    port: u16,
+    _timeout: Option<Duration>,
+) -> std::io::Result<Lookup> {
+    let hostname = hostname_to_cstring(hostname)?;
+    Ok(driver()?.start(hostname, ares_hints(&Hints::default()), port))
+}
+
+/// Starts separate IPv6 and IPv4 lookups.
+pub(crate) fn lookup_split(
+    hostname: &str,
+    port: u16,
+    _timeout: Option<Duration>,
+) -> std::io::Result<Vec<Lookup>> {
+    let hostname = hostname_to_cstring(hostname)?;
+    let driver = driver()?;
+    Ok(vec![
+        driver.start(hostname.clone(), family_hints(Family::Ipv6), port),
+        driver.start(hostname, family_hints(Family::Ipv4), port),
+    ])
+}
+
+fn answer(host: &str, port: u16, hints: &Hints, timeout: Duration) -> std::io::Result<Answer> {
+    let mut lookup = driver()?.start(hostname_to_cstring(host)?, ares_hints(hints), port);
+    block_on_deadline(
+        &mut std::future::poll_fn(|cx| lookup.poll_answer(cx)),
+        crate::deadline_after(timeout),
+    )
+    .unwrap_or_else(|| Err(std::io::ErrorKind::TimedOut.into()))
+}
+
+/// The c-ares resolver: `ares_getaddrinfo` on a background event loop, canceled on timeout.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemResolver;
+
+impl ResolverBackend for SystemResolver {
+    fn resolve(
+        &self,
+        host: &str,
+        port: u16,
+        timeout: Duration,
+    ) -> std::io::Result<std::vec::IntoIter<SocketAddr>> {
+        self.resolve_with_hints(host, port, &Hints::default(), timeout)
+    }
+
+    fn resolve_with_hints(
+        &self,
+        host: &str,
+        port: u16,
+        hints: &Hints,
+        timeout: Duration,
+    ) -> std::io::Result<std::vec::IntoIter<SocketAddr>> {
+        let mut lookup = driver()?.start(hostname_to_cstring(host)?, ares_hints(hints), port);
+        block_on_deadline(&mut lookup, crate::deadline_after(timeout))
+            .unwrap_or_else(|| Err(std::io::ErrorKind::TimedOut.into()))
+    }
+
+    fn resolve_canonical(
+        &self,
+        host: &str,
+        port: u16,
+        hints: &Hints,
+        timeout: Duration,
+    ) -> std::io::Result<CanonicalAddrs> {
+        let hints = Hints {
+            flags: hints.flags | HintFlags::CANONNAME,
+            ..*hints
+        };
+        let answer = answer(host, port, &hints, timeout)?;
+        Ok(CanonicalAddrs {
+            name: answer.name,
+            addrs: answer.addrs.into_iter().map(|r| r.addr).collect(),
+        })
+    }
+
+    fn resolve_with_ttl(
+        &self,
+        host: &str,
+        port: u16,
+        hints: &Hints,
+        timeout: Duration,
+    ) -> std::io::Result<Vec<ResolvedAddr>> {
+        answer(host, port, hints, timeout).map(|answer| answer.addrs)
+    }
+}
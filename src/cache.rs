@@ -0,0 +1,281 @@
+use std::{
+    cell::Cell,
+    collections::HashMap,
+    io,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use crate::{CanonicalAddrs, Family, HintFlags, Hints, ResolvedAddr, ResolverBackend};
+
+/// Settings of the lookup cache of a [`Resolver`](crate::Resolver), see
+/// [`ResolverBuilder::cache`](crate::ResolverBuilder::cache).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct CacheConfig {
+    /// Most hosts kept, the least recently used being evicted first, 1024 by default.
+    pub max_entries: usize,
+    /// How long addresses are kept when the backend reports no TTL, 60 seconds by default.
+    pub default_ttl: Duration,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            max_entries: 1024,
+            default_ttl: Duration::from_secs(60),
+        }
+    }
+}
+
+impl CacheConfig {
+    /// Sets [`max_entries`](Self::max_entries).
+    pub fn max_entries(mut self, max: usize) -> Self {
+        self.max_entries = max;
+        self
+    }
+
+    /// Sets [`default_ttl`](Self::default_ttl).
+    pub fn default_ttl(mut self, ttl: Duration) -> Self {
+        self.default_ttl = ttl;
+        self
+    }
+}
+
+/// `inner` with the addresses it finds kept until the shortest of their TTLs expires.
+///
+/// Entries are keyed by host, family and flags, which change what is returned; canonical name
+/// lookups go to `inner` every time.
+pub(crate) struct Cached {
+    inner: Arc<dyn ResolverBackend>,
+    config: CacheConfig,
+    entries: Arc<Mutex<Entries>>,
+}
+
+type Key = (String, Family, HintFlags);
+
+#[derive(Default)]
+struct Entries {
+    map: HashMap<Key, Entry>,
+    /// Incremented on every use, the entry with the lowest `used` being the least recent.
+    clock: u64,
+}
+
+struct Entry {
+    /// Addresses with port 0.
+    addrs: Vec<SocketAddr>,
+    expires: Instant,
+    used: u64,
+}
+
+impl Cached {
+    pub fn new(inner: Arc<dyn ResolverBackend>, config: CacheConfig) -> Self {
+        Self {
+            inner,
+            config,
+            entries: Arc::default(),
+        }
+    }
+
+    /// The cached addresses of `host` on `port`, with the TTL left, or those `inner` finds.
+    fn lookup(
+        &self,
+        host: &str,
+        port: u16,
+        hints: &Hints,
+        timeout: Duration,
+    ) -> io::Result<Vec<ResolvedAddr>> {
+        if self.config.max_entries == 0 {
+            return self.inner.resolve_with_ttl(host, port, hints, timeout);
+        }
+        let key = (host.to_ascii_lowercase(), hints.family, hints.flags);
+        if let Some(hit) = self.get(&key, port) {
+            crate::stats::cache_hit();
+            return Ok(hit);
+        }
+
+        let late = {
+            let (entries, key, hints) = (self.entries.clone(), key.clone(), *hints);
+            let (max_entries, ttl) = (self.config.max_entries, self.config.default_ttl);
+            move |addrs: Vec<SocketAddr>| {
+                let addrs: Vec<_> = hints
+                    .filter(addrs.into_iter())
+                    .map(|mut addr| {
+                        addr.set_port(0);
+                        addr
+                    })
+                    .collect();
+                if !addrs.is_empty() && !ttl.is_zero() {
+                    entries.lock().unwrap_or_else(|e| e.into_inner()).insert(
+                        key,
+                        addrs,
+                        crate::deadline_after(ttl),
+                        max_entries,
+                    );
+                }
+            }
+        };
+        let resolved = with_late_sink(Box::new(late), || {
+            self.inner.resolve_with_ttl(host, port, hints, timeout)
+        })?;
+        let ttl = resolved
+            .iter()
+            .map(|r| r.ttl.unwrap_or(self.config.default_ttl))
+            .min()
+            .unwrap_or_default();
+        if !ttl.is_zero() {
+            let addrs = resolved
+                .iter()
+                .map(|r| {
+                    let mut addr = r.addr;
+                    addr.set_port(0);
+                    addr
+                })
+                .collect();
+            self.entries
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .insert(
+                    key,
+                    addrs,
+                    crate::deadline_after(ttl),
+                    self.config.max_entries,
+                );
+        }
+        Ok(resolved)
+    }
+
+    fn get(&self, key: &Key, port: u16) -> Option<Vec<ResolvedAddr>> {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries.clock += 1;
+        let clock = entries.clock;
+        let now = Instant::now();
+        let entry = entries.map.get_mut(key)?;
+        let Some(ttl) = entry
+            .expires
+            .checked_duration_since(now)
+            .filter(|d| !d.is_zero())
+        else {
+            entries.map.remove(key);
+            return None;
+        };
+        entry.used = clock;
+        Some(
+            entry
+                .addrs
+                .iter()
+                .copied()
+                .map(|mut addr| {
+                    addr.set_port(port);
+                    ResolvedAddr {
+                        addr,
+                        ttl: Some(ttl),
+                    }
+                })
+                .collect(),
+        )
+    }
+}
+
+impl Entries {
+    /// Keeps `addrs` for `key` until `expires`, evicting an entry to stay within `max_entries`.
+    fn insert(&mut self, key: Key, addrs: Vec<SocketAddr>, expires: Instant, max_entries: usize) {
+        self.clock += 1;
+        let used = self.clock;
+        if !self.map.contains_key(&key) && self.map.len() >= max_entries {
+            let now = Instant::now();
+            self.map.retain(|_, entry| entry.expires > now);
+            if self.map.len() >= max_entries {
+                let oldest = self
+                    .map
+                    .iter()
+                    .min_by_key(|(_, entry)| entry.used)
+                    .map(|(key, _)| key.clone());
+                if let Some(oldest) = oldest {
+                    self.map.remove(&oldest);
+                }
+            }
+        }
+        self.map.insert(
+            key,
+            Entry {
+                addrs,
+                expires,
+                used,
+            },
+        );
+    }
+}
+
+/// Receives the addresses of a lookup that timed out, once it completes after all.
+pub(crate) type LateSink = Box<dyn FnOnce(Vec<SocketAddr>) + Send>;
+
+thread_local! {
+    static LATE_SINK: Cell<Option<LateSink>> = const { Cell::new(None) };
+}
+
+/// Runs `lookup` with `sink` offered to a backend able to finish it in the background, see
+/// [`take_late_sink`].
+fn with_late_sink<T>(sink: LateSink, lookup: impl FnOnce() -> T) -> T {
+    let outer = LATE_SINK.replace(Some(sink));
+    let result = lookup();
+    LATE_SINK.set(outer);
+    result
+}
+
+/// The sink of the cache lookup running on this thread, for a backend whose lookups keep
+/// running once they time out to hand their addresses to the cache all the same.
+#[cfg_attr(
+    not(any(backend = "fallback", backend = "android", backend = "linux_glibc")),
+    allow(dead_code)
+)]
+pub(crate) fn take_late_sink() -> Option<LateSink> {
+    LATE_SINK.take()
+}
+
+impl ResolverBackend for Cached {
+    fn resolve(
+        &self,
+        host: &str,
+        port: u16,
+        timeout: Duration,
+    ) -> io::Result<std::vec::IntoIter<SocketAddr>> {
+        self.resolve_with_hints(host, port, &Hints::default(), timeout)
+    }
+
+    fn resolve_with_hints(
+        &self,
+        host: &str,
+        port: u16,
+        hints: &Hints,
+        timeout: Duration,
+    ) -> io::Result<std::vec::IntoIter<SocketAddr>> {
+        let addrs: Vec<_> = self
+            .lookup(host, port, hints, timeout)?
+            .into_iter()
+            .map(|r| r.addr)
+            .collect();
+        Ok(addrs.into_iter())
+    }
+
+    fn resolve_canonical(
+        &self,
+        host: &str,
+        port: u16,
+        hints: &Hints,
+        timeout: Duration,
+    ) -> io::Result<CanonicalAddrs> {
+        self.inner.resolve_canonical(host, port, hints, timeout)
+    }
+
+    fn resolve_with_ttl(
+        &self,
+        host: &str,
+        port: u16,
+        hints: &Hints,
+        timeout: Duration,
+    ) -> io::Result<Vec<ResolvedAddr>> {
+        self.lookup(host, port, hints, timeout)
+    }
+}
@@ -0,0 +1,215 @@
+use std::{
+    collections::HashMap,
+    io,
+    net::SocketAddr,
+    sync::{Arc, Condvar, Mutex},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    dns::Rcode, AddressInfoError, CanonicalAddrs, DnssecError, Hints, ResolveError, ResolvedAddr,
+    ResolverBackend,
+};
+
+type Key = (String, u16, Hints);
+
+type Outcome = io::Result<Vec<ResolvedAddr>>;
+
+/// `inner` with concurrent lookups of the same host, port and hints sharing a single one.
+///
+/// The first caller runs the lookup with its own timeout while the others wait for the outcome
+/// as long as theirs allow, and try again themselves if it timed out before they did.
+pub(crate) struct Coalesced {
+    inner: Arc<dyn ResolverBackend>,
+    flights: Mutex<HashMap<Key, Arc<Flight>>>,
+}
+
+/// A lookup in progress.
+#[derive(Default)]
+struct Flight {
+    outcome: Mutex<Option<Outcome>>,
+    landed: Condvar,
+}
+
+impl Coalesced {
+    pub fn new(inner: Arc<dyn ResolverBackend>) -> Self {
+        Self {
+            inner,
+            flights: Mutex::default(),
+        }
+    }
+
+    fn lookup(&self, host: &str, port: u16, hints: &Hints, timeout: Duration) -> Outcome {
+        // answered from the cache or not at all, never worth waiting for
+        if timeout.is_zero() {
+            return self.inner.resolve_with_ttl(host, port, hints, timeout);
+        }
+        let deadline = crate::deadline_after(timeout);
+        let key = (host.to_ascii_lowercase(), port, *hints);
+        loop {
+            let (flight, leading) = {
+                let mut flights = self.flights.lock().unwrap_or_else(|e| e.into_inner());
+                match flights.get(&key) {
+                    Some(flight) => (flight.clone(), false),
+                    None => {
+                        let flight = Arc::new(Flight::default());
+                        flights.insert(key.clone(), flight.clone());
+                        (flight, true)
+                    }
+                }
+            };
+
+            if leading {
+                let mut landing = Landing {
+                    flights: &self.flights,
+                    key: &key,
+                    flight: &flight,
+                    outcome: None,
+                };
+                let timeout = deadline.saturating_duration_since(Instant::now());
+                let outcome = self.inner.resolve_with_ttl(host, port, hints, timeout);
+                landing.outcome = Some(duplicate(&outcome));
+                return outcome;
+            }
+
+            match flight.wait(deadline) {
+                None => return Err(io::ErrorKind::TimedOut.into()),
+                Some(Err(err))
+                    if err.kind() == io::ErrorKind::TimedOut && Instant::now() < deadline => {}
+                Some(outcome) => return outcome,
+            }
+        }
+    }
+}
+
+impl Flight {
+    /// A copy of the outcome once the lookup lands, `None` if `deadline` passes first.
+    fn wait(&self, deadline: Instant) -> Option<Outcome> {
+        let mut outcome = self.outcome.lock().unwrap_or_else(|e| e.into_inner());
+        loop {
+            if let Some(ref outcome) = *outcome {
+                return Some(duplicate(outcome));
+            }
+            let left = deadline
+                .checked_duration_since(Instant::now())
+                .filter(|d| !d.is_zero())?;
+            outcome = match self.landed.wait_timeout(outcome, left) {
+                Ok((outcome, _)) => outcome,
+                Err(e) => e.into_inner().0,
+            };
+        }
+    }
+}
+
+/// Retires a flight and hands its outcome to the waiters, a failure if the lookup panicked.
+struct Landing<'a> {
+    flights: &'a Mutex<HashMap<Key, Arc<Flight>>>,
+    key: &'a Key,
+    flight: &'a Flight,
+    outcome: Option<Outcome>,
+}
+
+impl Drop for Landing<'_> {
+    fn drop(&mut self) {
+        self.flights
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(self.key);
+        let outcome = self
+            .outcome
+            .take()
+            .unwrap_or_else(|| Err(io::Error::other("lookup panicked")));
+        *self
+            .flight
+            .outcome
+            .lock()
+            .unwrap_or_else(|e| e.into_inner()) = Some(outcome);
+        self.flight.landed.notify_all();
+    }
+}
+
+/// A copy of `outcome` for another caller, the errors of this crate staying downcastable.
+fn duplicate(outcome: &Outcome) -> Outcome {
+    let err = match outcome {
+        Ok(addrs) => return Ok(addrs.clone()),
+        Err(err) => err,
+    };
+    if let Some(code) = err.raw_os_error() {
+        return Err(io::Error::from_raw_os_error(code));
+    }
+    Err(match err.get_ref() {
+        None => err.kind().into(),
+        Some(inner) => {
+            if let Some(resolve) = inner
+                .downcast_ref::<ResolveError>()
+                .and_then(ResolveError::duplicate)
+            {
+                resolve.into()
+            } else if let Some(&code) = inner.downcast_ref::<AddressInfoError>() {
+                code.into()
+            } else if let Some(&rcode) = inner.downcast_ref::<Rcode>() {
+                rcode.into()
+            } else if let Some(&dnssec) = inner.downcast_ref::<DnssecError>() {
+                dnssec.into()
+            } else {
+                io::Error::new(err.kind(), inner.to_string())
+            }
+        }
+    })
+}
+
+impl ResolverBackend for Coalesced {
+    fn resolve(
+        &self,
+        host: &str,
+        port: u16,
+        timeout: Duration,
+    ) -> io::Result<std::vec::IntoIter<SocketAddr>> {
+        self.resolve_with_hints(host, port, &Hints::default(), timeout)
+    }
+
+    fn resolve_with_hints(
+        &self,
+        host: &str,
+        port: u16,
+        hints: &Hints,
+        timeout: Duration,
+    ) -> io::Result<std::vec::IntoIter<SocketAddr>> {
+        let addrs: Vec<_> = self
+            .lookup(host, port, hints, timeout)?
+            .into_iter()
+            .map(|r| r.addr)
+            .collect();
+        Ok(addrs.into_iter())
+    }
+
+    fn resolve_service(
+        &self,
+        host: &str,
+        service: &str,
+        hints: &Hints,
+        timeout: Duration,
+    ) -> io::Result<std::vec::IntoIter<SocketAddr>> {
+        self.inner.resolve_service(host, service, hints, timeout)
+    }
+
+    fn resolve_canonical(
+        &self,
+        host: &str,
+        port: u16,
+        hints: &Hints,
+        timeout: Duration,
+    ) -> io::Result<CanonicalAddrs> {
+        self.inner.resolve_canonical(host, port, hints, timeout)
+    }
+
+    fn resolve_with_ttl(
+        &self,
+        host: &str,
+        port: u16,
+        hints: &Hints,
+        timeout: Duration,
+    ) -> io::Result<Vec<ResolvedAddr>> {
+        self.lookup(host, port, hints, timeout)
+    }
+}
@@ -0,0 +1,50 @@
+use std::{
+    sync::Mutex,
+    task::{Context, Poll, Waker},
+};
+
+/// One-shot slot filled by a backend completion callback and polled by a future.
+pub(crate) struct Completion<T> {
+    state: Mutex<State<T>>,
+}
+
+struct State<T> {
+    value: Option<T>,
+    waker: Option<Waker>,
+}
+
+impl<T> Completion<T> {
+    pub const fn new() -> Self {
+        Self {
+            state: Mutex::new(State {
+                value: None,
+                waker: None,
+            }),
+        }
+    }
+
+    pub fn complete(&self, value: T) {
+        let waker = {
+            let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+            state.value = Some(value);
+            state.waker.take()
+        };
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+
+    pub fn poll(&self, cx: &mut Context<'_>) -> Poll<T> {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        match state.value.take() {
+            Some(value) => Poll::Ready(value),
+            None => {
+                match state.waker {
+                    Some(ref w) if w.will_wake(cx.waker()) => (),
+                    _ => state.waker = Some(cx.waker().clone()),
+                }
+                Poll::Pending
+            }
+        }
+    }
+}
@@ -0,0 +1,227 @@
+use std::{
+    io,
+    net::{SocketAddr, TcpStream, UdpSocket},
+    sync::mpsc,
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{AddrInfoIter, AddrIterExt, Budget, ToSocketAddrsTimeout};
+
+/// Wait before starting the next connection attempt, the value RFC 8305 recommends.
+const ATTEMPT_DELAY: Duration = Duration::from_millis(250);
+
+/// Connecting to host names with timeouts.
+pub trait TcpStreamExt: Sized {
+    /// Like [`TcpStream::connect_timeout`], for any address: `addr` is resolved within
+    /// `resolve_timeout`, then each of its addresses is tried in order, each connection attempt
+    /// given up once `connect_timeout` has elapsed.
+    ///
+    /// As with [`TcpStream::connect`], the error of the last attempt is returned when none
+    /// succeeds.
+    fn connect_timeout_resolved<A: ToSocketAddrsTimeout>(
+        addr: A,
+        resolve_timeout: Duration,
+        connect_timeout: Duration,
+    ) -> io::Result<Self>;
+
+    /// Like [`connect_timeout_resolved`](Self::connect_timeout_resolved), resolving `addr` and
+    /// trying its addresses with whatever time is left in `budget`, shared out as by
+    /// [`connect_each`].
+    fn connect_within<A: ToSocketAddrsTimeout>(addr: A, budget: &Budget) -> io::Result<Self>;
+}
+
+impl TcpStreamExt for TcpStream {
+    fn connect_timeout_resolved<A: ToSocketAddrsTimeout>(
+        addr: A,
+        resolve_timeout: Duration,
+        connect_timeout: Duration,
+    ) -> io::Result<Self> {
+        let mut last_err = None;
+        for addr in addr.to_socket_addrs_timeout(resolve_timeout)?.dedup_addrs() {
+            match TcpStream::connect_timeout(&addr, connect_timeout) {
+                Ok(stream) => return Ok(stream),
+                Err(err) => last_err = Some(err),
+            }
+        }
+        Err(last_err.unwrap_or_else(no_addresses))
+    }
+
+    fn connect_within<A: ToSocketAddrsTimeout>(addr: A, budget: &Budget) -> io::Result<Self> {
+        let addrs = addr.to_socket_addrs_within(budget)?;
+        connect_each(addrs.dedup_addrs(), budget).map(|connected| connected.stream)
+    }
+}
+
+/// A connection made by [`connect_each`].
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct Connected {
+    pub stream: TcpStream,
+    /// The address connected to.
+    pub addr: SocketAddr,
+    /// Connection attempts made, the successful one included.
+    pub attempts: usize,
+    /// Time taken by the successful attempt.
+    pub connect_time: Duration,
+    /// Time taken by all the attempts.
+    pub elapsed: Duration,
+}
+
+/// Connects to the first of `addrs` that accepts, one after the other, within `budget`.
+///
+/// Each attempt gets an equal share of the time left for the addresses not tried yet, so one
+/// that cannot be reached does not use up the budget of the others, and those that fail fast
+/// leave more to the rest. The error of the last attempt is returned when none succeeds.
+pub fn connect_each<I: IntoIterator<Item = SocketAddr>>(
+    addrs: I,
+    budget: &Budget,
+) -> io::Result<Connected> {
+    let start = Instant::now();
+    let mut addrs: AddrInfoIter = addrs.into_iter().collect();
+    let total = addrs.len();
+    let mut last_err = None;
+    for (tried, addr) in addrs.by_ref().enumerate() {
+        let left = budget.remaining();
+        if left.is_zero() {
+            return Err(io::ErrorKind::TimedOut.into());
+        }
+        let share = left / (total - tried) as u32;
+        let attempt = Instant::now();
+        // below a microsecond connect_timeout could be handed a zero timeout, which it rejects
+        match TcpStream::connect_timeout(&addr, share.max(Duration::from_micros(1))) {
+            Ok(stream) => {
+                return Ok(Connected {
+                    stream,
+                    addr,
+                    attempts: tried + 1,
+                    connect_time: attempt.elapsed(),
+                    elapsed: start.elapsed(),
+                })
+            }
+            Err(err) => last_err = Some(err),
+        }
+    }
+    Err(last_err.unwrap_or_else(no_addresses))
+}
+
+/// Connecting UDP sockets to host names with a timeout.
+pub trait UdpSocketExt {
+    /// Like [`UdpSocket::connect`], resolving `addr` within `timeout` rather than with the
+    /// blocking std resolver.
+    ///
+    /// The addresses are tried in order, so one of the family the socket is bound to is used;
+    /// the error of the last attempt is returned when none can be.
+    fn connect_resolved<A: ToSocketAddrsTimeout>(
+        &self,
+        addr: A,
+        timeout: Duration,
+    ) -> io::Result<()>;
+}
+
+impl UdpSocketExt for UdpSocket {
+    fn connect_resolved<A: ToSocketAddrsTimeout>(
+        &self,
+        addr: A,
+        timeout: Duration,
+    ) -> io::Result<()> {
+        let mut last_err = None;
+        for addr in addr.to_socket_addrs_timeout(timeout)? {
+            match self.connect(addr) {
+                Ok(()) => return Ok(()),
+                Err(err) => last_err = Some(err),
+            }
+        }
+        Err(last_err.unwrap_or_else(no_addresses))
+    }
+}
+
+/// Connects to `host` on `port` the Happy Eyeballs way ([RFC 8305]), resolving it within
+/// `resolve_timeout` and connecting within `connect_timeout`.
+///
+/// The addresses are tried in the order of
+/// [`interleave_families`](AddrIterExt::interleave_families), alternating between IPv6 and
+/// IPv4, the next attempt starting 250 ms after the previous one or as soon as it fails. The
+/// first connection made is returned and the others are closed. Each attempt runs on a helper
+/// thread.
+///
+/// [RFC 8305]: https://www.rfc-editor.org/rfc/rfc8305
+pub fn connect_happy_eyeballs(
+    host: &str,
+    port: u16,
+    resolve_timeout: Duration,
+    connect_timeout: Duration,
+) -> io::Result<TcpStream> {
+    let addrs = (host, port).to_socket_addrs_timeout(resolve_timeout)?;
+    connect_staggered(
+        addrs.dedup_addrs().interleave_families().collect(),
+        &Budget::new(connect_timeout),
+    )
+}
+
+/// [`connect_happy_eyeballs`] resolving `host` and connecting to it with whatever time is left
+/// in `budget`.
+pub fn connect_happy_eyeballs_within(
+    host: &str,
+    port: u16,
+    budget: &Budget,
+) -> io::Result<TcpStream> {
+    let addrs = (host, port).to_socket_addrs_within(budget)?;
+    connect_staggered(addrs.dedup_addrs().interleave_families().collect(), budget)
+}
+
+/// The first connection to one of `addrs` made before `budget` is spent, staggering the
+/// attempts by [`ATTEMPT_DELAY`].
+fn connect_staggered(addrs: Vec<SocketAddr>, budget: &Budget) -> io::Result<TcpStream> {
+    let deadline = budget.deadline();
+    let (tx, rx) = mpsc::channel();
+    let mut next = addrs.into_iter();
+    let mut pending = 0;
+    let mut last_err = None;
+    while let Some(left) = deadline
+        .checked_duration_since(Instant::now())
+        .filter(|d| !d.is_zero())
+    {
+        match next.next() {
+            Some(addr) => {
+                pending += 1;
+                let attempt = {
+                    let tx = tx.clone();
+                    move || _ = tx.send(TcpStream::connect_timeout(&addr, left))
+                };
+                // without threads, the attempts are made one after the other
+                if thread::Builder::new().spawn(attempt).is_err() {
+                    _ = tx.send(TcpStream::connect_timeout(&addr, left));
+                }
+            }
+            None if pending == 0 => break,
+            None => {}
+        }
+        let wait = if next.len() > 0 {
+            left.min(ATTEMPT_DELAY)
+        } else {
+            left
+        };
+        match rx.recv_timeout(wait) {
+            Ok(Ok(stream)) => return Ok(stream),
+            Ok(Err(err)) => {
+                pending -= 1;
+                last_err = Some(err);
+            }
+            Err(_) => {}
+        }
+    }
+    Err(if pending > 0 || next.len() > 0 {
+        io::ErrorKind::TimedOut.into()
+    } else {
+        last_err.unwrap_or_else(no_addresses)
+    })
+}
+
+/// Error for a host without addresses to connect to, as std reports it.
+fn no_addresses() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        "could not resolve to any addresses",
+    )
+}
@@ -0,0 +1,368 @@
+use std::{io, net::IpAddr};
+
+use crate::ResolveError;
+
+pub(crate) const TYPE_A: u16 = 1;
+pub(crate) const TYPE_CNAME: u16 = 5;
+pub(crate) const TYPE_PTR: u16 = 12;
+pub(crate) const TYPE_TXT: u16 = 16;
+pub(crate) const TYPE_AAAA: u16 = 28;
+pub(crate) const TYPE_OPT: u16 = 41;
+pub(crate) const TYPE_HTTPS: u16 = 65;
+pub(crate) const CLASS_IN: u16 = 1;
+
+pub(crate) const FLAG_QR: u16 = 0x8000;
+pub(crate) const FLAG_TC: u16 = 0x0200;
+pub(crate) const FLAG_RD: u16 = 0x0100;
+pub(crate) const FLAG_AD: u16 = 0x0020;
+
+/// The EDNS `DO` flag, in the TTL field of the OPT record.
+const EDNS_DO: u32 = 0x8000;
+/// The EDNS Client Subnet option, RFC 7871.
+pub(crate) const OPT_ECS: u16 = 8;
+/// The EDNS option carrying an extended DNS error, RFC 8914.
+const OPT_EDE: u16 = 15;
+
+/// Top bit of the class, the mDNS cache-flush or unicast-response bit.
+const CLASS_MDNS_BIT: u16 = 0x8000;
+
+#[repr(transparent)]
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct Rcode(pub u8);
+
+#[allow(non_upper_case_globals)]
+impl Rcode {
+    /// No error condition.
+    pub const NoError: Self = Self(0);
+    /// The server was unable to interpret the query.
+    pub const FormErr: Self = Self(1);
+    /// The server failed to process the query.
+    pub const ServFail: Self = Self(2);
+    /// The name does not exist.
+    pub const NXDomain: Self = Self(3);
+    /// The server does not support the query.
+    pub const NotImp: Self = Self(4);
+    /// The server refused to answer.
+    pub const Refused: Self = Self(5);
+}
+
+impl core::fmt::Display for Rcode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            Self::NoError => f.write_str("no error"),
+            Self::FormErr => f.write_str("format error"),
+            Self::ServFail => f.write_str("server failure"),
+            Self::NXDomain => f.write_str("name does not exist"),
+            Self::NotImp => f.write_str("not implemented"),
+            Self::Refused => f.write_str("query refused"),
+            _ => write!(f, "response code {}", self.0),
+        }
+    }
+}
+
+impl core::fmt::Debug for Rcode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            Self::NoError => f.write_str("Rcode::NoError"),
+            Self::FormErr => f.write_str("Rcode::FormErr"),
+            Self::ServFail => f.write_str("Rcode::ServFail"),
+            Self::NXDomain => f.write_str("Rcode::NXDomain"),
+            Self::NotImp => f.write_str("Rcode::NotImp"),
+            Self::Refused => f.write_str("Rcode::Refused"),
+            _ => write!(f, "Rcode({})", self.0),
+        }
+    }
+}
+
+impl std::error::Error for Rcode {}
+
+impl From<Rcode> for io::Error {
+    fn from(value: Rcode) -> Self {
+        match value {
+            Rcode::NXDomain => ResolveError::HostNotFound.into(),
+            Rcode::ServFail => ResolveError::TemporaryFailure.into(),
+            _ => io::Error::other(value),
+        }
+    }
+}
+
+/// Encodes `host` as a sequence of length prefixed labels.
+pub(crate) fn encode_name(host: &str) -> io::Result<Vec<u8>> {
+    let invalid = || io::Error::new(io::ErrorKind::InvalidInput, "invalid host name");
+
+    let host = host.strip_suffix('.').unwrap_or(host);
+    if host.is_empty() {
+        return Err(invalid());
+    }
+    let mut name = Vec::with_capacity(host.len() + 2);
+    for label in host.split('.') {
+        if label.is_empty() || label.len() > 63 {
+            return Err(invalid());
+        }
+        name.push(label.len() as u8);
+        name.extend_from_slice(label.as_bytes());
+    }
+    name.push(0);
+    if name.len() > 255 {
+        return Err(invalid());
+    }
+    Ok(name)
+}
+
+/// Decodes an encoded, uncompressed name to its dotted form, without the trailing dot.
+pub(crate) fn name_to_string(name: &[u8]) -> String {
+    let mut labels = Vec::new();
+    let mut pos = 0;
+    while let Some(&len) = name.get(pos).filter(|&&len| len != 0) {
+        let label = name
+            .get(pos + 1..pos + 1 + len as usize)
+            .unwrap_or_default();
+        labels.push(String::from_utf8_lossy(label));
+        pos += 1 + len as usize;
+    }
+    labels.join(".")
+}
+
+/// The character strings of TXT record data joined together, `None` if it is malformed.
+pub(crate) fn txt_data(mut rdata: &[u8]) -> Option<Vec<u8>> {
+    let mut data = Vec::with_capacity(rdata.len());
+    while let Some((&len, rest)) = rdata.split_first() {
+        let string = rest.get(..len as usize)?;
+        data.extend_from_slice(string);
+        rdata = &rest[len as usize..];
+    }
+    Some(data)
+}
+
+/// The `in-addr.arpa` or `ip6.arpa` name PTR records of `ip` are held under.
+pub(crate) fn reverse_name(ip: IpAddr) -> String {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, c, d] = ip.octets();
+            format!("{d}.{c}.{b}.{a}.in-addr.arpa")
+        }
+        IpAddr::V6(ip) => {
+            let mut name = String::with_capacity(72);
+            for byte in ip.octets().iter().rev() {
+                name.push_str(&format!("{:x}.{:x}.", byte & 0xf, byte >> 4));
+            }
+            name.push_str("ip6.arpa");
+            name
+        }
+    }
+}
+
+/// Builds a query for the encoded `name`.
+pub(crate) fn build_query(id: u16, flags: u16, name: &[u8], qtype: u16, class: u16) -> Vec<u8> {
+    let mut msg = Vec::with_capacity(12 + name.len() + 4);
+    msg.extend_from_slice(&id.to_be_bytes());
+    msg.extend_from_slice(&flags.to_be_bytes());
+    msg.extend_from_slice(&1u16.to_be_bytes());
+    msg.extend_from_slice(&[0; 6]);
+    msg.extend_from_slice(name);
+    msg.extend_from_slice(&qtype.to_be_bytes());
+    msg.extend_from_slice(&class.to_be_bytes());
+    msg
+}
+
+/// Appends an OPT record to the query `msg`, advertising `udp_size`, setting the `DO` flag
+/// with `dnssec_ok` and holding the encoded `options`.
+pub(crate) fn push_opt(msg: &mut Vec<u8>, udp_size: u16, dnssec_ok: bool, options: &[u8]) {
+    let additional = read_u16(msg, 10).unwrap_or_default() + 1;
+    msg[10..12].copy_from_slice(&additional.to_be_bytes());
+    msg.push(0);
+    msg.extend_from_slice(&TYPE_OPT.to_be_bytes());
+    msg.extend_from_slice(&udp_size.to_be_bytes());
+    msg.extend_from_slice(&(if dnssec_ok { EDNS_DO } else { 0 }).to_be_bytes());
+    msg.extend_from_slice(&(options.len() as u16).to_be_bytes());
+    msg.extend_from_slice(options);
+}
+
+pub(crate) fn read_u16(msg: &[u8], pos: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(msg.get(pos..pos + 2)?.try_into().ok()?))
+}
+
+fn read_u32(msg: &[u8], pos: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(msg.get(pos..pos + 4)?.try_into().ok()?))
+}
+
+/// Reads the, possibly compressed, name at `pos`, returning it uncompressed together with the
+/// position following it.
+pub(crate) fn read_name(msg: &[u8], mut pos: usize) -> Option<(Vec<u8>, usize)> {
+    let mut name = Vec::new();
+    let mut end = None;
+    // every pointer must go backwards, which bounds the loop
+    let mut limit = pos;
+    loop {
+        let len = *msg.get(pos)? as usize;
+        match len & 0xc0 {
+            0 if len == 0 => {
+                name.push(0);
+                return (name.len() <= 255).then(|| (name, end.unwrap_or(pos + 1)));
+            }
+            0 => {
+                name.extend_from_slice(msg.get(pos..pos + 1 + len)?);
+                pos += 1 + len;
+            }
+            0xc0 => {
+                let target = (len & 0x3f) << 8 | *msg.get(pos + 1)? as usize;
+                if target >= limit {
+                    return None;
+                }
+                end.get_or_insert(pos + 2);
+                limit = target;
+                pos = target;
+            }
+            _ => return None,
+        }
+    }
+}
+
+/// Compares encoded names case-insensitively.
+pub(crate) fn names_eq(a: &[u8], b: &[u8]) -> bool {
+    a.eq_ignore_ascii_case(b)
+}
+
+pub(crate) struct Question {
+    pub name: Vec<u8>,
+    pub qtype: u16,
+    pub class: u16,
+}
+
+pub(crate) struct Record<'a> {
+    pub name: Vec<u8>,
+    pub rtype: u16,
+    pub class: u16,
+    pub ttl: u32,
+    pub rdata: &'a [u8],
+    /// Position of the data in the message.
+    pub rdata_pos: usize,
+}
+
+impl Record<'_> {
+    /// The address held by an A or AAAA record of class IN.
+    pub fn ip(&self) -> Option<IpAddr> {
+        if self.class != CLASS_IN {
+            return None;
+        }
+        match self.rtype {
+            TYPE_A => Some(IpAddr::V4(<[u8; 4]>::try_from(self.rdata).ok()?.into())),
+            TYPE_AAAA => Some(IpAddr::V6(<[u8; 16]>::try_from(self.rdata).ok()?.into())),
+            _ => None,
+        }
+    }
+
+    /// The name held by a PTR or CNAME record, read from `msg` as it may be compressed.
+    pub fn rdata_name(&self, msg: &[u8]) -> Option<Vec<u8>> {
+        read_name(msg, self.rdata_pos).map(|(name, _)| name)
+    }
+}
+
+/// A parsed DNS message.
+///
+/// The mDNS bit of record classes is cleared. When the message is truncated, the records that
+/// could not be read are left out.
+pub(crate) struct Message<'a> {
+    pub id: u16,
+    pub flags: u16,
+    pub questions: Vec<Question>,
+    pub answers: Vec<Record<'a>>,
+    pub authority: Vec<Record<'a>>,
+    pub additional: Vec<Record<'a>>,
+}
+
+impl<'a> Message<'a> {
+    pub fn parse(msg: &'a [u8]) -> Option<Self> {
+        let id = read_u16(msg, 0)?;
+        let flags = read_u16(msg, 2)?;
+        let counts = [
+            read_u16(msg, 4)?,
+            read_u16(msg, 6)?,
+            read_u16(msg, 8)?,
+            read_u16(msg, 10)?,
+        ];
+
+        let mut pos = 12;
+        let mut questions = Vec::new();
+        for _ in 0..counts[0] {
+            let (name, next) = read_name(msg, pos)?;
+            questions.push(Question {
+                name,
+                qtype: read_u16(msg, next)?,
+                class: read_u16(msg, next + 2)? & !CLASS_MDNS_BIT,
+            });
+            pos = next + 4;
+        }
+
+        let mut sections: [Vec<Record<'a>>; 3] = Default::default();
+        'sections: for (&count, records) in counts[1..].iter().zip(&mut sections) {
+            for _ in 0..count {
+                match read_record(msg, pos) {
+                    Some((record, next)) => {
+                        records.push(record);
+                        pos = next;
+                    }
+                    None if flags & FLAG_TC != 0 => break 'sections,
+                    None => return None,
+                }
+            }
+        }
+        let [answers, authority, additional] = sections;
+
+        Some(Self {
+            id,
+            flags,
+            questions,
+            answers,
+            authority,
+            additional,
+        })
+    }
+
+    pub fn is_response(&self) -> bool {
+        self.flags & FLAG_QR != 0
+    }
+
+    pub fn truncated(&self) -> bool {
+        self.flags & FLAG_TC != 0
+    }
+
+    pub fn rcode(&self) -> Rcode {
+        Rcode((self.flags & 0xf) as u8)
+    }
+
+    /// Whether the `AD` flag is set, the server having validated the answer with DNSSEC.
+    pub fn authenticated(&self) -> bool {
+        self.flags & FLAG_AD != 0
+    }
+
+    /// The info code of the first extended DNS error in the OPT record.
+    pub fn extended_error(&self) -> Option<u16> {
+        let opt = self.additional.iter().find(|r| r.rtype == TYPE_OPT)?;
+        let mut options = opt.rdata;
+        while options.len() >= 4 {
+            let code = read_u16(options, 0)?;
+            let len = read_u16(options, 2)? as usize;
+            let value = options.get(4..4 + len)?;
+            if code == OPT_EDE {
+                return read_u16(value, 0);
+            }
+            options = &options[4 + len..];
+        }
+        None
+    }
+}
+
+fn read_record(msg: &[u8], pos: usize) -> Option<(Record<'_>, usize)> {
+    let (name, pos) = read_name(msg, pos)?;
+    let len = read_u16(msg, pos + 8)? as usize;
+    let record = Record {
+        name,
+        rtype: read_u16(msg, pos)?,
+        class: read_u16(msg, pos + 2)? & !CLASS_MDNS_BIT,
+        ttl: read_u32(msg, pos + 4)?,
+        rdata: msg.get(pos + 10..pos + 10 + len)?,
+        rdata_pos: pos + 10,
+    };
+    Some((record, pos + 10 + len))
+}
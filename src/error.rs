@@ -0,0 +1,150 @@
+use std::io;
+
+use crate::AddressInfoError;
+
+/// Why a lookup failed, telling the failures worth trying again later from those that are not.
+///
+/// The lookups of this crate report [`io::Error`]s, which convert into this and back:
+/// `ResolveError::from(err)` reads the error a lookup returned, while failures this crate
+/// reports itself carry a `ResolveError` which [`io::Error::get_ref`] gives back.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ResolveError {
+    /// The lookup did not finish in time.
+    Timeout,
+    /// The name does not exist.
+    HostNotFound,
+    /// The name exists but has no address, none of the family asked for at least.
+    NoAddresses,
+    /// The resolver could not answer for now, a server failing or unreachable for instance.
+    TemporaryFailure,
+    /// Too many lookups were waiting already, see
+    /// [`PoolOverflow::FailFast`](crate::PoolOverflow::FailFast).
+    ResolverBusy,
+    /// The lookup was made after [`shutdown`](crate::shutdown()).
+    ShutDown,
+    /// The lookup asked for something the backend does not do.
+    Unsupported(io::Error),
+    /// The name, the service or the hints are not valid.
+    InvalidInput(io::Error),
+    /// Any other failure, as the backend reported it.
+    Backend(io::Error),
+}
+
+impl ResolveError {
+    /// Whether the same lookup may succeed if tried again.
+    pub fn is_temporary(&self) -> bool {
+        matches!(
+            self,
+            Self::Timeout | Self::TemporaryFailure | Self::ResolverBusy
+        )
+    }
+
+    /// A copy of an error carried by an [`io::Error`], those of the other variants staying
+    /// with the error they wrap.
+    pub(crate) fn duplicate(&self) -> Option<Self> {
+        match self {
+            Self::Timeout => Some(Self::Timeout),
+            Self::HostNotFound => Some(Self::HostNotFound),
+            Self::NoAddresses => Some(Self::NoAddresses),
+            Self::TemporaryFailure => Some(Self::TemporaryFailure),
+            Self::ResolverBusy => Some(Self::ResolverBusy),
+            Self::ShutDown => Some(Self::ShutDown),
+            Self::Unsupported(_) | Self::InvalidInput(_) | Self::Backend(_) => None,
+        }
+    }
+
+    /// The variant `err` reads into, in snake case, without taking it.
+    #[cfg_attr(not(feature = "metrics"), allow(dead_code))]
+    pub(crate) fn label_of(err: &io::Error) -> &'static str {
+        let carried = err.get_ref().and_then(|inner| {
+            inner
+                .downcast_ref::<Self>()
+                .and_then(Self::duplicate)
+                .or_else(|| {
+                    inner
+                        .downcast_ref::<AddressInfoError>()
+                        .and_then(|code| code.resolve_error())
+                })
+        });
+        match (carried, err.kind()) {
+            (Some(Self::Timeout), _) | (None, io::ErrorKind::TimedOut) => "timeout",
+            (Some(Self::HostNotFound), _) => "host_not_found",
+            (Some(Self::NoAddresses), _) => "no_addresses",
+            (Some(Self::TemporaryFailure), _) => "temporary_failure",
+            (Some(Self::ResolverBusy), _) => "resolver_busy",
+            (Some(Self::ShutDown), _) => "shut_down",
+            (_, io::ErrorKind::Unsupported) => "unsupported",
+            (_, io::ErrorKind::InvalidInput) => "invalid_input",
+            _ => "backend",
+        }
+    }
+}
+
+impl core::fmt::Display for ResolveError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Timeout => f.write_str("lookup timed out"),
+            Self::HostNotFound => f.write_str("host not found"),
+            Self::NoAddresses => f.write_str("no address associated with name"),
+            Self::TemporaryFailure => f.write_str("temporary failure in name resolution"),
+            Self::ResolverBusy => f.write_str("too many lookups waiting for the resolver"),
+            Self::ShutDown => f.write_str("resolver shut down"),
+            Self::Unsupported(err) | Self::InvalidInput(err) | Self::Backend(err) => {
+                core::fmt::Display::fmt(err, f)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ResolveError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Unsupported(err) | Self::InvalidInput(err) | Self::Backend(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+/// Reads the error of a lookup: the `ResolveError` it carries, or else one told by its kind.
+impl From<io::Error> for ResolveError {
+    fn from(err: io::Error) -> Self {
+        if err.get_ref().is_some_and(|inner| inner.is::<Self>()) {
+            let inner = err.into_inner().and_then(|inner| inner.downcast().ok());
+            return *inner.expect("checked to be a ResolveError");
+        }
+        if let Some(resolve) = err
+            .get_ref()
+            .and_then(|inner| inner.downcast_ref::<AddressInfoError>())
+            .and_then(|code| code.resolve_error())
+        {
+            return resolve;
+        }
+        match err.kind() {
+            io::ErrorKind::TimedOut => Self::Timeout,
+            io::ErrorKind::Unsupported => Self::Unsupported(err),
+            io::ErrorKind::InvalidInput => Self::InvalidInput(err),
+            _ => Self::Backend(err),
+        }
+    }
+}
+
+/// The wrapped error for the variants that have one, otherwise an error of the matching kind
+/// carrying `value`.
+impl From<ResolveError> for io::Error {
+    fn from(value: ResolveError) -> Self {
+        match value {
+            ResolveError::Timeout => io::ErrorKind::TimedOut.into(),
+            ResolveError::HostNotFound | ResolveError::NoAddresses => {
+                io::Error::new(io::ErrorKind::NotFound, value)
+            }
+            ResolveError::TemporaryFailure | ResolveError::ResolverBusy => {
+                io::Error::new(io::ErrorKind::ResourceBusy, value)
+            }
+            ResolveError::ShutDown => io::Error::other(value),
+            ResolveError::Unsupported(err)
+            | ResolveError::InvalidInput(err)
+            | ResolveError::Backend(err) => err,
+        }
+    }
+}
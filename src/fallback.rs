@@ -1,88 +1,151 @@
 use std::{
-    mem::MaybeUninit,
-    net::{Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6, ToSocketAddrs},
-    sync::mpsc::{self, RecvTimeoutError},
-    thread,
+    future::Future,
+    net::{SocketAddr, ToSocketAddrs},
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU8, Ordering},
+        Arc, OnceLock,
+    },
+    task::{ready, Context, Poll},
     time::Duration,
 };
 
-use crate::ToSocketAddrsTimeout;
+#[cfg(feature = "cache")]
+use std::sync::Mutex;
 
-fn resolve_timeout(
-    v: &str,
+#[cfg(feature = "cache")]
+use crate::cache;
+use crate::{
+    completion::Completion, future::block_on_deadline, hostaliases, orphan::Orphan, pool::Pool,
+    ResolverBackend,
+};
+
+static POOL: Pool = Pool::new("to_socket_addrs_timeout-fallback");
+
+const QUEUED: u8 = 0;
+const RUNNING: u8 = 1;
+const CANCELED: u8 = 2;
+
+/// State shared with the worker running the job, which holds a reference until it returns.
+struct Job {
+    host: String,
     port: u16,
-    timeout: Duration,
-) -> std::io::Result<std::vec::IntoIter<SocketAddr>> {
-    if v.len() > 253 {
-        return Err(std::io::Error::new(
-            std::io::ErrorKind::InvalidInput,
-            "invalid socket address",
-        ));
-    }
-    let (tx, rx) = mpsc::sync_channel(1);
-    {
-        let mut buffer = MaybeUninit::<[u8; 253]>::uninit();
-        let len = v.len();
-        let buffer = unsafe {
-            (*buffer.as_mut_ptr())
-                .get_unchecked_mut(..len)
-                .copy_from_slice(v.as_bytes());
-            buffer.assume_init()
-        };
-        thread::spawn(move || {
-            let v = unsafe { std::str::from_utf8_unchecked(buffer.get_unchecked(..len)) };
-            tx.send((v, port).to_socket_addrs())
-        });
-    }
-    match rx.recv_timeout(timeout) {
-        Ok(v) => v,
-        Err(c) => match c {
-            RecvTimeoutError::Timeout => Err(std::io::ErrorKind::TimedOut.into()),
-            RecvTimeoutError::Disconnected => unreachable!(),
-        },
+    state: AtomicU8,
+    completion: Completion<std::io::Result<std::vec::IntoIter<SocketAddr>>>,
+    orphan: OnceLock<Orphan>,
+    /// Where the addresses go if the caller timed out, see [`cache::take_late_sink`].
+    #[cfg(feature = "cache")]
+    late: Mutex<Option<cache::LateSink>>,
+}
+
+impl Job {
+    fn run(&self) {
+        if self
+            .state
+            .compare_exchange(QUEUED, RUNNING, Ordering::AcqRel, Ordering::Acquire)
+            .is_ok()
+        {
+            let result = (self.host.as_str(), self.port).to_socket_addrs();
+            #[cfg(feature = "cache")]
+            let addrs = result.as_ref().ok().map(|addrs| addrs.as_slice().to_vec());
+            self.completion.complete(result);
+            #[cfg(feature = "cache")]
+            if let Some(addrs) = addrs {
+                let late = self.late.lock().unwrap_or_else(|e| e.into_inner()).take();
+                if let Some(sink) = late {
+                    sink(addrs);
+                }
+            }
+        }
     }
 }
 
-impl ToSocketAddrsTimeout for str {
-    type Iter = std::vec::IntoIter<SocketAddr>;
+/// Lookup queued on the worker pool.
+///
+/// Dropping it before a worker picks it up removes it from the pool's work, once running it can
+/// only be abandoned.
+pub(crate) struct Lookup {
+    job: Arc<Job>,
+    done: bool,
+}
 
-    fn to_socket_addrs_timeout(
-        &self,
-        timeout: Duration,
-    ) -> std::io::Result<std::vec::IntoIter<SocketAddr>> {
-        if let Ok(addr) = self.parse() {
-            return Ok(vec![addr].into_iter());
-        }
+impl Future for Lookup {
+    type Output = std::io::Result<std::vec::IntoIter<SocketAddr>>;
 
-        let (host, port_str) = self.rsplit_once(':').ok_or_else(|| {
-            std::io::Error::new(std::io::ErrorKind::InvalidInput, "invalid socket address")
-        })?;
-        let port: u16 = port_str.parse().map_err(|_| {
-            std::io::Error::new(std::io::ErrorKind::InvalidInput, "invalid port value")
-        })?;
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let res = ready!(self.job.completion.poll(cx));
+        self.done = true;
+        Poll::Ready(res)
+    }
+}
 
-        resolve_timeout(host, port, timeout)
+impl Drop for Lookup {
+    fn drop(&mut self) {
+        if !self.done
+            && self
+                .job
+                .state
+                .compare_exchange(QUEUED, CANCELED, Ordering::AcqRel, Ordering::Acquire)
+                .is_err()
+        {
+            // the worker can not be stopped, it releases the orphan when the lookup returns
+            self.job.orphan.get_or_init(Orphan::new);
+        }
     }
 }
 
-impl ToSocketAddrsTimeout for (&str, u16) {
-    type Iter = std::vec::IntoIter<SocketAddr>;
+pub(crate) fn lookup(v: &str, port: u16, timeout: Option<Duration>) -> std::io::Result<Lookup> {
+    let job = Arc::new(Job {
+        host: hostaliases::resolve(v).unwrap_or_else(|| v.to_owned()),
+        port,
+        state: AtomicU8::new(QUEUED),
+        completion: Completion::new(),
+        orphan: OnceLock::new(),
+        #[cfg(feature = "cache")]
+        late: Mutex::new(None),
+    });
+    POOL.execute(
+        {
+            let job = job.clone();
+            move || job.run()
+        },
+        timeout,
+    )?;
+    Ok(Lookup { job, done: false })
+}
 
-    fn to_socket_addrs_timeout(
-        &self,
-        timeout: Duration,
-    ) -> ::std::io::Result<std::vec::IntoIter<SocketAddr>> {
-        let (host, port) = *self;
+/// The thread based lookup cannot select an address family, so there is nothing to split.
+pub(crate) fn lookup_split(
+    v: &str,
+    port: u16,
+    timeout: Option<Duration>,
+) -> std::io::Result<Vec<Lookup>> {
+    Ok(vec![lookup(v, port, timeout)?])
+}
 
-        if let Ok(addr) = host.parse::<Ipv4Addr>() {
-            let addr = SocketAddrV4::new(addr, port);
-            return Ok(vec![SocketAddr::V4(addr)].into_iter());
-        }
-        if let Ok(addr) = host.parse::<Ipv6Addr>() {
-            let addr = SocketAddrV6::new(addr, port, 0, 0);
-            return Ok(vec![SocketAddr::V6(addr)].into_iter());
-        }
+/// The platform resolver: std's `ToSocketAddrs` on a bounded pool of worker threads, abandoned
+/// on timeout.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemResolver;
 
-        resolve_timeout(host, port, timeout)
+impl ResolverBackend for SystemResolver {
+    fn resolve(
+        &self,
+        host: &str,
+        port: u16,
+        timeout: Duration,
+    ) -> std::io::Result<std::vec::IntoIter<SocketAddr>> {
+        #[cfg(feature = "cache")]
+        let late = cache::take_late_sink();
+        let deadline = crate::deadline_after(timeout);
+        let mut lookup = lookup(host, port, Some(timeout))?;
+        block_on_deadline(&mut lookup, deadline).unwrap_or_else(|| {
+            // the worker hands what it finds to the cache, for the next lookup to find
+            #[cfg(feature = "cache")]
+            if let Some(sink) = late {
+                *lookup.job.late.lock().unwrap_or_else(|e| e.into_inner()) = Some(sink);
+            }
+            Err(std::io::ErrorKind::TimedOut.into())
+        })
     }
 }
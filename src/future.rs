@@ -0,0 +1,147 @@
+use std::{
+    future::Future,
+    io,
+    net::SocketAddr,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll, Wake, Waker},
+    thread::{self, Thread},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    hooks::{LookupHooks, Observation},
+    timer::Sleep,
+};
+
+struct ThreadWaker(Thread);
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+/// Waker unparking the current thread.
+pub(crate) fn thread_waker() -> Waker {
+    Waker::from(Arc::new(ThreadWaker(thread::current())))
+}
+
+/// Polls `fut` on the current thread until it completes or `deadline` passes.
+pub(crate) fn block_on_deadline<F: Future + Unpin>(
+    fut: &mut F,
+    deadline: Instant,
+) -> Option<F::Output> {
+    block_on_deadline_with(fut, deadline, &thread_waker())
+}
+
+/// [`block_on_deadline`] with `waker`, which has to unpark the current thread.
+pub(crate) fn block_on_deadline_with<F: Future + Unpin>(
+    fut: &mut F,
+    deadline: Instant,
+    waker: &Waker,
+) -> Option<F::Output> {
+    let mut cx = Context::from_waker(waker);
+    loop {
+        if let Poll::Ready(res) = Pin::new(&mut *fut).poll(&mut cx) {
+            return Some(res);
+        }
+        thread::park_timeout(deadline.checked_duration_since(Instant::now())?);
+    }
+}
+
+/// Runtime agnostic future resolving a host name with a timeout.
+///
+/// The lookup is driven by the platform completion notification, which wakes the registered
+/// [`Waker`](std::task::Waker), so it can be polled by any executor. Dropping the future cancels
+/// the underlying query where the platform allows it.
+#[must_use = "futures do nothing unless polled"]
+pub struct ResolveFuture {
+    state: State,
+    sleep: Sleep,
+    observation: Option<Observation>,
+}
+
+enum State {
+    Ready(Option<io::Result<std::vec::IntoIter<SocketAddr>>>),
+    Pending(crate::sys::Lookup),
+}
+
+impl ResolveFuture {
+    /// Starts resolving `host`, giving up once `timeout` has elapsed.
+    pub fn new(host: &str, port: u16, timeout: Duration) -> Self {
+        let sleep = Sleep::until(crate::deadline_after(timeout));
+
+        if let Some(addr) = crate::literal(host, port) {
+            return Self::ready(Ok(vec![addr].into_iter()), sleep);
+        }
+
+        let host = crate::hostname::to_ascii(host);
+        if let Err(err) = crate::shutdown::check() {
+            return Self::ready(Err(err), sleep);
+        }
+        let observation = Observation::start(&LookupHooks::NONE, crate::BACKEND, &host, timeout);
+        let started = crate::HostnameSyntax::Strict
+            .check(&host)
+            .map_err(Into::into)
+            .and_then(|()| {
+                if timeout.is_zero() {
+                    return Err(crate::resolver::would_block());
+                }
+                crate::sys::lookup(crate::hostname::without_root(&host), port, Some(timeout))
+            });
+        match started {
+            Ok(lookup) => Self {
+                state: State::Pending(lookup),
+                sleep,
+                observation,
+            },
+            Err(err) => {
+                if let Some(observation) = observation {
+                    observation.finish(Err(&err));
+                }
+                Self::ready(Err(err), sleep)
+            }
+        }
+    }
+
+    fn ready(res: io::Result<std::vec::IntoIter<SocketAddr>>, sleep: Sleep) -> Self {
+        Self {
+            state: State::Ready(Some(res)),
+            sleep,
+            observation: None,
+        }
+    }
+}
+
+impl Future for ResolveFuture {
+    type Output = io::Result<std::vec::IntoIter<SocketAddr>>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = &mut *self;
+        let lookup = match this.state {
+            State::Ready(ref mut res) => {
+                return Poll::Ready(res.take().expect("ResolveFuture polled after completion"))
+            }
+            State::Pending(ref mut lookup) => lookup,
+        };
+
+        let res = if let Poll::Ready(res) = Pin::new(lookup).poll(cx) {
+            res
+        } else if Pin::new(&mut this.sleep).poll(cx).is_ready() {
+            Err(io::ErrorKind::TimedOut.into())
+        } else {
+            return Poll::Pending;
+        };
+        // dropping a lookup that timed out cancels it
+        this.state = State::Ready(None);
+        if let Some(observation) = this.observation.take() {
+            observation.finish(res.as_ref().map(ExactSizeIterator::len));
+        }
+        Poll::Ready(res)
+    }
+}
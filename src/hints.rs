@@ -0,0 +1,354 @@
+use std::{
+    io,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV6, UdpSocket},
+    ops::{BitOr, BitOrAssign},
+    time::Duration,
+};
+
+use crate::{ResolvedAddr, ResolverBackend};
+
+/// Address family to look up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Family {
+    /// Both IPv4 and IPv6 addresses.
+    #[default]
+    Unspecified,
+    Ipv4,
+    Ipv6,
+}
+
+/// Which address families a [`Resolver`](crate::Resolver) returns, and in which order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum FamilyPreference {
+    /// Both families, in the order the backend gives them.
+    #[default]
+    Unspecified,
+    /// Only IPv4 addresses, the only ones asked for.
+    V4Only,
+    /// Only IPv6 addresses, the only ones asked for.
+    V6Only,
+    /// Both families, IPv4 addresses first.
+    PreferV4,
+    /// Both families, IPv6 addresses first.
+    PreferV6,
+    /// Both families, alternating between IPv6 and IPv4 as
+    /// [`interleave_families`](crate::AddrIterExt::interleave_families) does.
+    Interleave,
+}
+
+impl FamilyPreference {
+    /// `hints` asking for the only family wanted, unless they ask for one already.
+    pub(crate) fn apply(self, hints: &Hints) -> Hints {
+        let family = match (hints.family, self) {
+            (Family::Unspecified, Self::V4Only) => Family::Ipv4,
+            (Family::Unspecified, Self::V6Only) => Family::Ipv6,
+            (family, _) => family,
+        };
+        Hints { family, ..*hints }
+    }
+
+    /// Puts `items` in the order of the preference, keeping the order within each family.
+    pub(crate) fn sort<T>(self, items: &mut Vec<T>, addr: impl Fn(&T) -> SocketAddr) {
+        match self {
+            Self::PreferV4 => items.sort_by_key(|item| !addr(item).is_ipv4()),
+            Self::PreferV6 => items.sort_by_key(|item| !addr(item).is_ipv6()),
+            Self::Interleave => {
+                let ranks = crate::order::interleaved_ranks(items.iter().map(&addr));
+                let mut ranked: Vec<_> = ranks.into_iter().zip(items.drain(..)).collect();
+                ranked.sort_by_key(|&(rank, _)| rank);
+                items.extend(ranked.into_iter().map(|(_, item)| item));
+            }
+            Self::Unspecified | Self::V4Only | Self::V6Only => {}
+        }
+    }
+}
+
+/// Socket type the addresses are for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum SocketType {
+    /// Any type; platform resolvers may then report each address once per type.
+    Any,
+    #[default]
+    Stream,
+    Datagram,
+    Raw,
+}
+
+/// Transport protocol the addresses are for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Protocol {
+    /// Any protocol matching the socket type.
+    #[default]
+    Any,
+    Tcp,
+    Udp,
+}
+
+/// `getaddrinfo` flags, translated to each platform's values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct HintFlags(u8);
+
+impl HintFlags {
+    /// `AI_PASSIVE`: addresses for binding rather than connecting.
+    pub const PASSIVE: Self = Self(1 << 0);
+    /// `AI_CANONNAME`: ask for the canonical name of the host.
+    pub const CANONNAME: Self = Self(1 << 1);
+    /// `AI_NUMERICHOST`: never query name services.
+    pub const NUMERICHOST: Self = Self(1 << 2);
+    /// `AI_NUMERICSERV`: the service is a port number.
+    pub const NUMERICSERV: Self = Self(1 << 3);
+    /// `AI_V4MAPPED`: with [`Family::Ipv6`], IPv4 addresses mapped to IPv6 when there are no
+    /// IPv6 ones.
+    pub const V4MAPPED: Self = Self(1 << 4);
+    /// `AI_ALL`: with [`V4MAPPED`](Self::V4MAPPED), mapped IPv4 addresses in any case.
+    pub const ALL: Self = Self(1 << 5);
+    /// `AI_ADDRCONFIG`: only families configured on a local interface.
+    pub const ADDRCONFIG: Self = Self(1 << 6);
+    /// `AI_DISABLE_IDN_ENCODING`: on Windows, names are looked up as given rather than
+    /// converted to Punycode by `GetAddrInfoExW`. Other platform resolvers never convert them.
+    ///
+    /// With the `idna` feature names reach the resolver already converted by this crate, the
+    /// same on every platform, and the flag is always set.
+    pub const DISABLE_IDN_ENCODING: Self = Self(1 << 7);
+
+    /// No flags.
+    pub const fn empty() -> Self {
+        Self(0)
+    }
+
+    /// Whether all of `other` is set.
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// ORs the platform values `table` pairs with the flags set.
+    #[cfg_attr(
+        not(any(
+            backend = "c_ares",
+            backend = "illumos",
+            backend = "linux_glibc",
+            backend = "windows"
+        )),
+        allow(dead_code)
+    )]
+    pub(crate) fn to_native<T: BitOr<Output = T> + Copy + Default>(self, table: &[(Self, T)]) -> T {
+        table
+            .iter()
+            .filter(|&&(flag, _)| self.contains(flag))
+            .fold(T::default(), |acc, &(_, value)| acc | value)
+    }
+
+    /// The flags whose platform values in `table` are set in `value`.
+    #[cfg(any(backend = "linux_glibc", backend = "windows"))]
+    pub(crate) fn from_native<T: std::ops::BitAnd<Output = T> + PartialEq + Copy + Default>(
+        value: T,
+        table: &[(Self, T)],
+    ) -> Self {
+        table
+            .iter()
+            .filter(|&&(_, native)| value & native != T::default())
+            .fold(Self::empty(), |acc, &(flag, _)| acc | flag)
+    }
+}
+
+impl BitOr for HintFlags {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for HintFlags {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+/// Per-call lookup options, the portable counterpart of the `addrinfo` hints given to
+/// `getaddrinfo`.
+///
+/// The default looks up addresses of any family for stream sockets, like
+/// [`to_socket_addrs_timeout`](crate::ToSocketAddrsTimeout::to_socket_addrs_timeout).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Hints {
+    pub family: Family,
+    pub socktype: SocketType,
+    pub protocol: Protocol,
+    pub flags: HintFlags,
+}
+
+impl Hints {
+    /// Keeps the addresses of the requested family, for resolvers that cannot be asked for one,
+    /// and emulates [`HintFlags::ADDRCONFIG`], [`HintFlags::V4MAPPED`] and [`HintFlags::ALL`].
+    pub(crate) fn filter(
+        &self,
+        addrs: std::vec::IntoIter<SocketAddr>,
+    ) -> std::vec::IntoIter<SocketAddr> {
+        let (mut v4, mut v6) = match self.family {
+            Family::Unspecified => (true, true),
+            Family::Ipv4 => (true, false),
+            Family::Ipv6 => (false, true),
+        };
+        if self.flags.contains(HintFlags::ADDRCONFIG) {
+            v4 = v4 && routable(SocketAddr::new(Ipv4Addr::new(192, 0, 2, 1).into(), 53));
+            v6 = v6
+                && routable(SocketAddr::new(
+                    Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1).into(),
+                    53,
+                ));
+        }
+        let mapped = self.family == Family::Ipv6 && self.flags.contains(HintFlags::V4MAPPED);
+        if v4 && v6 && !mapped {
+            return addrs;
+        }
+
+        let (found_v4, found_v6): (Vec<_>, Vec<_>) = addrs.partition(SocketAddr::is_ipv4);
+        let mut kept = if v6 { found_v6 } else { Vec::new() };
+        if v4 {
+            kept.extend(found_v4);
+        } else if mapped && (kept.is_empty() || self.flags.contains(HintFlags::ALL)) {
+            kept.extend(found_v4.into_iter().filter_map(|addr| match addr {
+                SocketAddr::V4(addr) => Some(SocketAddr::V6(SocketAddrV6::new(
+                    addr.ip().to_ipv6_mapped(),
+                    addr.port(),
+                    0,
+                    0,
+                ))),
+                SocketAddr::V6(_) => None,
+            }));
+        }
+        kept.into_iter()
+    }
+
+    /// [`filter`](Self::filter) for addresses with TTLs, mapped IPv4 addresses keeping theirs.
+    pub(crate) fn filter_resolved(&self, resolved: Vec<ResolvedAddr>) -> Vec<ResolvedAddr> {
+        let ttl_of = |addr: SocketAddr| {
+            let unmapped = match addr {
+                SocketAddr::V6(v6) => v6
+                    .ip()
+                    .to_ipv4_mapped()
+                    .map(|ip| SocketAddr::new(ip.into(), v6.port())),
+                SocketAddr::V4(_) => None,
+            };
+            resolved
+                .iter()
+                .find(|r| r.addr == addr || Some(r.addr) == unmapped)
+                .and_then(|r| r.ttl)
+        };
+        let addrs: Vec<_> = resolved.iter().map(|r| r.addr).collect();
+        self.filter(addrs.into_iter())
+            .map(|addr| ResolvedAddr {
+                addr,
+                ttl: ttl_of(addr),
+            })
+            .collect()
+    }
+
+    /// The services file protocol the socket type and protocol ask for.
+    pub(crate) fn service_proto(&self) -> &'static str {
+        match (self.socktype, self.protocol) {
+            (_, Protocol::Udp) | (SocketType::Datagram, _) => "udp",
+            _ => "tcp",
+        }
+    }
+}
+
+/// Error for a host that is not an IP literal when only those are accepted.
+pub(crate) fn not_numeric() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, "host is not a numeric address")
+}
+
+/// Whether the host has a route to `probe`, standing in for an address of its family being
+/// configured; connecting a UDP socket sends nothing.
+fn routable(probe: SocketAddr) -> bool {
+    let local: IpAddr = match probe {
+        SocketAddr::V4(_) => Ipv4Addr::UNSPECIFIED.into(),
+        SocketAddr::V6(_) => Ipv6Addr::UNSPECIFIED.into(),
+    };
+    match UdpSocket::bind((local, 0)).and_then(|socket| socket.connect(probe)) {
+        Ok(()) => true,
+        // nothing to tell from, keep the family
+        Err(e) => e.kind() == io::ErrorKind::Unsupported,
+    }
+}
+
+#[cfg(any(backend = "c_ares", backend = "illumos", backend = "linux_glibc"))]
+impl Hints {
+    pub(crate) fn libc_family(&self) -> ::core::ffi::c_int {
+        match self.family {
+            Family::Unspecified => libc::AF_UNSPEC,
+            Family::Ipv4 => libc::AF_INET,
+            Family::Ipv6 => libc::AF_INET6,
+        }
+    }
+
+    pub(crate) fn libc_socktype(&self) -> ::core::ffi::c_int {
+        match self.socktype {
+            SocketType::Any => 0,
+            SocketType::Stream => libc::SOCK_STREAM,
+            SocketType::Datagram => libc::SOCK_DGRAM,
+            SocketType::Raw => libc::SOCK_RAW,
+        }
+    }
+
+    pub(crate) fn libc_protocol(&self) -> ::core::ffi::c_int {
+        match self.protocol {
+            Protocol::Any => 0,
+            Protocol::Tcp => libc::IPPROTO_TCP,
+            Protocol::Udp => libc::IPPROTO_UDP,
+        }
+    }
+}
+
+#[cfg(backend = "linux_glibc")]
+impl SocketType {
+    pub(crate) fn from_libc(socktype: ::core::ffi::c_int) -> Self {
+        match socktype {
+            libc::SOCK_STREAM => Self::Stream,
+            libc::SOCK_DGRAM => Self::Datagram,
+            libc::SOCK_RAW => Self::Raw,
+            _ => Self::Any,
+        }
+    }
+}
+
+#[cfg(backend = "linux_glibc")]
+impl Protocol {
+    pub(crate) fn from_libc(protocol: ::core::ffi::c_int) -> Self {
+        match protocol {
+            libc::IPPROTO_TCP => Self::Tcp,
+            libc::IPPROTO_UDP => Self::Udp,
+            _ => Self::Any,
+        }
+    }
+}
+
+/// `backend` with `hints` applied to every lookup.
+pub(crate) struct WithHints<'a> {
+    pub backend: &'a dyn ResolverBackend,
+    pub hints: &'a Hints,
+}
+
+impl ResolverBackend for WithHints<'_> {
+    fn resolve(
+        &self,
+        host: &str,
+        port: u16,
+        timeout: Duration,
+    ) -> io::Result<std::vec::IntoIter<SocketAddr>> {
+        self.backend
+            .resolve_with_hints(host, port, self.hints, timeout)
+    }
+
+    fn resolve_service(
+        &self,
+        host: &str,
+        service: &str,
+        _hints: &Hints,
+        timeout: Duration,
+    ) -> io::Result<std::vec::IntoIter<SocketAddr>> {
+        self.backend
+            .resolve_service(host, service, self.hints, timeout)
+    }
+}
@@ -0,0 +1,291 @@
+use std::{
+    io,
+    net::SocketAddr,
+    ops::Deref,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, RwLock,
+    },
+    time::{Duration, Instant},
+};
+
+use crate::{CanonicalAddrs, Hints, ResolvedAddr, ResolverBackend};
+
+/// A lookup about to start, as [`LookupHooks::on_lookup_start`] sees it.
+#[derive(Debug, Clone, Copy)]
+#[non_exhaustive]
+pub struct LookupStart<'a> {
+    pub host: &'a str,
+    pub timeout: Duration,
+}
+
+/// A lookup that finished, as [`LookupHooks::on_lookup_complete`] sees it.
+#[derive(Debug, Clone, Copy)]
+#[non_exhaustive]
+pub struct LookupComplete<'a> {
+    pub host: &'a str,
+    pub timeout: Duration,
+    /// Time the lookup took.
+    pub elapsed: Duration,
+    /// How many addresses were found, or why none were.
+    pub outcome: Result<usize, &'a io::Error>,
+}
+
+type StartHook = dyn Fn(&LookupStart<'_>) + Send + Sync;
+
+type CompleteHook = dyn Fn(&LookupComplete<'_>) + Send + Sync;
+
+/// Callbacks run around the lookups of host names, to feed logs or metrics, set on a
+/// [`Resolver`](crate::Resolver) with [`ResolverBuilder::hooks`](crate::ResolverBuilder::hooks)
+/// or for the whole process with [`set_global_hooks`].
+///
+/// They run on the thread making the lookup, those of the resolver before the global ones;
+/// IP literals, which are not looked up, do not trigger them. The global hooks see the lookups
+/// of the [`ToSocketAddrsTimeout`](crate::ToSocketAddrsTimeout) impls too, [`Resolution`]s
+/// and the futures included, whose completion hooks run where the lookup is found complete or
+/// timed out, and not at all for one dropped before that; a [`Resolution`] has no timeout of
+/// its own and reports [`Duration::MAX`]. Batches and streams are not observed.
+///
+/// [`Resolution`]: crate::Resolution
+#[derive(Clone, Default)]
+pub struct LookupHooks {
+    start: Option<Arc<StartHook>>,
+    complete: Option<Arc<CompleteHook>>,
+}
+
+impl LookupHooks {
+    pub(crate) const NONE: Self = Self {
+        start: None,
+        complete: None,
+    };
+
+    /// No hooks.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Runs `hook` before each lookup.
+    pub fn on_lookup_start(
+        mut self,
+        hook: impl Fn(&LookupStart<'_>) + Send + Sync + 'static,
+    ) -> Self {
+        self.start = Some(Arc::new(hook));
+        self
+    }
+
+    /// Runs `hook` after each lookup, whether it succeeded or not.
+    pub fn on_lookup_complete(
+        mut self,
+        hook: impl Fn(&LookupComplete<'_>) + Send + Sync + 'static,
+    ) -> Self {
+        self.complete = Some(Arc::new(hook));
+        self
+    }
+
+    fn is_empty(&self) -> bool {
+        self.start.is_none() && self.complete.is_none()
+    }
+
+    fn started(&self, event: &LookupStart<'_>) {
+        if let Some(hook) = &self.start {
+            hook(event);
+        }
+    }
+
+    fn completed(&self, event: &LookupComplete<'_>) {
+        if let Some(hook) = &self.complete {
+            hook(event);
+        }
+    }
+}
+
+impl core::fmt::Debug for LookupHooks {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("LookupHooks")
+            .field("on_lookup_start", &self.start.is_some())
+            .field("on_lookup_complete", &self.complete.is_some())
+            .finish()
+    }
+}
+
+static GLOBAL: RwLock<LookupHooks> = RwLock::new(LookupHooks::NONE);
+
+/// Whether [`GLOBAL`] holds any hook, sparing the lock to lookups without hooks.
+static GLOBAL_SET: AtomicBool = AtomicBool::new(false);
+
+/// Sets the hooks run around the lookups of the whole process, replacing those set before,
+/// see [`LookupHooks`].
+pub fn set_global_hooks(hooks: LookupHooks) {
+    let mut global = GLOBAL.write().unwrap_or_else(|e| e.into_inner());
+    GLOBAL_SET.store(!hooks.is_empty(), Ordering::Release);
+    *global = hooks;
+}
+
+/// Runs `lookup` of `host` by `backend` between the hooks of `hooks` and the global ones,
+/// `found` counting the addresses it returns, and records it with the `metrics` feature.
+pub(crate) fn observe<T>(
+    hooks: &LookupHooks,
+    backend: &'static str,
+    host: &str,
+    timeout: Duration,
+    found: impl FnOnce(&T) -> usize,
+    lookup: impl FnOnce() -> io::Result<T>,
+) -> io::Result<T> {
+    crate::shutdown::check()?;
+    let Some(observation) = Observation::start(hooks, backend, host, timeout) else {
+        return lookup();
+    };
+    let result = lookup();
+    observation.finish(result.as_ref().map(found));
+    result
+}
+
+/// A lookup whose start hooks ran, for lookups completing after the call starting them, whose
+/// completion hooks run and metrics are recorded by [`Observation::finish`]; one dropped before
+/// that is not recorded.
+pub(crate) struct Observation {
+    hooks: LookupHooks,
+    global: LookupHooks,
+    #[cfg_attr(not(feature = "metrics"), allow(dead_code))]
+    backend: &'static str,
+    host: String,
+    timeout: Duration,
+    began: Instant,
+}
+
+impl Observation {
+    /// Runs the start hooks of `hooks` and the global ones for `host`, or returns `None` when
+    /// there is neither a hook nor the `metrics` feature to observe the lookup.
+    pub fn start(
+        hooks: &LookupHooks,
+        backend: &'static str,
+        host: &str,
+        timeout: Duration,
+    ) -> Option<Self> {
+        let global = if GLOBAL_SET.load(Ordering::Acquire) {
+            GLOBAL.read().unwrap_or_else(|e| e.into_inner()).clone()
+        } else if hooks.is_empty() && !cfg!(feature = "metrics") {
+            return None;
+        } else {
+            LookupHooks::NONE
+        };
+
+        let start = LookupStart { host, timeout };
+        hooks.started(&start);
+        global.started(&start);
+        Some(Self {
+            hooks: hooks.clone(),
+            global,
+            backend,
+            host: host.to_owned(),
+            timeout,
+            began: Instant::now(),
+        })
+    }
+
+    /// Runs the completion hooks with `outcome`, the number of addresses found or the error.
+    pub fn finish(self, outcome: Result<usize, &io::Error>) {
+        let complete = LookupComplete {
+            host: &self.host,
+            timeout: self.timeout,
+            elapsed: self.began.elapsed(),
+            outcome,
+        };
+        self.hooks.completed(&complete);
+        self.global.completed(&complete);
+        #[cfg(feature = "metrics")]
+        crate::lookup_metrics::record(self.backend, complete.elapsed, outcome.err());
+    }
+}
+
+/// `inner`, named `backend`, with its lookups run between `hooks` and the global hooks.
+pub(crate) struct Hooked<B> {
+    pub inner: B,
+    pub backend: &'static str,
+    pub hooks: LookupHooks,
+}
+
+impl<B> ResolverBackend for Hooked<B>
+where
+    B: Deref + Send + Sync,
+    B::Target: ResolverBackend,
+{
+    fn resolve(
+        &self,
+        host: &str,
+        port: u16,
+        timeout: Duration,
+    ) -> io::Result<std::vec::IntoIter<SocketAddr>> {
+        observe(
+            &self.hooks,
+            self.backend,
+            host,
+            timeout,
+            ExactSizeIterator::len,
+            || self.inner.resolve(host, port, timeout),
+        )
+    }
+
+    fn resolve_with_hints(
+        &self,
+        host: &str,
+        port: u16,
+        hints: &Hints,
+        timeout: Duration,
+    ) -> io::Result<std::vec::IntoIter<SocketAddr>> {
+        observe(
+            &self.hooks,
+            self.backend,
+            host,
+            timeout,
+            ExactSizeIterator::len,
+            || self.inner.resolve_with_hints(host, port, hints, timeout),
+        )
+    }
+
+    fn resolve_service(
+        &self,
+        host: &str,
+        service: &str,
+        hints: &Hints,
+        timeout: Duration,
+    ) -> io::Result<std::vec::IntoIter<SocketAddr>> {
+        observe(
+            &self.hooks,
+            self.backend,
+            host,
+            timeout,
+            ExactSizeIterator::len,
+            || self.inner.resolve_service(host, service, hints, timeout),
+        )
+    }
+
+    fn resolve_canonical(
+        &self,
+        host: &str,
+        port: u16,
+        hints: &Hints,
+        timeout: Duration,
+    ) -> io::Result<CanonicalAddrs> {
+        observe(
+            &self.hooks,
+            self.backend,
+            host,
+            timeout,
+            |found: &CanonicalAddrs| found.addrs.len(),
+            || self.inner.resolve_canonical(host, port, hints, timeout),
+        )
+    }
+
+    fn resolve_with_ttl(
+        &self,
+        host: &str,
+        port: u16,
+        hints: &Hints,
+        timeout: Duration,
+    ) -> io::Result<Vec<ResolvedAddr>> {
+        observe(&self.hooks, self.backend, host, timeout, Vec::len, || {
+            self.inner.resolve_with_ttl(host, port, hints, timeout)
+        })
+    }
+}
@@ -0,0 +1,23 @@
+/// Maps a single label `name` through the file named by `HOSTALIASES`, as glibc does.
+///
+/// Each line of the file holds an alias and the name it stands for; aliases are compared
+/// case-insensitively. The file is read again for every lookup.
+pub(crate) fn resolve(name: &str) -> Option<String> {
+    if name.contains('.') {
+        return None;
+    }
+    let path = std::env::var_os("HOSTALIASES")?;
+    lookup(&std::fs::read_to_string(path).ok()?, name)
+}
+
+fn lookup(text: &str, name: &str) -> Option<String> {
+    text.lines().find_map(|line| {
+        let mut words = line.split_ascii_whitespace();
+        let alias = words.next()?;
+        if alias.eq_ignore_ascii_case(name) {
+            words.next().map(str::to_owned)
+        } else {
+            None
+        }
+    })
+}
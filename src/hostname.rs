@@ -0,0 +1,210 @@
+use std::{borrow::Cow, io};
+
+/// Longest host name, that of a DNS name in presentation format without its trailing dot.
+const MAX_LEN: usize = 253;
+
+/// Longest label of a host name.
+const MAX_LABEL_LEN: usize = 63;
+
+/// A host name rejected before any lookup for not following the syntax of RFC 1123: labels of
+/// letters, digits and inner hyphens, up to 63 characters each and 253 in all. See
+/// [`HostnameSyntax`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum InvalidHostname {
+    /// A name longer than 253 characters, without its trailing dot.
+    TooLong(usize),
+    /// An empty name, or one with an empty label: a leading dot or two dots in a row.
+    EmptyLabel,
+    /// A label longer than 63 characters.
+    LabelTooLong(String),
+    /// A label holding something other than a letter, a digit, a hyphen or, with
+    /// [`HostnameSyntax::AllowUnderscores`], an underscore.
+    InvalidCharacter { label: String, character: char },
+    /// A label starting or ending with a hyphen.
+    Hyphen(String),
+}
+
+impl core::fmt::Display for InvalidHostname {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::TooLong(len) => write!(f, "host name of {len} characters, over {MAX_LEN}"),
+            Self::EmptyLabel => f.write_str("host name with an empty label"),
+            Self::LabelTooLong(label) => {
+                write!(
+                    f,
+                    "host name label `{label}` over {MAX_LABEL_LEN} characters"
+                )
+            }
+            Self::InvalidCharacter { label, character } => {
+                write!(f, "host name label `{label}` holds {character:?}")
+            }
+            Self::Hyphen(label) => {
+                write!(f, "host name label `{label}` starts or ends with a hyphen")
+            }
+        }
+    }
+}
+
+impl std::error::Error for InvalidHostname {}
+
+impl From<InvalidHostname> for io::Error {
+    fn from(value: InvalidHostname) -> Self {
+        io::Error::new(io::ErrorKind::InvalidInput, value)
+    }
+}
+
+/// How strictly host names are checked before they are looked up, see
+/// [`ResolverBuilder::hostname_syntax`](crate::ResolverBuilder::hostname_syntax).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[non_exhaustive]
+pub enum HostnameSyntax {
+    /// Host names as RFC 1123 has them.
+    #[default]
+    Strict,
+    /// RFC 1123 host names whose labels may also hold underscores, as SRV-style names
+    /// (`_sip._tcp.example.com`) and some internal names do.
+    AllowUnderscores,
+    /// Any name, left for the backend to reject.
+    Any,
+}
+
+impl HostnameSyntax {
+    /// Checks `host`, a name rather than an IP literal, a trailing dot allowed.
+    pub(crate) fn check(self, host: &str) -> Result<(), InvalidHostname> {
+        if self == Self::Any {
+            return Ok(());
+        }
+        let name = host.strip_suffix('.').unwrap_or(host);
+        if name.len() > MAX_LEN {
+            return Err(InvalidHostname::TooLong(name.len()));
+        }
+        let underscores = self == Self::AllowUnderscores;
+        name.split('.')
+            .try_for_each(|label| validate_label(label, underscores))
+    }
+}
+
+fn validate_label(label: &str, underscores: bool) -> Result<(), InvalidHostname> {
+    if label.is_empty() {
+        return Err(InvalidHostname::EmptyLabel);
+    }
+    if label.len() > MAX_LABEL_LEN {
+        return Err(InvalidHostname::LabelTooLong(label.to_owned()));
+    }
+    if let Some(character) = label
+        .chars()
+        .find(|&c| !(c.is_ascii_alphanumeric() || c == '-' || underscores && c == '_'))
+    {
+        return Err(InvalidHostname::InvalidCharacter {
+            label: label.to_owned(),
+            character,
+        });
+    }
+    if label.starts_with('-') || label.ends_with('-') {
+        return Err(InvalidHostname::Hyphen(label.to_owned()));
+    }
+    Ok(())
+}
+
+/// `host` without the trailing dot marking it fully qualified, which the platform resolvers do
+/// not match against the hosts file.
+pub(crate) fn without_root(host: &str) -> &str {
+    host.strip_suffix('.')
+        .filter(|name| !name.is_empty())
+        .unwrap_or(host)
+}
+
+/// `host` with its Unicode labels as A-labels (`xn--...`) with the `idna` feature, so that
+/// internationalized names are looked up the same with every backend.
+pub(crate) fn to_ascii(host: &str) -> Cow<'_, str> {
+    #[cfg(feature = "idna")]
+    if !host.is_ascii() {
+        // a name that cannot be encoded fails validation as it is
+        if let Some(ascii) = crate::idna::to_ascii(host) {
+            return Cow::Owned(ascii);
+        }
+    }
+    Cow::Borrowed(host)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A name of `len` characters, of labels up to 63 characters long.
+    fn name_of(len: usize) -> String {
+        let mut name = String::new();
+        while name.len() < len {
+            if !name.is_empty() {
+                name.push('.');
+            }
+            let label = (len - name.len()).min(MAX_LABEL_LEN);
+            name.push_str(&"a".repeat(label));
+        }
+        name
+    }
+
+    #[test]
+    fn name_length() {
+        let strict = HostnameSyntax::Strict;
+        assert_eq!(strict.check(&name_of(253)), Ok(()));
+        assert_eq!(strict.check(&format!("{}.", name_of(253))), Ok(()));
+        assert_eq!(
+            strict.check(&name_of(254)),
+            Err(InvalidHostname::TooLong(254))
+        );
+    }
+
+    #[test]
+    fn label_length() {
+        let strict = HostnameSyntax::Strict;
+        let label = "a".repeat(63);
+        assert_eq!(strict.check(&format!("{label}.example")), Ok(()));
+        let label = "a".repeat(64);
+        assert_eq!(
+            strict.check(&format!("{label}.example")),
+            Err(InvalidHostname::LabelTooLong(label))
+        );
+    }
+
+    #[test]
+    fn dots() {
+        let strict = HostnameSyntax::Strict;
+        assert_eq!(strict.check("example.com."), Ok(()));
+        for name in [".", "", "a..b", ".example.com", "example.com.."] {
+            assert_eq!(
+                strict.check(name),
+                Err(InvalidHostname::EmptyLabel),
+                "{name}"
+            );
+        }
+    }
+
+    #[test]
+    fn hyphens() {
+        let strict = HostnameSyntax::Strict;
+        assert_eq!(strict.check("a-b.example"), Ok(()));
+        for label in ["-ab", "ab-", "-"] {
+            assert_eq!(
+                strict.check(&format!("{label}.example")),
+                Err(InvalidHostname::Hyphen(label.to_owned())),
+                "{label}"
+            );
+        }
+    }
+
+    #[test]
+    fn underscores() {
+        let name = "_sip._tcp.example.com";
+        assert_eq!(
+            HostnameSyntax::Strict.check(name),
+            Err(InvalidHostname::InvalidCharacter {
+                label: "_sip".to_owned(),
+                character: '_',
+            })
+        );
+        assert_eq!(HostnameSyntax::AllowUnderscores.check(name), Ok(()));
+        assert_eq!(HostnameSyntax::Any.check("a..b"), Ok(()));
+    }
+}
@@ -0,0 +1,95 @@
+use std::{
+    collections::HashMap,
+    io,
+    net::{IpAddr, Ipv6Addr},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::SystemTime,
+};
+
+/// Static host table, as read from the system hosts file.
+#[derive(Debug, Clone, Default)]
+pub struct Hosts {
+    by_name: HashMap<String, Vec<IpAddr>>,
+}
+
+impl Hosts {
+    /// Reads the system hosts file, `/etc/hosts` or `%SystemRoot%\System32\drivers\etc\hosts`.
+    pub fn load() -> io::Result<Self> {
+        Self::from_path(system_path())
+    }
+
+    /// Reads the hosts formatted file at `path`.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Ok(Self::parse(&std::fs::read_to_string(path)?))
+    }
+
+    /// Parses the contents of a hosts file.
+    pub fn parse(text: &str) -> Self {
+        let mut hosts = Self::default();
+        for line in text.lines() {
+            let line = line.split('#').next().unwrap_or_default();
+            let mut words = line.split_ascii_whitespace();
+            let Some(ip) = words.next().and_then(parse_ip) else {
+                continue;
+            };
+            for name in words {
+                let addrs = hosts.by_name.entry(normalize(name)).or_default();
+                if !addrs.contains(&ip) {
+                    addrs.push(ip);
+                }
+            }
+        }
+        hosts
+    }
+
+    /// Addresses listed for `name`, compared case-insensitively.
+    pub fn lookup(&self, name: &str) -> &[IpAddr] {
+        self.by_name
+            .get(&normalize(name))
+            .map_or(&[], Vec::as_slice)
+    }
+}
+
+fn normalize(name: &str) -> String {
+    name.strip_suffix('.').unwrap_or(name).to_ascii_lowercase()
+}
+
+/// Parses an IP address, dropping an IPv6 zone (`fe80::1%eth0`).
+fn parse_ip(s: &str) -> Option<IpAddr> {
+    s.parse().ok().or_else(|| {
+        let (ip, _zone) = s.split_once('%')?;
+        ip.parse::<Ipv6Addr>().ok().map(IpAddr::V6)
+    })
+}
+
+#[cfg(windows)]
+fn system_path() -> PathBuf {
+    let root = std::env::var_os("SystemRoot").unwrap_or_else(|| "C:\\Windows".into());
+    PathBuf::from(root).join("System32\\drivers\\etc\\hosts")
+}
+
+#[cfg(not(windows))]
+fn system_path() -> PathBuf {
+    PathBuf::from("/etc/hosts")
+}
+
+/// The system hosts file, read again whenever its modification time changes.
+pub(crate) fn system() -> Arc<Hosts> {
+    static CACHE: Mutex<Option<(Option<SystemTime>, Arc<Hosts>)>> = Mutex::new(None);
+
+    let path = system_path();
+    let modified = std::fs::metadata(&path)
+        .and_then(|meta| meta.modified())
+        .ok();
+
+    let mut cache = CACHE.lock().unwrap_or_else(|e| e.into_inner());
+    match *cache {
+        Some((cached, ref hosts)) if cached == modified && modified.is_some() => hosts.clone(),
+        _ => {
+            let hosts = Arc::new(Hosts::from_path(&path).unwrap_or_default());
+            *cache = Some((modified, hosts.clone()));
+            hosts
+        }
+    }
+}
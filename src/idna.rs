@@ -0,0 +1,190 @@
+const BASE: u32 = 36;
+const T_MIN: u32 = 1;
+const T_MAX: u32 = 26;
+const SKEW: u32 = 38;
+const DAMP: u32 = 700;
+const INITIAL_BIAS: u32 = 72;
+const INITIAL_N: u32 = 0x80;
+
+/// Dots separating labels, those of IDNA2003 included: ideographic, fullwidth and halfwidth.
+const DOTS: [char; 4] = ['.', '\u{3002}', '\u{ff0e}', '\u{ff61}'];
+
+/// `host` with its non-ASCII labels as A-labels (`xn--...`), encoded with Punycode
+/// ([RFC 3492]), `None` for a label too long to encode.
+///
+/// Labels are lowercased but not normalized, the Unicode tables of full IDNA processing
+/// ([UTS #46]) being left out: names are expected in the NFC form input methods produce.
+///
+/// [RFC 3492]: https://www.rfc-editor.org/rfc/rfc3492
+/// [UTS #46]: https://www.unicode.org/reports/tr46/
+pub(crate) fn to_ascii(host: &str) -> Option<String> {
+    let labels = host
+        .split(DOTS)
+        .map(|label| {
+            if label.is_ascii() {
+                Some(label.to_owned())
+            } else {
+                encode(&label.to_lowercase()).map(|label| format!("xn--{label}"))
+            }
+        })
+        .collect::<Option<Vec<_>>>()?;
+    Some(labels.join("."))
+}
+
+/// The Punycode encoding of `label`.
+fn encode(label: &str) -> Option<String> {
+    let input: Vec<u32> = label.chars().map(u32::from).collect();
+    let mut output: String = label.chars().filter(char::is_ascii).collect();
+    let basic = output.len() as u32;
+    if basic > 0 {
+        output.push('-');
+    }
+
+    let (mut n, mut delta, mut bias, mut handled) = (INITIAL_N, 0u32, INITIAL_BIAS, basic);
+    while (handled as usize) < input.len() {
+        let m = input.iter().copied().filter(|&c| c >= n).min()?;
+        delta = delta.checked_add((m - n).checked_mul(handled + 1)?)?;
+        n = m;
+        for &c in &input {
+            if c < n {
+                delta = delta.checked_add(1)?;
+            }
+            if c != n {
+                continue;
+            }
+            let mut q = delta;
+            let mut k = BASE;
+            loop {
+                let t = k.saturating_sub(bias).clamp(T_MIN, T_MAX);
+                if q < t {
+                    break;
+                }
+                output.push(digit(t + (q - t) % (BASE - t)));
+                q = (q - t) / (BASE - t);
+                k += BASE;
+            }
+            output.push(digit(q));
+            bias = adapt(delta, handled + 1, handled == basic);
+            delta = 0;
+            handled += 1;
+        }
+        delta = delta.checked_add(1)?;
+        n += 1;
+    }
+    Some(output)
+}
+
+fn adapt(delta: u32, points: u32, first: bool) -> u32 {
+    let mut delta = if first { delta / DAMP } else { delta / 2 };
+    delta += delta / points;
+    let mut k = 0;
+    while delta > ((BASE - T_MIN) * T_MAX) / 2 {
+        delta /= BASE - T_MIN;
+        k += BASE;
+    }
+    k + (BASE - T_MIN + 1) * delta / (delta + SKEW)
+}
+
+fn digit(d: u32) -> char {
+    char::from(if d < 26 {
+        b'a' + d as u8
+    } else {
+        b'0' + (d - 26) as u8
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The sample strings of RFC 3492, section 7.1.
+    const SAMPLES: [(&str, &str); 19] = [
+        ("ليهمابتكلموشعربي؟", "egbpdaj6bu4bxfgehfvwxn"),
+        ("他们为什么不说中文", "ihqwcrb4cv8a8dqg056pqjye"),
+        ("他們爲什麽不說中文", "ihqwctvzc91f659drss3x8bo0yb"),
+        ("Pročprostěnemluvíčesky", "Proprostnemluvesky-uyb24dma41a"),
+        ("למההםפשוטלאמדבריםעברית", "4dbcagdahymbxekheh6e0a7fei0b"),
+        (
+            "यहलोगहिन्दीक्योंनहींबोलसकतेहैं",
+            "i1baa7eci9glrd9b2ae1bj0hfcgg6iyaf8o0a1dig0cd",
+        ),
+        (
+            "なぜみんな日本語を話してくれないのか",
+            "n8jok5ay5dzabd5bym9f0cm5685rrjetr6pdxa",
+        ),
+        (
+            "세계의모든사람들이한국어를이해한다면얼마나좋을까",
+            "989aomsvi5e83db1d2a355cv1e0vak1dwrv93d5xbh15a0dt30a5jpsd879ccm6fea98c",
+        ),
+        (
+            "почемужеонинеговорятпорусски",
+            "b1abfaaepdrnnbgefbadotcwatmq2g4l",
+        ),
+        (
+            "PorquénopuedensimplementehablarenEspañol",
+            "PorqunopuedensimplementehablarenEspaol-fmd56a",
+        ),
+        (
+            "TạisaohọkhôngthểchỉnóitiếngViệt",
+            "TisaohkhngthchnitingVit-kjcr8268qyxafd2f1b9g",
+        ),
+        ("3年B組金八先生", "3B-ww4c5e180e575a65lsy2b"),
+        (
+            "安室奈美恵-with-SUPER-MONKEYS",
+            "-with-SUPER-MONKEYS-pc58ag80a8qai00g7n9n",
+        ),
+        (
+            "Hello-Another-Way-それぞれの場所",
+            "Hello-Another-Way--fc4qua05auwb3674vfr0b",
+        ),
+        ("ひとつ屋根の下2", "2-u9tlzr9756bt3uc0v"),
+        ("MajiでKoiする5秒前", "MajiKoi5-783gue6qz075azm5e"),
+        ("パフィーdeルンバ", "de-jg4avhby1noc0d"),
+        ("そのスピードで", "d9juau41awczczp"),
+        ("-> $1.00 <-", "-> $1.00 <--"),
+    ];
+
+    #[test]
+    fn rfc3492_samples() {
+        for (label, encoded) in SAMPLES {
+            assert_eq!(encode(label).as_deref(), Some(encoded), "{label}");
+        }
+    }
+
+    #[test]
+    fn a_labels() {
+        assert_eq!(
+            to_ascii("bücher.example").as_deref(),
+            Some("xn--bcher-kva.example")
+        );
+        assert_eq!(to_ascii("example.com").as_deref(), Some("example.com"));
+    }
+
+    #[test]
+    fn mixed_case() {
+        assert_eq!(
+            to_ascii("BÜCHER.Example").as_deref(),
+            Some("xn--bcher-kva.Example")
+        );
+        assert_eq!(
+            to_ascii("Bücher.example").as_deref(),
+            Some("xn--bcher-kva.example")
+        );
+    }
+
+    #[test]
+    fn dots() {
+        for dot in DOTS {
+            assert_eq!(
+                to_ascii(&format!("bücher{dot}example")).as_deref(),
+                Some("xn--bcher-kva.example"),
+                "{dot:?}"
+            );
+            assert_eq!(
+                to_ascii(&format!("example{dot}com")).as_deref(),
+                Some("example.com"),
+                "{dot:?}"
+            );
+        }
+    }
+}
@@ -0,0 +1,274 @@
+use std::{
+    ffi::CString,
+    future::Future,
+    net::{Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6},
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU8, Ordering},
+        Arc, OnceLock,
+    },
+    task::{ready, Context, Poll},
+    time::Duration,
+};
+
+use crate::{
+    completion::Completion, future::block_on_deadline, hostaliases, orphan::Orphan, pool::Pool,
+    AddressInfoError, CanonicalAddrs, Family, HintFlags, Hints, ResolverBackend,
+};
+
+static POOL: Pool = Pool::new("to_socket_addrs_timeout-resolver");
+
+// <netdb.h> flags, which the libc crate leaves out on these targets
+const AI_V4MAPPED: ::core::ffi::c_int = 0x0001;
+const AI_ALL: ::core::ffi::c_int = 0x0002;
+const AI_ADDRCONFIG: ::core::ffi::c_int = 0x0004;
+const AI_PASSIVE: ::core::ffi::c_int = 0x0008;
+const AI_CANONNAME: ::core::ffi::c_int = 0x0010;
+const AI_NUMERICHOST: ::core::ffi::c_int = 0x0020;
+const AI_NUMERICSERV: ::core::ffi::c_int = 0x0040;
+
+const QUEUED: u8 = 0;
+const RUNNING: u8 = 1;
+const CANCELED: u8 = 2;
+
+/// Converts a `getaddrinfo` failure, reading `errno` for `EAI_SYSTEM`, so it must be called
+/// right after the failing call.
+fn gai_error(code: ::core::ffi::c_int) -> std::io::Error {
+    if code == libc::EAI_SYSTEM {
+        std::io::Error::last_os_error()
+    } else {
+        AddressInfoError::from_raw_code(code).into()
+    }
+}
+
+/// Reads an address, `sockaddr_in6` carrying the illumos specific `__sin6_src_id` field.
+fn sockaddr_to_addr(ai: &libc::addrinfo, port: u16) -> Option<SocketAddr> {
+    let addr = unsafe { ai.ai_addr.as_ref()? };
+    let len = ai.ai_addrlen as usize;
+    match addr.sa_family as ::core::ffi::c_int {
+        libc::AF_INET if len >= core::mem::size_of::<libc::sockaddr_in>() => {
+            let addr = unsafe { &*(addr as *const libc::sockaddr as *const libc::sockaddr_in) };
+            Some(SocketAddr::V4(SocketAddrV4::new(
+                Ipv4Addr::from(addr.sin_addr.s_addr.to_ne_bytes()),
+                port,
+            )))
+        }
+        libc::AF_INET6 if len >= core::mem::size_of::<libc::sockaddr_in6>() => {
+            let addr = unsafe { &*(addr as *const libc::sockaddr as *const libc::sockaddr_in6) };
+            Some(SocketAddr::V6(SocketAddrV6::new(
+                Ipv6Addr::from(addr.sin6_addr.s6_addr),
+                port,
+                addr.sin6_flowinfo,
+                addr.sin6_scope_id,
+            )))
+        }
+        _ => None,
+    }
+}
+
+fn getaddrinfo(host: &CString, hints: &Hints, port: u16) -> std::io::Result<CanonicalAddrs> {
+    let mut ai: libc::addrinfo = unsafe { core::mem::zeroed() };
+    ai.ai_flags = hints.flags.to_native(&[
+        (HintFlags::PASSIVE, AI_PASSIVE),
+        (HintFlags::CANONNAME, AI_CANONNAME),
+        (HintFlags::NUMERICHOST, AI_NUMERICHOST),
+        (HintFlags::NUMERICSERV, AI_NUMERICSERV),
+        (HintFlags::V4MAPPED, AI_V4MAPPED),
+        (HintFlags::ALL, AI_ALL),
+        (HintFlags::ADDRCONFIG, AI_ADDRCONFIG),
+    ]);
+    ai.ai_family = hints.libc_family();
+    ai.ai_socktype = hints.libc_socktype();
+    ai.ai_protocol = hints.libc_protocol();
+
+    let mut res = core::ptr::null_mut();
+    let ret = unsafe { libc::getaddrinfo(host.as_ptr(), core::ptr::null(), &ai, &mut res) };
+    if ret != 0 {
+        return Err(gai_error(ret));
+    }
+    let name = unsafe { res.as_ref() }
+        .and_then(|first| unsafe { first.ai_canonname.as_ref() })
+        .map(|name| unsafe { ::core::ffi::CStr::from_ptr(name) })
+        .unwrap_or(host)
+        .to_string_lossy()
+        .into_owned();
+    let mut addrs = Vec::new();
+    let mut cur = res;
+    while let Some(ai) = unsafe { cur.as_ref() } {
+        addrs.extend(sockaddr_to_addr(ai, port));
+        cur = ai.ai_next;
+    }
+    unsafe { libc::freeaddrinfo(res) };
+    Ok(CanonicalAddrs { name, addrs })
+}
+
+/// State shared with the worker running the job, which holds a reference until it returns.
+struct Job {
+    host: CString,
+    hints: Hints,
+    port: u16,
+    state: AtomicU8,
+    completion: Completion<std::io::Result<CanonicalAddrs>>,
+    orphan: OnceLock<Orphan>,
+}
+
+impl Job {
+    fn run(&self) {
+        if self
+            .state
+            .compare_exchange(QUEUED, RUNNING, Ordering::AcqRel, Ordering::Acquire)
+            .is_ok()
+        {
+            self.completion
+                .complete(getaddrinfo(&self.host, &self.hints, self.port));
+        }
+    }
+}
+
+/// Lookup queued on the worker pool.
+///
+/// Dropping it before a worker picks it up removes it from the pool's work, once running it can
+/// only be abandoned.
+pub(crate) struct Lookup {
+    job: Arc<Job>,
+    done: bool,
+}
+
+impl Lookup {
+    fn poll_canonical(&mut self, cx: &mut Context<'_>) -> Poll<std::io::Result<CanonicalAddrs>> {
+        let res = ready!(self.job.completion.poll(cx));
+        self.done = true;
+        Poll::Ready(res)
+    }
+}
+
+impl Future for Lookup {
+    type Output = std::io::Result<std::vec::IntoIter<SocketAddr>>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.poll_canonical(cx)
+            .map(|res| res.map(|answer| answer.addrs.into_iter()))
+    }
+}
+
+impl Drop for Lookup {
+    fn drop(&mut self) {
+        if !self.done
+            && self
+                .job
+                .state
+                .compare_exchange(QUEUED, CANCELED, Ordering::AcqRel, Ordering::Acquire)
+                .is_err()
+        {
+            // the worker releases the orphan when getaddrinfo returns
+            self.job.orphan.get_or_init(Orphan::new);
+        }
+    }
+}
+
+fn hostname_to_cstring(hostname: &str) -> std::io::Result<CString> {
+    let alias = hostaliases::resolve(hostname);
+    CString::new(alias.as_deref().unwrap_or(hostname)).map_err(|_| {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "host name contained an unexpected NUL byte",
+        )
+    })
+}
+
+fn start(
+    host: CString,
+    hints: Hints,
+    port: u16,
+    timeout: Option<Duration>,
+) -> std::io::Result<Lookup> {
+    let job = Arc::new(Job {
+        host,
+        hints,
+        port,
+        state: AtomicU8::new(QUEUED),
+        completion: Completion::new(),
+        orphan: OnceLock::new(),
+    });
+    POOL.execute(
+        {
+            let job = job.clone();
+            move || job.run()
+        },
+        timeout,
+    )?;
+    Ok(Lookup { job, done: false })
+}
+
+pub(crate) fn lookup(host: &str, port: u16, timeout: Option<Duration>) -> std::io::Result<Lookup> {
+    start(hostname_to_cstring(host)?, Hints::default(), port, timeout)
+}
+
+fn family_hints(family: Family) -> Hints {
+    Hints {
+        family,
+        ..Hints::default()
+    }
+}
+
+/// Starts separate IPv6 and IPv4 lookups.
+pub(crate) fn lookup_split(
+    host: &str,
+    port: u16,
+    timeout: Option<Duration>,
+) -> std::io::Result<Vec<Lookup>> {
+    let host = hostname_to_cstring(host)?;
+    Ok(vec![
+        start(host.clone(), family_hints(Family::Ipv6), port, timeout)?,
+        start(host, family_hints(Family::Ipv4), port, timeout)?,
+    ])
+}
+
+/// The platform resolver on illumos and Solaris: `getaddrinfo` on a bounded pool of worker
+/// threads, abandoned on timeout.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemResolver;
+
+impl ResolverBackend for SystemResolver {
+    fn resolve(
+        &self,
+        host: &str,
+        port: u16,
+        timeout: Duration,
+    ) -> std::io::Result<std::vec::IntoIter<SocketAddr>> {
+        self.resolve_with_hints(host, port, &Hints::default(), timeout)
+    }
+
+    fn resolve_with_hints(
+        &self,
+        host: &str,
+        port: u16,
+        hints: &Hints,
+        timeout: Duration,
+    ) -> std::io::Result<std::vec::IntoIter<SocketAddr>> {
+        let deadline = crate::deadline_after(timeout);
+        let mut lookup = start(hostname_to_cstring(host)?, *hints, port, Some(timeout))?;
+        block_on_deadline(&mut lookup, deadline)
+            .unwrap_or_else(|| Err(std::io::ErrorKind::TimedOut.into()))
+    }
+
+    fn resolve_canonical(
+        &self,
+        host: &str,
+        port: u16,
+        hints: &Hints,
+        timeout: Duration,
+    ) -> std::io::Result<CanonicalAddrs> {
+        let hints = Hints {
+            flags: hints.flags | HintFlags::CANONNAME,
+            ..*hints
+        };
+        let deadline = crate::deadline_after(timeout);
+        let mut lookup = start(hostname_to_cstring(host)?, hints, port, Some(timeout))?;
+        block_on_deadline(
+            &mut std::future::poll_fn(|cx| lookup.poll_canonical(cx)),
+            deadline,
+        )
+        .unwrap_or_else(|| Err(std::io::ErrorKind::TimedOut.into()))
+    }
+}
@@ -1,22 +1,389 @@
-// TODO:
-// - macos https://developer.apple.com/documentation/dnssd/dnsservicegetaddrinfo(_:_:_:_:_:_:_:) - https://eggerapps.at/blog/2014/hostname-lookups.html
-
 use std::{
+    borrow::Cow,
     io,
     net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6},
     option,
-    time::Duration,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        OnceLock,
+    },
+    time::{Duration, Instant},
 };
 
-#[cfg(not(windows))]
+mod addrinfo;
+#[cfg(backend = "android")]
+mod android;
+#[cfg(backend = "apple")]
+mod apple;
+mod backend;
+mod batch;
+#[cfg(backend = "bsd")]
+mod bsd;
+mod budget;
+#[cfg(backend = "c_ares")]
+mod c_ares;
+#[cfg(feature = "cache")]
+mod cache;
+mod coalesce;
+#[cfg(not(any(backend = "wasi", backend = "wasm")))]
+mod completion;
+mod connect;
+mod dns;
+mod error;
+#[cfg(any(backend = "fallback", backend = "android", backend = "linux_glibc"))]
 mod fallback;
-#[cfg(windows)]
+mod future;
+mod hints;
+mod hooks;
+mod hostaliases;
+mod hostname;
+mod hosts;
+#[cfg(feature = "idna")]
+mod idna;
+#[cfg(backend = "illumos")]
+mod illumos;
+#[cfg(target_os = "linux")]
+mod json;
+mod limit;
+#[cfg(backend = "linux_glibc")]
+mod linux_glibc;
+mod llmnr;
+#[cfg(feature = "metrics")]
+mod lookup_metrics;
+mod mdns;
+#[cfg(all(unix, feature = "mio"))]
+mod mio_source;
+mod multicast;
+mod order;
+mod orphan;
+mod passive;
+mod pool;
+#[cfg(any(unix, windows))]
+mod readiness;
+mod records;
+mod resolution;
+mod resolvconf;
+mod resolved;
+mod resolver;
+mod retry;
+mod reverse;
+mod services;
+mod shutdown;
+mod stats;
+mod stream;
+mod stub;
+#[cfg(backend = "stub")]
+mod system_stub;
+#[cfg(target_os = "linux")]
+mod systemd;
+mod timer;
+#[cfg(feature = "tokio")]
+pub mod tokio;
+#[cfg(backend = "wasi")]
+mod wasi;
+#[cfg(backend = "wasm")]
+mod wasm;
+#[cfg(backend = "windows")]
 mod windows;
+mod zone;
+
+#[cfg(backend = "android")]
+use android as sys;
+#[cfg(backend = "apple")]
+use apple as sys;
+#[cfg(backend = "bsd")]
+use bsd as sys;
+#[cfg(backend = "c_ares")]
+use c_ares as sys;
+#[cfg(backend = "fallback")]
+use fallback as sys;
+#[cfg(backend = "illumos")]
+use illumos as sys;
+#[cfg(backend = "linux_glibc")]
+use linux_glibc as sys;
+#[cfg(backend = "stub")]
+use system_stub as sys;
+#[cfg(backend = "wasi")]
+use wasi as sys;
+#[cfg(backend = "wasm")]
+use wasm as sys;
+#[cfg(backend = "windows")]
+use windows as sys;
+
+pub use addrinfo::{lookup_addrinfo, AddrInfo, AddrInfoIter, AddressInfoError};
+#[cfg(backend = "android")]
+pub use android::NetworkResolver;
+pub use backend::{backend, BackendKind};
+pub use batch::{resolve_all, resolve_many};
+pub use budget::Budget;
+#[cfg(feature = "cache")]
+pub use cache::CacheConfig;
+pub use connect::{
+    connect_each, connect_happy_eyeballs, connect_happy_eyeballs_within, Connected, TcpStreamExt,
+    UdpSocketExt,
+};
+pub use error::ResolveError;
+pub use future::ResolveFuture;
+pub use hints::{Family, FamilyPreference, HintFlags, Hints, Protocol, SocketType};
+pub use hooks::{set_global_hooks, LookupComplete, LookupHooks, LookupStart};
+pub use hostname::{HostnameSyntax, InvalidHostname};
+pub use hosts::Hosts;
+pub use limit::ConcurrencyLimit;
+pub use mdns::MdnsResolver;
+#[cfg(all(unix, feature = "mio"))]
+pub use mio_source::MioSource;
+pub use order::{AddrIterExt, Dedup, Interleave};
+pub use orphan::orphaned_lookups;
+pub use passive::bind_addrs_timeout;
+pub use pool::{set_pool_config, PoolConfig, PoolOverflow};
+#[cfg(any(unix, windows))]
+pub use readiness::Readiness;
+pub use records::{
+    lookup_cname_chain, lookup_https_records, lookup_txt, raw_query, CnameChain, DnsMessage,
+    DnsQuestion, DnsRecord, HttpsRecord,
+};
+pub use resolution::Resolution;
+pub use resolvconf::ResolvConf;
+pub use resolved::ResolvedAddrs;
+pub use resolver::{CanonicalAddrs, ResolvedAddr, Resolver, ResolverBackend, ResolverBuilder};
+pub use retry::RetryPolicy;
+pub use reverse::{lookup_ptr, ToHostnameTimeout};
+pub use shutdown::shutdown;
+pub use stats::ResolveStats;
+pub use stream::{resolve_stream, ResolveStream};
+pub use stub::{ClientSubnet, DnssecError, StubResolver};
+pub use sys::SystemResolver;
+#[cfg(target_os = "linux")]
+pub use systemd::SystemdResolver;
+#[cfg(backend = "wasm")]
+pub use wasm::set_wasm_provider;
+
+/// Resolves `host` with the platform resolver, along with its canonical name, giving up once
+/// `timeout` has elapsed.
+pub fn resolve_with_canonical(
+    host: &str,
+    port: u16,
+    timeout: Duration,
+) -> io::Result<CanonicalAddrs> {
+    if let Some(literal) = CanonicalAddrs::literal(host, port) {
+        return Ok(literal);
+    }
+    let host = hostname::to_ascii(host);
+    resolver::SYSTEM.resolve_canonical(&host, port, &Hints::default(), timeout)
+}
+
+/// Resolves `host` like `tokio::net::lookup_host` does, giving up once `timeout` has elapsed.
+///
+/// The `tokio` feature adds `tokio::lookup_host_timeout`, the asynchronous version.
+pub fn lookup_host_timeout<T: ToSocketAddrsTimeout>(
+    host: T,
+    timeout: Duration,
+) -> io::Result<impl Iterator<Item = SocketAddr>> {
+    host.to_socket_addrs_timeout(timeout)
+}
+
+/// The addresses of `host`, with the platform resolver, giving up once `timeout` has elapsed.
+pub fn resolve_host(host: &str, port: u16, timeout: Duration) -> io::Result<Vec<SocketAddr>> {
+    (host, port)
+        .to_socket_addrs_timeout(timeout)
+        .map(ResolvedAddrs::into_vec)
+}
+
+/// The addresses of `spec`, a socket address or a `host:port` pair, with the platform
+/// resolver, giving up once `timeout` has elapsed.
+pub fn resolve_addr(spec: &str, timeout: Duration) -> io::Result<Vec<SocketAddr>> {
+    spec.to_socket_addrs_timeout(timeout)
+        .map(ResolvedAddrs::into_vec)
+}
+
+/// The IP addresses of `host`, with the platform resolver, giving up once `timeout` has
+/// elapsed.
+///
+/// No port or service is given to the resolver.
+pub fn lookup_ip(host: &str, timeout: Duration) -> io::Result<Vec<IpAddr>> {
+    lookup_ip_with(host, &Hints::default(), timeout)
+}
+
+/// The IPv4 addresses of `host`, asking the platform resolver for those alone, giving up once
+/// `timeout` has elapsed.
+///
+/// An IPv6 literal has none.
+pub fn lookup_ipv4(host: &str, timeout: Duration) -> io::Result<Vec<Ipv4Addr>> {
+    let hints = Hints {
+        family: Family::Ipv4,
+        ..Hints::default()
+    };
+    let addrs = lookup_ip_with(host, &hints, timeout)?;
+    Ok(addrs
+        .into_iter()
+        .filter_map(|addr| match addr {
+            IpAddr::V4(addr) => Some(addr),
+            IpAddr::V6(_) => None,
+        })
+        .collect())
+}
+
+/// The IPv6 addresses of `host`, asking the platform resolver for those alone, giving up once
+/// `timeout` has elapsed.
+///
+/// An IPv4 literal has none.
+pub fn lookup_ipv6(host: &str, timeout: Duration) -> io::Result<Vec<Ipv6Addr>> {
+    let hints = Hints {
+        family: Family::Ipv6,
+        ..Hints::default()
+    };
+    let addrs = lookup_ip_with(host, &hints, timeout)?;
+    Ok(addrs
+        .into_iter()
+        .filter_map(|addr| match addr {
+            IpAddr::V6(addr) => Some(addr),
+            IpAddr::V4(_) => None,
+        })
+        .collect())
+}
+
+fn lookup_ip_with(host: &str, hints: &Hints, timeout: Duration) -> io::Result<Vec<IpAddr>> {
+    if let Some(addr) = literal(host, 0) {
+        return Ok(hints
+            .filter(vec![addr].into_iter())
+            .map(|addr| addr.ip())
+            .collect());
+    }
+    let host = hostname::to_ascii(host);
+    let addrs = resolver::SYSTEM.resolve_with_hints(&host, 0, hints, timeout)?;
+    Ok(addrs.map(|addr| addr.ip()).collect())
+}
+
+/// The backend [`SystemResolver`] uses, as the `metrics` feature labels its lookups.
+pub(crate) const BACKEND: &str = env!("TO_SOCKET_ADDRS_TIMEOUT_BACKEND");
+
+/// A timeout that never elapses, lookups waiting for as long as the resolver takes.
+///
+/// Timeouts too long for a platform are cut to the longest it supports rather than wrapping.
+pub const NO_TIMEOUT: Duration = Duration::MAX;
+
+/// The instant `timeout` from now, or one in the distant future when that is past what
+/// [`Instant`] can represent.
+pub(crate) fn deadline_after(timeout: Duration) -> Instant {
+    // about 34 years
+    const DISTANT: Duration = Duration::from_secs(1 << 30);
+    let now = Instant::now();
+    now.checked_add(timeout)
+        .or_else(|| now.checked_add(DISTANT))
+        .unwrap_or(now)
+}
+
+/// The timeout set with [`set_default_timeout`] in nanoseconds, [`UNSET`] before and
+/// [`FOREVER`] for those that do not fit.
+static DEFAULT_TIMEOUT: AtomicU64 = AtomicU64::new(UNSET);
+
+const UNSET: u64 = u64::MAX;
 
+const FOREVER: u64 = u64::MAX - 1;
+
+/// Sets the timeout of [`resolve`](ToSocketAddrsTimeout::resolve), for the whole process,
+/// overriding `TO_SOCKET_ADDRS_TIMEOUT_MS`.
+pub fn set_default_timeout(timeout: Duration) {
+    let nanos = u64::try_from(timeout.as_nanos())
+        .unwrap_or(FOREVER)
+        .min(FOREVER);
+    DEFAULT_TIMEOUT.store(nanos, Ordering::Relaxed);
+}
+
+/// The timeout of [`resolve`](ToSocketAddrsTimeout::resolve): the one set with
+/// [`set_default_timeout`], or else the milliseconds in the `TO_SOCKET_ADDRS_TIMEOUT_MS`
+/// environment variable when the process first asks, or else 10 seconds.
+pub fn default_timeout() -> Duration {
+    static FROM_ENV: OnceLock<Duration> = OnceLock::new();
+    match DEFAULT_TIMEOUT.load(Ordering::Relaxed) {
+        UNSET => *FROM_ENV.get_or_init(|| {
+            std::env::var("TO_SOCKET_ADDRS_TIMEOUT_MS")
+                .ok()
+                .and_then(|ms| ms.trim().parse().ok())
+                .map_or(Duration::from_secs(10), Duration::from_millis)
+        }),
+        FOREVER => NO_TIMEOUT,
+        nanos => Duration::from_nanos(nanos),
+    }
+}
+
+/// Values resolving to socket addresses, like [`ToSocketAddrs`](std::net::ToSocketAddrs) with
+/// a timeout on the lookup.
+///
+/// Libraries taking an `impl ToSocketAddrsTimeout` can leave the timeout to the application
+/// by calling [`to_socket_addrs_default`](Self::to_socket_addrs_default), which uses the
+/// process-wide [`default_timeout`].
 pub trait ToSocketAddrsTimeout {
     type Iter: Iterator<Item = SocketAddr>;
 
+    /// The addresses, giving up once `timeout` has elapsed.
+    ///
+    /// A zero timeout never blocks: IP literals are returned, and host names fail with
+    /// [`WouldBlock`](io::ErrorKind::WouldBlock) unless a [`Resolver`] has them cached.
+    /// [`NO_TIMEOUT`] waits for as long as the resolver takes.
     fn to_socket_addrs_timeout(&self, timeout: Duration) -> io::Result<Self::Iter>;
+
+    /// [`to_socket_addrs_timeout`](Self::to_socket_addrs_timeout) with the
+    /// [`default_timeout`].
+    fn resolve(&self) -> io::Result<Self::Iter> {
+        self.to_socket_addrs_timeout(default_timeout())
+    }
+
+    /// [`to_socket_addrs_timeout`](Self::to_socket_addrs_timeout) with the
+    /// [`default_timeout`], as [`resolve`](Self::resolve) does.
+    fn to_socket_addrs_default(&self) -> io::Result<Self::Iter> {
+        self.resolve()
+    }
+
+    /// [`to_socket_addrs_timeout`](Self::to_socket_addrs_timeout) with the time left in
+    /// `budget`, failing with [`TimedOut`](io::ErrorKind::TimedOut) once it is spent unless
+    /// no lookup is needed.
+    fn to_socket_addrs_within(&self, budget: &Budget) -> io::Result<Self::Iter> {
+        budget.spend(|timeout| self.to_socket_addrs_timeout(timeout))
+    }
+
+    /// Like [`to_socket_addrs_timeout`](Self::to_socket_addrs_timeout), resolving host names
+    /// through `backend` instead of the platform resolver.
+    ///
+    /// The default implementation is for values that need no lookup and ignores `backend`.
+    fn to_socket_addrs_with(
+        &self,
+        backend: &dyn ResolverBackend,
+        timeout: Duration,
+    ) -> io::Result<Self::Iter> {
+        _ = backend;
+        self.to_socket_addrs_timeout(timeout)
+    }
+
+    /// Like [`to_socket_addrs_timeout`](Self::to_socket_addrs_timeout), passing `hints` on to
+    /// the platform resolver.
+    ///
+    /// Values that need no lookup are returned as they are.
+    fn to_socket_addrs_timeout_with(
+        &self,
+        hints: &Hints,
+        timeout: Duration,
+    ) -> io::Result<Self::Iter> {
+        self.to_socket_addrs_with(
+            &hints::WithHints {
+                backend: &resolver::SYSTEM,
+                hints,
+            },
+            timeout,
+        )
+    }
+
+    /// Starts a lookup in the background, returning a handle that can be waited on, polled or
+    /// canceled.
+    ///
+    /// The default implementation answers immediately by calling
+    /// [`to_socket_addrs_timeout`](Self::to_socket_addrs_timeout) with a zero timeout.
+    fn start_lookup(&self) -> Resolution {
+        Resolution::ready(
+            self.to_socket_addrs_timeout(Duration::ZERO)
+                .map(|addrs| addrs.collect::<Vec<_>>().into_iter()),
+        )
+    }
 }
 
 impl<'a> ToSocketAddrsTimeout for &'a [SocketAddr] {
@@ -33,6 +400,110 @@ impl<T: ToSocketAddrsTimeout + ?Sized> ToSocketAddrsTimeout for &T {
     fn to_socket_addrs_timeout(&self, timeout: Duration) -> io::Result<T::Iter> {
         (**self).to_socket_addrs_timeout(timeout)
     }
+
+    fn to_socket_addrs_with(
+        &self,
+        backend: &dyn ResolverBackend,
+        timeout: Duration,
+    ) -> io::Result<T::Iter> {
+        (**self).to_socket_addrs_with(backend, timeout)
+    }
+
+    fn start_lookup(&self) -> Resolution {
+        (**self).start_lookup()
+    }
+}
+
+/// Addresses boxed by [`ToSocketAddrsTimeoutDyn`].
+pub type BoxedAddrs = Box<dyn Iterator<Item = SocketAddr> + Send>;
+
+/// Dyn-compatible version of [`ToSocketAddrsTimeout`], implemented for all of its
+/// implementors with sendable addresses, for targets of different types to be kept together
+/// as `dyn ToSocketAddrsTimeoutDyn`.
+pub trait ToSocketAddrsTimeoutDyn {
+    /// [`ToSocketAddrsTimeout::to_socket_addrs_timeout`], boxing the addresses.
+    fn to_socket_addrs_timeout_dyn(&self, timeout: Duration) -> io::Result<BoxedAddrs>;
+
+    /// [`ToSocketAddrsTimeout::to_socket_addrs_with`], boxing the addresses.
+    fn to_socket_addrs_with_dyn(
+        &self,
+        backend: &dyn ResolverBackend,
+        timeout: Duration,
+    ) -> io::Result<BoxedAddrs>;
+
+    /// [`ToSocketAddrsTimeout::start_lookup`].
+    fn start_lookup_dyn(&self) -> Resolution;
+}
+
+impl<T> ToSocketAddrsTimeoutDyn for T
+where
+    T: ToSocketAddrsTimeout + ?Sized,
+    T::Iter: Send + 'static,
+{
+    fn to_socket_addrs_timeout_dyn(&self, timeout: Duration) -> io::Result<BoxedAddrs> {
+        Ok(Box::new(self.to_socket_addrs_timeout(timeout)?))
+    }
+
+    fn to_socket_addrs_with_dyn(
+        &self,
+        backend: &dyn ResolverBackend,
+        timeout: Duration,
+    ) -> io::Result<BoxedAddrs> {
+        Ok(Box::new(self.to_socket_addrs_with(backend, timeout)?))
+    }
+
+    fn start_lookup_dyn(&self) -> Resolution {
+        self.start_lookup()
+    }
+}
+
+/// Version of [`ToSocketAddrsTimeout`] whose iterator borrows from the value resolved, for
+/// those holding their addresses already, like `[SocketAddr]`, `[SocketAddr; N]` and
+/// `Vec<SocketAddr>`, to be read in place rather than through a reference to them.
+///
+/// Implemented for all implementors of [`ToSocketAddrsTimeout`] with their iterator.
+pub trait ToSocketAddrsTimeoutRef {
+    type Iter<'a>: Iterator<Item = SocketAddr>
+    where
+        Self: 'a;
+
+    /// [`ToSocketAddrsTimeout::to_socket_addrs_timeout`], borrowing from `self`.
+    fn to_socket_addrs_timeout_ref(&self, timeout: Duration) -> io::Result<Self::Iter<'_>>;
+}
+
+impl<T: ToSocketAddrsTimeout + ?Sized> ToSocketAddrsTimeoutRef for T {
+    type Iter<'a>
+        = T::Iter
+    where
+        Self: 'a;
+
+    fn to_socket_addrs_timeout_ref(&self, timeout: Duration) -> io::Result<T::Iter> {
+        self.to_socket_addrs_timeout(timeout)
+    }
+}
+
+impl ToSocketAddrsTimeoutRef for [SocketAddr] {
+    type Iter<'a> = std::iter::Copied<std::slice::Iter<'a, SocketAddr>>;
+
+    fn to_socket_addrs_timeout_ref(&self, _timeout: Duration) -> io::Result<Self::Iter<'_>> {
+        Ok(self.iter().copied())
+    }
+}
+
+impl<const N: usize> ToSocketAddrsTimeoutRef for [SocketAddr; N] {
+    type Iter<'a> = std::iter::Copied<std::slice::Iter<'a, SocketAddr>>;
+
+    fn to_socket_addrs_timeout_ref(&self, _timeout: Duration) -> io::Result<Self::Iter<'_>> {
+        Ok(self.iter().copied())
+    }
+}
+
+impl ToSocketAddrsTimeoutRef for Vec<SocketAddr> {
+    type Iter<'a> = std::iter::Copied<std::slice::Iter<'a, SocketAddr>>;
+
+    fn to_socket_addrs_timeout_ref(&self, _timeout: Duration) -> io::Result<Self::Iter<'_>> {
+        Ok(self.iter().copied())
+    }
 }
 
 impl ToSocketAddrsTimeout for SocketAddr {
@@ -104,22 +575,201 @@ impl ToSocketAddrsTimeout for (Ipv6Addr, u16) {
     }
 }
 
+/// What a `host:port` string names.
+enum Target<'a> {
+    Addr(SocketAddr),
+    Host(Cow<'a, str>, u16),
+    /// A host and a service name, looked up together.
+    Service(Cow<'a, str>, &'a str),
+}
+
+impl<'a> Target<'a> {
+    fn parse(s: &'a str) -> io::Result<Self> {
+        if let Ok(addr) = s.parse() {
+            return Ok(Self::Addr(addr));
+        }
+
+        let (host, port_str) = parse_host_port(s)?;
+        if let Ok(port) = port_str.parse() {
+            return Ok(match literal(host, port) {
+                Some(addr) => Self::Addr(addr),
+                None => Self::Host(hostname::to_ascii(host), port),
+            });
+        }
+        if let Some(mut addr) = literal(host, 0) {
+            addr.set_port(services::parse_port(port_str)?);
+            return Ok(Self::Addr(addr));
+        }
+        if port_str.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid port value",
+            ));
+        }
+        Ok(Self::Service(hostname::to_ascii(host), port_str))
+    }
+}
+
+/// Splits `host:port` in one pass for all the string forms: at the `]:` of `[host]:port`, the
+/// brackets dropped, otherwise at the last colon. The port is left as written, a number or a
+/// service name.
+///
+/// As with [`SocketAddr`], only IPv6 literals go in brackets, here optionally with a zone.
+pub(crate) fn parse_host_port(s: &str) -> io::Result<(&str, &str)> {
+    let invalid = || io::Error::new(io::ErrorKind::InvalidInput, "invalid socket address");
+    let (host, port) = match s.strip_prefix('[') {
+        Some(rest) => {
+            let (host, port) = rest.split_once("]:").ok_or_else(invalid)?;
+            let ip = host.split_once('%').map_or(host, |(ip, _)| ip);
+            if ip.parse::<Ipv6Addr>().is_err() {
+                return Err(invalid());
+            }
+            (host, port)
+        }
+        None => s.rsplit_once(':').ok_or_else(invalid)?,
+    };
+    Ok((host, port))
+}
+
+/// `host` on `port` if it is an IP literal, IPv6 ones optionally with a zone, an interface
+/// name or a scope ID: `fe80::1%eth0`, `fe80::1%2`.
+pub(crate) fn literal(host: &str, port: u16) -> Option<SocketAddr> {
+    if let Ok(addr) = host.parse::<Ipv4Addr>() {
+        return Some(SocketAddr::V4(SocketAddrV4::new(addr, port)));
+    }
+    let (ip, zone) = match host.split_once('%') {
+        Some((ip, zone)) => (ip, Some(zone)),
+        None => (host, None),
+    };
+    let ip = ip.parse::<Ipv6Addr>().ok()?;
+    let scope_id = match zone {
+        Some(zone) => zone::scope_id(zone)?,
+        None => 0,
+    };
+    Some(SocketAddr::V6(SocketAddrV6::new(ip, port, 0, scope_id)))
+}
+
+/// Resolves `host` with the platform resolver, the addresses being read off the list it returns
+/// as they are iterated where it returns one.
+fn resolve_system(host: &str, port: u16, timeout: Duration) -> io::Result<ResolvedAddrs> {
+    hooks::observe(
+        &LookupHooks::NONE,
+        BACKEND,
+        host,
+        timeout,
+        ExactSizeIterator::len,
+        || {
+            HostnameSyntax::Strict.check(host)?;
+            let host = hostname::without_root(host);
+            if timeout.is_zero() {
+                return Err(resolver::would_block());
+            }
+            #[cfg(any(backend = "linux_glibc", backend = "windows"))]
+            return sys::resolve_iter(host, port, timeout).map(Into::into);
+            #[cfg(not(any(backend = "linux_glibc", backend = "windows")))]
+            SystemResolver.resolve(host, port, timeout).map(Into::into)
+        },
+    )
+}
+
+impl ToSocketAddrsTimeout for str {
+    type Iter = ResolvedAddrs;
+
+    fn to_socket_addrs_timeout(&self, timeout: Duration) -> io::Result<ResolvedAddrs> {
+        match Target::parse(self)? {
+            Target::Host(host, port) => resolve_system(&host, port, timeout),
+            _ => self.to_socket_addrs_with(&resolver::SYSTEM, timeout),
+        }
+    }
+
+    fn to_socket_addrs_with(
+        &self,
+        backend: &dyn ResolverBackend,
+        timeout: Duration,
+    ) -> io::Result<ResolvedAddrs> {
+        match Target::parse(self)? {
+            Target::Addr(addr) => Ok(addr.into()),
+            Target::Host(host, port) => backend.resolve(&host, port, timeout).map(Into::into),
+            Target::Service(host, service) => backend
+                .resolve_service(&host, service, &Hints::default(), timeout)
+                .map(Into::into),
+        }
+    }
+
+    fn start_lookup(&self) -> Resolution {
+        Resolution::start_str(self)
+    }
+}
+
+impl ToSocketAddrsTimeout for (&str, u16) {
+    type Iter = ResolvedAddrs;
+
+    fn to_socket_addrs_timeout(&self, timeout: Duration) -> io::Result<ResolvedAddrs> {
+        let (host, port) = *self;
+        match literal(host, port) {
+            Some(addr) => Ok(addr.into()),
+            None => resolve_system(&hostname::to_ascii(host), port, timeout),
+        }
+    }
+
+    fn to_socket_addrs_with(
+        &self,
+        backend: &dyn ResolverBackend,
+        timeout: Duration,
+    ) -> io::Result<ResolvedAddrs> {
+        let (host, port) = *self;
+        match literal(host, port) {
+            Some(addr) => Ok(addr.into()),
+            None => backend
+                .resolve(&hostname::to_ascii(host), port, timeout)
+                .map(Into::into),
+        }
+    }
+
+    fn start_lookup(&self) -> Resolution {
+        Resolution::start(self.0, self.1)
+    }
+}
+
 impl ToSocketAddrsTimeout for String {
-    type Iter = std::vec::IntoIter<SocketAddr>;
+    type Iter = ResolvedAddrs;
 
     #[inline]
     fn to_socket_addrs_timeout(&self, timeout: Duration) -> ::std::io::Result<Self::Iter> {
         (**self).to_socket_addrs_timeout(timeout)
     }
+
+    #[inline]
+    fn to_socket_addrs_with(
+        &self,
+        backend: &dyn ResolverBackend,
+        timeout: Duration,
+    ) -> io::Result<Self::Iter> {
+        (**self).to_socket_addrs_with(backend, timeout)
+    }
+
+    #[inline]
+    fn start_lookup(&self) -> Resolution {
+        (**self).start_lookup()
+    }
 }
 
 impl ToSocketAddrsTimeout for (String, u16) {
-    type Iter = std::vec::IntoIter<SocketAddr>;
+    type Iter = ResolvedAddrs;
 
-    fn to_socket_addrs_timeout(
+    fn to_socket_addrs_timeout(&self, timeout: Duration) -> std::io::Result<ResolvedAddrs> {
+        (&*self.0, self.1).to_socket_addrs_timeout(timeout)
+    }
+
+    fn to_socket_addrs_with(
         &self,
+        backend: &dyn ResolverBackend,
         timeout: Duration,
-    ) -> std::io::Result<std::vec::IntoIter<SocketAddr>> {
-        (&*self.0, self.1).to_socket_addrs_timeout(timeout)
+    ) -> io::Result<Self::Iter> {
+        (&*self.0, self.1).to_socket_addrs_with(backend, timeout)
+    }
+
+    fn start_lookup(&self) -> Resolution {
+        (&*self.0, self.1).start_lookup()
     }
 }
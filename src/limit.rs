@@ -0,0 +1,167 @@
+use std::{
+    io,
+    net::SocketAddr,
+    sync::{Arc, Condvar, Mutex},
+    time::{Duration, Instant},
+};
+
+use crate::{CanonicalAddrs, Hints, PoolOverflow, ResolveError, ResolvedAddr, ResolverBackend};
+
+/// Most lookups a [`Resolver`](crate::Resolver) makes at once, see
+/// [`ResolverBuilder::concurrency_limit`](crate::ResolverBuilder::concurrency_limit).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct ConcurrencyLimit {
+    /// Most lookups in flight, 64 by default, 0 counting as 1.
+    pub max_in_flight: usize,
+    /// What a lookup does when as many are in flight, [`PoolOverflow::Block`] by default.
+    pub overflow: PoolOverflow,
+}
+
+impl Default for ConcurrencyLimit {
+    fn default() -> Self {
+        Self {
+            max_in_flight: 64,
+            overflow: PoolOverflow::Block,
+        }
+    }
+}
+
+impl ConcurrencyLimit {
+    /// Sets [`max_in_flight`](Self::max_in_flight).
+    pub fn max_in_flight(mut self, max: usize) -> Self {
+        self.max_in_flight = max;
+        self
+    }
+
+    /// Sets [`overflow`](Self::overflow).
+    pub fn overflow(mut self, overflow: PoolOverflow) -> Self {
+        self.overflow = overflow;
+        self
+    }
+}
+
+/// `inner` with no more than `limit` lookups handed to it at once.
+pub(crate) struct Limited {
+    inner: Arc<dyn ResolverBackend>,
+    limit: ConcurrencyLimit,
+    in_flight: Mutex<usize>,
+    released: Condvar,
+}
+
+impl Limited {
+    pub fn new(inner: Arc<dyn ResolverBackend>, limit: ConcurrencyLimit) -> Self {
+        Self {
+            inner,
+            limit: ConcurrencyLimit {
+                max_in_flight: limit.max_in_flight.max(1),
+                ..limit
+            },
+            in_flight: Mutex::new(0),
+            released: Condvar::new(),
+        }
+    }
+
+    /// Runs `lookup` with the time left of `timeout` once fewer than the limit are in flight.
+    ///
+    /// Lookups with a zero timeout go through, the backend failing them without blocking.
+    fn limit<T>(
+        &self,
+        timeout: Duration,
+        lookup: impl FnOnce(Duration) -> io::Result<T>,
+    ) -> io::Result<T> {
+        if timeout.is_zero() {
+            return lookup(timeout);
+        }
+        let deadline = crate::deadline_after(timeout);
+        let mut in_flight = self.in_flight.lock().unwrap_or_else(|e| e.into_inner());
+        while *in_flight >= self.limit.max_in_flight {
+            if self.limit.overflow == PoolOverflow::FailFast {
+                return Err(ResolveError::ResolverBusy.into());
+            }
+            let left = deadline.saturating_duration_since(Instant::now());
+            if left.is_zero() {
+                return Err(io::ErrorKind::TimedOut.into());
+            }
+            in_flight = self
+                .released
+                .wait_timeout(in_flight, left)
+                .unwrap_or_else(|e| e.into_inner())
+                .0;
+        }
+        *in_flight += 1;
+        drop(in_flight);
+
+        let _slot = Slot(self);
+        lookup(deadline.saturating_duration_since(Instant::now()))
+    }
+}
+
+/// A lookup in flight, counted until dropped.
+struct Slot<'a>(&'a Limited);
+
+impl Drop for Slot<'_> {
+    fn drop(&mut self) {
+        *self.0.in_flight.lock().unwrap_or_else(|e| e.into_inner()) -= 1;
+        self.0.released.notify_one();
+    }
+}
+
+impl ResolverBackend for Limited {
+    fn resolve(
+        &self,
+        host: &str,
+        port: u16,
+        timeout: Duration,
+    ) -> io::Result<std::vec::IntoIter<SocketAddr>> {
+        self.limit(timeout, |timeout| self.inner.resolve(host, port, timeout))
+    }
+
+    fn resolve_with_hints(
+        &self,
+        host: &str,
+        port: u16,
+        hints: &Hints,
+        timeout: Duration,
+    ) -> io::Result<std::vec::IntoIter<SocketAddr>> {
+        self.limit(timeout, |timeout| {
+            self.inner.resolve_with_hints(host, port, hints, timeout)
+        })
+    }
+
+    fn resolve_service(
+        &self,
+        host: &str,
+        service: &str,
+        hints: &Hints,
+        timeout: Duration,
+    ) -> io::Result<std::vec::IntoIter<SocketAddr>> {
+        self.limit(timeout, |timeout| {
+            self.inner.resolve_service(host, service, hints, timeout)
+        })
+    }
+
+    fn resolve_canonical(
+        &self,
+        host: &str,
+        port: u16,
+        hints: &Hints,
+        timeout: Duration,
+    ) -> io::Result<CanonicalAddrs> {
+        self.limit(timeout, |timeout| {
+            self.inner.resolve_canonical(host, port, hints, timeout)
+        })
+    }
+
+    fn resolve_with_ttl(
+        &self,
+        host: &str,
+        port: u16,
+        hints: &Hints,
+        timeout: Duration,
+    ) -> io::Result<Vec<ResolvedAddr>> {
+        self.limit(timeout, |timeout| {
+            self.inner.resolve_with_ttl(host, port, hints, timeout)
+        })
+    }
+}
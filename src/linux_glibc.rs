@@ -1,9 +1,17 @@
 use std::{
+    cell::UnsafeCell,
+    future::Future,
     net::{Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6},
+    pin::Pin,
+    sync::{Arc, OnceLock},
+    task::{ready, Context as TaskContext, Poll},
     time::{Duration, Instant},
 };
 
-use crate::ToSocketAddrsTimeout;
+use crate::{
+    addrinfo, completion::Completion, fallback, orphan::Orphan, AddrInfo, AddrInfoIter,
+    AddressInfoError, CanonicalAddrs, HintFlags, Hints, Protocol, ResolverBackend, SocketType,
+};
 
 #[repr(C)]
 #[allow(non_camel_case_types)]
@@ -18,12 +26,14 @@ struct gaicb {
 
 impl gaicb {
     pub unsafe fn new(
-        name: &::core::ffi::CStr,
+        name: Option<&::core::ffi::CStr>,
         service: Option<&::core::ffi::CStr>,
         request: Option<&libc::addrinfo>,
     ) -> Self {
         Self {
-            name: name.as_ptr(),
+            name: name
+                .map(::core::ffi::CStr::as_ptr)
+                .unwrap_or_else(core::ptr::null),
             service: service
                 .map(::core::ffi::CStr::as_ptr)
                 .unwrap_or_else(core::ptr::null),
@@ -40,28 +50,10 @@ impl gaicb {
 #[repr(transparent)]
 #[must_use]
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
-struct AddressInfoError(::core::ffi::c_int);
-
-impl core::fmt::Display for AddressInfoError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        #[link(name = "c")]
-        extern "C" {
-            fn gai_strerror(errcode: ::core::ffi::c_int) -> *const core::ffi::c_char;
-        }
-
-        unsafe {
-            let ptr = gai_strerror(self.0);
-            if ptr.is_null() {
-                f.write_str("unknown")
-            } else {
-                core::fmt::Display::fmt(&::core::ffi::CStr::from_ptr(ptr).to_string_lossy(), f)
-            }
-        }
-    }
-}
+struct GaiError(::core::ffi::c_int);
 
 #[allow(non_upper_case_globals)]
-impl AddressInfoError {
+impl GaiError {
     /// Invalid value for `ai_flags' field.
     pub const BadFlags: Self = Self(-1);
     /// NAME or SERVICE is unknown.
@@ -100,53 +92,112 @@ impl AddressInfoError {
     pub const IdnEncode: Self = Self(-105);
 }
 
-impl core::fmt::Debug for AddressInfoError {
+impl core::fmt::Debug for GaiError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match *self {
-            Self::BadFlags => f.write_str("AddressInfoError::BadFlags"),
-            Self::NoName => f.write_str("AddressInfoError::NoName"),
-            Self::Again => f.write_str("AddressInfoError::Again"),
-            Self::Fail => f.write_str("AddressInfoError::Fail"),
-            Self::Family => f.write_str("AddressInfoError::Family"),
-            Self::Socktype => f.write_str("AddressInfoError::Socktype"),
-            Self::Service => f.write_str("AddressInfoError::Service"),
-            Self::Memory => f.write_str("AddressInfoError::Memory"),
-            Self::System => f.write_str("AddressInfoError::System"),
-            Self::Overflow => f.write_str("AddressInfoError::Overflow"),
-            Self::NoData => f.write_str("AddressInfoError::NoData"),
-            Self::AddrFamily => f.write_str("AddressInfoError::AddrFamily"),
-            Self::InProgress => f.write_str("AddressInfoError::InProgress"),
-            Self::Canceled => f.write_str("AddressInfoError::Canceled"),
-            Self::NotCanceled => f.write_str("AddressInfoError::NotCanceled"),
-            Self::AllDone => f.write_str("AddressInfoError::AllDone"),
-            Self::Interrupted => f.write_str("AddressInfoError::Interrupted"),
-            Self::IdnEncode => f.write_str("AddressInfoError::IdnEncode"),
-            _ => f.write_str("AddressInfoError::Unknown"),
+            Self::BadFlags => f.write_str("GaiError::BadFlags"),
+            Self::NoName => f.write_str("GaiError::NoName"),
+            Self::Again => f.write_str("GaiError::Again"),
+            Self::Fail => f.write_str("GaiError::Fail"),
+            Self::Family => f.write_str("GaiError::Family"),
+            Self::Socktype => f.write_str("GaiError::Socktype"),
+            Self::Service => f.write_str("GaiError::Service"),
+            Self::Memory => f.write_str("GaiError::Memory"),
+            Self::System => f.write_str("GaiError::System"),
+            Self::Overflow => f.write_str("GaiError::Overflow"),
+            Self::NoData => f.write_str("GaiError::NoData"),
+            Self::AddrFamily => f.write_str("GaiError::AddrFamily"),
+            Self::InProgress => f.write_str("GaiError::InProgress"),
+            Self::Canceled => f.write_str("GaiError::Canceled"),
+            Self::NotCanceled => f.write_str("GaiError::NotCanceled"),
+            Self::AllDone => f.write_str("GaiError::AllDone"),
+            Self::Interrupted => f.write_str("GaiError::Interrupted"),
+            Self::IdnEncode => f.write_str("GaiError::IdnEncode"),
+            _ => f.write_str("GaiError::Unknown"),
         }
     }
 }
 
-impl std::error::Error for AddressInfoError {}
-
-impl From<AddressInfoError> for std::io::Error {
-    fn from(value: AddressInfoError) -> Self {
-        if value == AddressInfoError::System {
+impl From<GaiError> for std::io::Error {
+    fn from(value: GaiError) -> Self {
+        if value == GaiError::System {
             std::io::Error::from_raw_os_error(unsafe { *libc::__errno_location() })
         } else {
-            std::io::Error::new(std::io::ErrorKind::Other, value)
+            AddressInfoError::from_raw_code(value.0).into()
         }
     }
 }
 
-struct LookupHost {
+pub(crate) struct LookupHost {
     original: *mut libc::addrinfo,
     cur: *mut libc::addrinfo,
-    port: u16,
+    port: Option<u16>,
 }
 
 impl LookupHost {
-    pub fn port(&self) -> u16 {
-        self.port
+    /// The address of `ai` with the port of the lookup, `None` for families other than IPv4 and
+    /// IPv6.
+    fn addr_of(&self, ai: &libc::addrinfo) -> Option<SocketAddr> {
+        let mut addr = sockaddr_to_addr(
+            unsafe { &*(ai.ai_addr as *const libc::sockaddr_storage) },
+            ai.ai_addrlen as usize,
+        )
+        .ok()?;
+        if let Some(port) = self.port {
+            addr.set_port(port);
+        }
+        Some(addr)
+    }
+
+    /// The addresses not returned yet, leaving them to be.
+    pub(crate) fn remaining(&self) -> impl Iterator<Item = SocketAddr> + '_ {
+        let mut cur = self.cur;
+        std::iter::from_fn(move || loop {
+            let ai = unsafe { cur.as_ref()? };
+            cur = ai.ai_next;
+            if let Some(addr) = self.addr_of(ai) {
+                return Some(addr);
+            }
+        })
+    }
+
+    /// The `ai_canonname` of the first entry, set when asked for with `AI_CANONNAME`.
+    fn canonical_name(&self) -> Option<String> {
+        let first = unsafe { self.original.as_ref()? };
+        let name = unsafe { first.ai_canonname.as_ref() }?;
+        Some(
+            unsafe { ::core::ffi::CStr::from_ptr(name) }
+                .to_string_lossy()
+                .into_owned(),
+        )
+    }
+
+    /// Every entry in full, skipping those of families other than IPv4 and IPv6.
+    fn records(&self) -> Vec<AddrInfo> {
+        let mut records = Vec::new();
+        let mut cur = self.original;
+        while let Some(ai) = unsafe { cur.as_ref() } {
+            cur = ai.ai_next;
+            let Ok(addr) = sockaddr_to_addr(
+                unsafe { &*(ai.ai_addr as *const libc::sockaddr_storage) },
+                ai.ai_addrlen as usize,
+            ) else {
+                continue;
+            };
+            records.push(AddrInfo {
+                family: addrinfo::family_of(&addr),
+                socktype: SocketType::from_libc(ai.ai_socktype),
+                protocol: Protocol::from_libc(ai.ai_protocol),
+                flags: HintFlags::from_native(ai.ai_flags, &AI_FLAGS),
+                canonical_name: unsafe { ai.ai_canonname.as_ref() }.map(|name| {
+                    unsafe { ::core::ffi::CStr::from_ptr(name) }
+                        .to_string_lossy()
+                        .into_owned()
+                }),
+                addr,
+            });
+        }
+        records
     }
 }
 
@@ -186,16 +237,10 @@ impl Iterator for LookupHost {
 
     fn next(&mut self) -> Option<SocketAddr> {
         loop {
-            unsafe {
-                let cur = self.cur.as_ref()?;
-                self.cur = cur.ai_next;
-                match sockaddr_to_addr(
-                    &*(cur.ai_addr as *const libc::sockaddr_storage),
-                    cur.ai_addrlen as usize,
-                ) {
-                    Ok(addr) => return Some(addr),
-                    Err(_) => continue,
-                }
+            let ai = unsafe { self.cur.as_ref()? };
+            self.cur = ai.ai_next;
+            if let Some(addr) = self.addr_of(ai) {
+                return Some(addr);
             }
         }
     }
@@ -212,174 +257,703 @@ unsafe impl Send for LookupHost {}
 
 fn d2ts(duration: Duration) -> libc::timespec {
     libc::timespec {
-        tv_sec: duration.as_secs() as _,
+        tv_sec: duration.as_secs().min(libc::time_t::MAX as u64) as _,
         tv_nsec: duration.subsec_nanos() as _,
     }
 }
 
-/// BUG: this is a bad implementation beacuse it does not handle `AddressInfoError::NotCanceled`
-fn getaddrinfo_timeout(
-    hostname: &::core::ffi::CStr,
-    service: Option<&::core::ffi::CStr>,
-    hints: Option<&libc::addrinfo>,
-    timeout: Duration,
-) -> std::io::Result<LookupHost> {
-    struct GaicbGuard(*mut gaicb);
-    impl GaicbGuard {
-        pub fn run(self) -> AddressInfoError {
-            let list = self.0;
-            core::mem::forget(self);
-            unsafe { gai_cancel(list) }
+/// `struct sigevent` as laid out by glibc when `sigev_notify == SIGEV_THREAD`.
+#[repr(C)]
+#[allow(non_camel_case_types)]
+struct sigevent_thread {
+    pub sigev_value: libc::sigval,
+    pub sigev_signo: ::core::ffi::c_int,
+    pub sigev_notify: ::core::ffi::c_int,
+    pub sigev_notify_function: Option<unsafe extern "C" fn(libc::sigval)>,
+    pub sigev_notify_attributes: *mut libc::pthread_attr_t,
+    __pad: [u8; 64
+        - 3 * core::mem::size_of::<usize>()
+        - 2 * core::mem::size_of::<::core::ffi::c_int>()],
+}
+
+const _: () = assert!(core::mem::size_of::<sigevent_thread>() == 64);
+
+type GetaddrinfoAFn = unsafe extern "C" fn(
+    mode: ::core::ffi::c_int,
+    list: *mut *mut gaicb,
+    n: core::ffi::c_int,
+    sevp: *mut libc::sigevent,
+) -> GaiError;
+
+type GaiFn = unsafe extern "C" fn(req: *mut gaicb) -> GaiError;
+
+type GaiSuspendFn = unsafe extern "C" fn(
+    req: *const *const gaicb,
+    n: ::core::ffi::c_int,
+    timeout: *const libc::timespec,
+) -> GaiError;
+
+/// The `getaddrinfo_a` functions, looked up at runtime: they live in libc since glibc 2.34 and in
+/// libanl before, and are missing from some builds, which then use the thread based lookup.
+struct Api {
+    getaddrinfo_a: GetaddrinfoAFn,
+    gai_cancel: GaiFn,
+    gai_error: GaiFn,
+    gai_suspend: GaiSuspendFn,
+}
+
+fn api() -> Option<&'static Api> {
+    static API: OnceLock<Option<Api>> = OnceLock::new();
+    API.get_or_init(|| unsafe {
+        let mut lib = libc::RTLD_DEFAULT;
+        if libc::dlsym(lib, c"getaddrinfo_a".as_ptr()).is_null() {
+            // the library stays loaded for the symbols to remain valid
+            lib = libc::dlopen(c"libanl.so.1".as_ptr(), libc::RTLD_NOW);
+            if lib.is_null() {
+                return None;
+            }
         }
-    }
-    impl Drop for GaicbGuard {
-        fn drop(&mut self) {
-            _ = unsafe { gai_cancel(self.0) };
+        let getaddrinfo_a = libc::dlsym(lib, c"getaddrinfo_a".as_ptr());
+        let gai_cancel = libc::dlsym(lib, c"gai_cancel".as_ptr());
+        let gai_error = libc::dlsym(lib, c"gai_error".as_ptr());
+        let gai_suspend = libc::dlsym(lib, c"gai_suspend".as_ptr());
+        if [getaddrinfo_a, gai_cancel, gai_error, gai_suspend]
+            .iter()
+            .any(|f| f.is_null())
+        {
+            return None;
         }
-    }
+        Some(Api {
+            getaddrinfo_a: core::mem::transmute::<*mut ::core::ffi::c_void, GetaddrinfoAFn>(
+                getaddrinfo_a,
+            ),
+            gai_cancel: core::mem::transmute::<*mut ::core::ffi::c_void, GaiFn>(gai_cancel),
+            gai_error: core::mem::transmute::<*mut ::core::ffi::c_void, GaiFn>(gai_error),
+            gai_suspend: core::mem::transmute::<*mut ::core::ffi::c_void, GaiSuspendFn>(
+                gai_suspend,
+            ),
+        })
+    })
+    .as_ref()
+}
 
-    #[link(name = "c")]
-    extern "C" {
-        fn getaddrinfo_a(
-            mode: ::core::ffi::c_int,
-            list: *mut *mut gaicb,
-            n: core::ffi::c_int,
-            sevp: *mut libc::sigevent,
-        ) -> AddressInfoError;
+/// Whether the asynchronous resolver functions are there, lookups running on worker threads
+/// otherwise.
+pub(crate) fn has_async_api() -> bool {
+    api().is_some()
+}
+
+const GAI_NOWAIT: ::core::ffi::c_int = 1;
+
+/// A heap allocated `getaddrinfo_a` request.
+///
+/// glibc keeps writing into the `gaicb` until the request completes, even if the caller gave up
+/// on it, so the completion callback owns a reference to the request until it runs. A request
+/// that could not be canceled is counted as an orphan until that reference is released, which
+/// also frees its `addrinfo`.
+struct Request {
+    api: &'static Api,
+    cb: UnsafeCell<gaicb>,
+    hostname: Option<CName>,
+    service: Option<CName>,
+    hints: libc::addrinfo,
+    completion: Completion<()>,
+    orphan: OnceLock<Orphan>,
+}
 
-        fn gai_cancel(req: *mut gaicb) -> AddressInfoError;
+unsafe impl Sync for Request {}
+unsafe impl Send for Request {}
 
-        fn gai_error(req: *mut gaicb) -> AddressInfoError;
+unsafe extern "C" fn notify_callback(value: libc::sigval) {
+    let req = Arc::from_raw(value.sival_ptr as *const Request);
+    req.completion.complete(());
+}
 
-        fn gai_suspend(
-            req: *const *const gaicb,
-            n: ::core::ffi::c_int,
-            timeout: *const libc::timespec,
-        ) -> AddressInfoError;
+impl Request {
+    /// A request not submitted yet.
+    fn new(
+        api: &'static Api,
+        hostname: Option<CName>,
+        service: Option<CName>,
+        hints: libc::addrinfo,
+    ) -> Arc<Self> {
+        let req = Arc::new(Self {
+            api,
+            cb: UnsafeCell::new(unsafe { core::mem::zeroed() }),
+            hostname,
+            service,
+            hints,
+            completion: Completion::new(),
+            orphan: OnceLock::new(),
+        });
+        unsafe {
+            *req.cb.get() = gaicb::new(
+                req.hostname.as_deref(),
+                req.service.as_deref(),
+                Some(&req.hints),
+            )
+        };
+        req
     }
-    const GAI_NOWAIT: ::core::ffi::c_int = 1;
 
-    let mut host = unsafe { gaicb::new(hostname, service, hints) };
-    let mut list = [&mut host as *mut gaicb];
+    pub fn start(
+        api: &'static Api,
+        hostname: Option<CName>,
+        service: Option<CName>,
+        hints: libc::addrinfo,
+    ) -> std::io::Result<Arc<Self>> {
+        let req = Self::new(api, hostname, service, hints);
+        let mut list = [req.cb.get()];
+
+        let mut handler: sigevent_thread = unsafe { core::mem::zeroed() };
+        handler.sigev_notify = libc::SIGEV_THREAD;
+        handler.sigev_notify_function = Some(notify_callback);
+        handler.sigev_value.sival_ptr = Arc::into_raw(req.clone()) as *mut _;
+
+        let ret = unsafe {
+            (api.getaddrinfo_a)(
+                GAI_NOWAIT,
+                list.as_mut_ptr(),
+                1,
+                (&mut handler as *mut sigevent_thread).cast(),
+            )
+        };
+        if ret.0 != 0 {
+            // nothing was queued, so no notification will ever run
+            unsafe { Arc::decrement_strong_count(Arc::as_ptr(&req)) };
+            return Err(ret.into());
+        }
+        Ok(req)
+    }
 
-    let mut handler: libc::sigevent = unsafe { core::mem::zeroed() };
-    handler.sigev_notify = libc::SIGEV_NONE;
+    #[inline(always)]
+    fn as_ptr(&self) -> *mut gaicb {
+        self.cb.get()
+    }
+
+    /// Whether glibc is still working on the request.
+    pub fn in_progress(&self) -> bool {
+        unsafe { (self.api.gai_error)(self.as_ptr()) == GaiError::InProgress }
+    }
+
+    /// Cancels the request, releasing the callback's reference if it will never run.
+    ///
+    /// A request glibc is already processing keeps running and is marked as an orphan.
+    pub fn cancel(self: &Arc<Self>) -> GaiError {
+        let ret = unsafe { (self.api.gai_cancel)(self.as_ptr()) };
+        match ret {
+            GaiError::Canceled => unsafe { Arc::decrement_strong_count(Arc::as_ptr(self)) },
+            GaiError::NotCanceled => {
+                self.orphan.get_or_init(Orphan::new);
+            }
+            _ => (),
+        }
+        ret
+    }
 
-    let ret = unsafe { getaddrinfo_a(GAI_NOWAIT, list.as_mut_ptr(), 1, &mut handler) };
-    if ret.0 != 0 {
-        return Err(ret.into());
+    /// Takes the outcome of a completed request, setting `port` on the addresses if given.
+    pub fn result(&self, port: Option<u16>) -> std::io::Result<LookupHost> {
+        match unsafe { (self.api.gai_error)(self.as_ptr()) } {
+            GaiError(0) => {
+                let addrinfo = unsafe {
+                    core::mem::replace(&mut (*self.as_ptr()).addrinfo, core::ptr::null_mut())
+                };
+                Ok(LookupHost {
+                    original: addrinfo,
+                    cur: addrinfo,
+                    port,
+                })
+            }
+            err => Err(err.into()),
+        }
     }
-    let guard = GaicbGuard(&mut host);
+}
 
-    let end = Instant::now() + timeout;
+impl Drop for Request {
+    fn drop(&mut self) {
+        let addrinfo = self.cb.get_mut().addrinfo;
+        if !addrinfo.is_null() {
+            unsafe { libc::freeaddrinfo(addrinfo) };
+        }
+    }
+}
+
+fn getaddrinfo_timeout(
+    api: &'static Api,
+    hostname: Option<CName>,
+    service: Option<CName>,
+    hints: libc::addrinfo,
+    port: Option<u16>,
+    timeout: Duration,
+) -> std::io::Result<LookupHost> {
+    let req = Request::start(api, hostname, service, hints)?;
+    let list = [req.as_ptr() as *const gaicb];
+
+    let end = crate::deadline_after(timeout);
     loop {
+        if !req.in_progress() {
+            return req.result(port);
+        }
         let Some(timeout) = end.checked_duration_since(Instant::now()) else {
-            return Err(std::io::ErrorKind::TimedOut.into());
+            // only a request that completed before the cancel has an answer, one canceled while
+            // queued reports EAI_CANCELED
+            let done = req.cancel() == GaiError::AllDone
+                && unsafe { (api.gai_error)(req.as_ptr()) } != GaiError::Canceled;
+            return if done {
+                req.result(port)
+            } else {
+                Err(std::io::ErrorKind::TimedOut.into())
+            };
         };
-        let ret = unsafe { gai_suspend(list.as_ptr().cast(), 1, &d2ts(timeout)) };
-        if ret.0 == 0 {
-            if unsafe { gai_error(&mut host) }.0 == 0 {
-                return Ok(LookupHost {
-                    original: host.addrinfo,
-                    cur: host.addrinfo,
-                    port: 0,
-                });
+        match unsafe { (api.gai_suspend)(list.as_ptr(), 1, &d2ts(timeout)) } {
+            GaiError(0) | GaiError::AllDone | GaiError::Again | GaiError::Interrupted => continue,
+            // some glibc versions report an expired wait as a system error
+            GaiError::System
+                if unsafe { *libc::__errno_location() } == libc::EINTR || Instant::now() >= end =>
+            {
+                continue
+            }
+            ret => {
+                _ = req.cancel();
+                return Err(ret.into());
+            }
+        }
+    }
+}
+
+/// In-flight lookup driven by the `SIGEV_THREAD` completion notification.
+pub(crate) struct Lookup(Inner);
+
+enum Inner {
+    Native {
+        req: Arc<Request>,
+        port: u16,
+        done: bool,
+    },
+    /// Without `getaddrinfo_a`.
+    Thread(fallback::Lookup),
+}
+
+impl Future for Lookup {
+    type Output = std::io::Result<std::vec::IntoIter<SocketAddr>>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut TaskContext<'_>) -> Poll<Self::Output> {
+        match &mut self.get_mut().0 {
+            Inner::Native { req, port, done } => {
+                ready!(req.completion.poll(cx));
+                *done = true;
+                Poll::Ready(req.result(Some(*port)).and_then(resolve_socket_addr))
             }
-            continue;
+            Inner::Thread(lookup) => Pin::new(lookup).poll(cx),
         }
-        if ret == AddressInfoError::System && unsafe { *libc::__errno_location() } == libc::EINTR {
-            continue;
+    }
+}
+
+impl Drop for Lookup {
+    fn drop(&mut self) {
+        if let Inner::Native {
+            ref req,
+            done: false,
+            ..
+        } = self.0
+        {
+            _ = req.cancel();
         }
-        return if guard.run() == AddressInfoError::AllDone {
-            Ok(LookupHost {
-                original: host.addrinfo,
-                cur: host.addrinfo,
-                port: 0,
-            })
-        } else {
-            Err(ret.into())
-        };
     }
 }
 
-impl TryFrom<(&str, Duration)> for LookupHost {
-    type Error = std::io::Error;
+/// Longest name kept inline, NUL included, room enough for any DNS name.
+const INLINE_NAME: usize = 256;
+
+/// A NUL-terminated host name or service, kept in the request itself when it fits so that
+/// lookups of ordinary names do not allocate for it.
+#[derive(Clone)]
+#[allow(clippy::large_enum_variant)]
+enum CName {
+    Inline([u8; INLINE_NAME]),
+    Heap(::std::ffi::CString),
+}
+
+impl core::ops::Deref for CName {
+    type Target = ::core::ffi::CStr;
 
-    fn try_from((s, timeout): (&str, Duration)) -> Result<Self, Self::Error> {
-        let (host, port_str) = s.rsplit_once(':').ok_or_else(|| {
-            std::io::Error::new(std::io::ErrorKind::InvalidInput, "invalid socket address")
-        })?;
-        let port: u16 = port_str.parse().map_err(|_| {
-            std::io::Error::new(std::io::ErrorKind::InvalidInput, "invalid port value")
-        })?;
-        (host, port, timeout).try_into()
+    fn deref(&self) -> &::core::ffi::CStr {
+        match self {
+            Self::Inline(buf) => ::core::ffi::CStr::from_bytes_until_nul(buf).unwrap_or_default(),
+            Self::Heap(name) => name,
+        }
     }
 }
 
-impl TryFrom<(&str, u16, Duration)> for LookupHost {
-    type Error = std::io::Error;
+fn hostname_to_cstring(hostname: &str) -> std::io::Result<CName> {
+    let bytes = hostname.as_bytes();
+    if bytes.len() >= INLINE_NAME || bytes.contains(&0) {
+        return ::std::ffi::CString::new(hostname)
+            .map(CName::Heap)
+            .map_err(|_| {
+                std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    "host name contained an unexpected NUL byte",
+                )
+            });
+    }
+    let mut buf = [0; INLINE_NAME];
+    buf[..bytes.len()].copy_from_slice(bytes);
+    Ok(CName::Inline(buf))
+}
 
-    fn try_from((hostname, port, timeout): (&str, u16, Duration)) -> Result<Self, Self::Error> {
-        let hostname = ::std::ffi::CString::new(hostname).map_err(|_| {
-            std::io::Error::new(
-                std::io::ErrorKind::InvalidInput,
-                "host name contained an unexpected NUL byte",
-            )
-        })?;
+const AI_FLAGS: [(HintFlags, ::core::ffi::c_int); 7] = [
+    (HintFlags::PASSIVE, libc::AI_PASSIVE),
+    (HintFlags::CANONNAME, libc::AI_CANONNAME),
+    (HintFlags::NUMERICHOST, libc::AI_NUMERICHOST),
+    (HintFlags::NUMERICSERV, libc::AI_NUMERICSERV),
+    (HintFlags::V4MAPPED, libc::AI_V4MAPPED),
+    (HintFlags::ALL, libc::AI_ALL),
+    (HintFlags::ADDRCONFIG, libc::AI_ADDRCONFIG),
+];
+
+fn addrinfo_hints(hints: &Hints) -> libc::addrinfo {
+    let mut ai: libc::addrinfo = unsafe { core::mem::zeroed() };
+    ai.ai_flags = hints.flags.to_native(&AI_FLAGS);
+    ai.ai_family = hints.libc_family();
+    ai.ai_socktype = hints.libc_socktype();
+    ai.ai_protocol = hints.libc_protocol();
+    ai
+}
 
-        let mut hints: libc::addrinfo = unsafe { core::mem::zeroed() };
-        hints.ai_socktype = libc::SOCK_STREAM;
+fn start(
+    api: &'static Api,
+    hostname: CName,
+    hints: libc::addrinfo,
+    port: u16,
+) -> std::io::Result<Lookup> {
+    Ok(Lookup(Inner::Native {
+        req: Request::start(api, Some(hostname), None, hints)?,
+        port,
+        done: false,
+    }))
+}
 
-        let mut me = getaddrinfo_timeout(&hostname, None, Some(&hints), timeout)?;
-        me.port = port;
-        Ok(me)
+pub(crate) fn lookup(
+    hostname: &str,
+    port: u16,
+    timeout: Option<Duration>,
+) -> std::io::Result<Lookup> {
+    match api() {
+        Some(api) => start(
+            api,
+            hostname_to_cstring(hostname)?,
+            addrinfo_hints(&Hints::default()),
+            port,
+        ),
+        None => Ok(Lookup(Inner::Thread(fallback::lookup(
+            hostname, port, timeout,
+        )?))),
     }
 }
 
-fn resolve_socket_addr(lh: LookupHost) -> std::io::Result<std::vec::IntoIter<SocketAddr>> {
-    let p = lh.port();
-    let v: Vec<_> = lh
-        .map(|mut a| {
-            a.set_port(p);
-            a
+/// Starts separate IPv6 and IPv4 lookups.
+pub(crate) fn lookup_split(
+    hostname: &str,
+    port: u16,
+    timeout: Option<Duration>,
+) -> std::io::Result<Vec<Lookup>> {
+    let Some(api) = api() else {
+        return Ok(fallback::lookup_split(hostname, port, timeout)?
+            .into_iter()
+            .map(|lookup| Lookup(Inner::Thread(lookup)))
+            .collect());
+    };
+    let hostname = hostname_to_cstring(hostname)?;
+    let mut hints = addrinfo_hints(&Hints::default());
+    [libc::AF_INET6, libc::AF_INET]
+        .into_iter()
+        .map(|family| {
+            hints.ai_family = family;
+            start(api, hostname.clone(), hints, port)
         })
-        .collect();
-    Ok(v.into_iter())
+        .collect()
 }
 
-impl ToSocketAddrsTimeout for str {
-    type Iter = std::vec::IntoIter<SocketAddr>;
+fn resolve_socket_addr(lh: LookupHost) -> std::io::Result<std::vec::IntoIter<SocketAddr>> {
+    Ok(lh.collect::<Vec<_>>().into_iter())
+}
 
-    fn to_socket_addrs_timeout(
-        &self,
-        timeout: Duration,
-    ) -> std::io::Result<std::vec::IntoIter<SocketAddr>> {
-        if let Ok(addr) = self.parse() {
-            return Ok(vec![addr].into_iter());
+/// The addresses of `host`, read off the `getaddrinfo_a` list as they are iterated.
+pub(crate) fn resolve_iter(
+    host: &str,
+    port: u16,
+    timeout: Duration,
+) -> std::io::Result<AddrInfoIter> {
+    let Some(api) = api() else {
+        return fallback::SystemResolver
+            .resolve(host, port, timeout)
+            .map(AddrInfoIter::from);
+    };
+    getaddrinfo_timeout(
+        api,
+        Some(hostname_to_cstring(host)?),
+        None,
+        addrinfo_hints(&Hints::default()),
+        Some(port),
+        timeout,
+    )
+    .map(AddrInfoIter::native)
+}
+
+/// The addresses of each of `targets`, submitted together to a single `getaddrinfo_a` call
+/// and waited for with `gai_suspend`, or `None` where `getaddrinfo_a` is not available.
+///
+/// Requests still queued at the deadline are canceled, those glibc is already processing are
+/// abandoned to a thread keeping them alive until they complete, counted as orphans.
+pub(crate) fn resolve_many(
+    targets: &[(&str, u16)],
+    timeout: Duration,
+) -> Option<Vec<std::io::Result<AddrInfoIter>>> {
+    let api = api()?;
+    let mut results: Vec<_> = targets.iter().map(|_| None).collect();
+    let mut reqs = Vec::with_capacity(targets.len());
+    for (i, &(host, port)) in targets.iter().enumerate() {
+        match hostname_to_cstring(host) {
+            Ok(host) => {
+                let hints = addrinfo_hints(&Hints::default());
+                reqs.push((i, port, Request::new(api, Some(host), None, hints)));
+            }
+            Err(err) => results[i] = Some(Err(err)),
+        }
+    }
+
+    let mut list: Vec<_> = reqs.iter().map(|(_, _, req)| req.as_ptr()).collect();
+    let submitted = if list.is_empty() {
+        GaiError(0)
+    } else {
+        unsafe {
+            (api.getaddrinfo_a)(
+                GAI_NOWAIT,
+                list.as_mut_ptr(),
+                list.len() as _,
+                core::ptr::null_mut(),
+            )
+        }
+    };
+
+    let end = crate::deadline_after(timeout);
+    let mut failed = None;
+    loop {
+        let pending: Vec<_> = reqs
+            .iter()
+            .filter(|(_, _, req)| req.in_progress())
+            .map(|(_, _, req)| req.as_ptr() as *const gaicb)
+            .collect();
+        if pending.is_empty() {
+            break;
+        }
+        let Some(timeout) = end.checked_duration_since(Instant::now()) else {
+            break;
+        };
+        match unsafe { (api.gai_suspend)(pending.as_ptr(), pending.len() as _, &d2ts(timeout)) } {
+            GaiError(0) | GaiError::AllDone | GaiError::Again | GaiError::Interrupted => continue,
+            GaiError::System
+                if unsafe { *libc::__errno_location() } == libc::EINTR || Instant::now() >= end =>
+            {
+                continue
+            }
+            ret => {
+                failed = Some(ret);
+                break;
+            }
         }
+    }
 
-        resolve_socket_addr((self, timeout).try_into()?)
+    let mut running = Vec::new();
+    for (i, port, req) in reqs {
+        let stopped = req.in_progress() && {
+            // glibc forgets a request canceled while queued, and without a completion
+            // notification no callback holds a reference to release
+            let ret = unsafe { (api.gai_cancel)(req.as_ptr()) };
+            if ret == GaiError::NotCanceled {
+                running.push(req.clone());
+            }
+            ret != GaiError::AllDone
+        };
+        results[i] = Some(if stopped {
+            Err(failed.map_or_else(|| std::io::ErrorKind::TimedOut.into(), Into::into))
+        } else if submitted != GaiError(0)
+            && unsafe { (api.gai_error)(req.as_ptr()) } == GaiError(0)
+            && unsafe { (*req.as_ptr()).addrinfo.is_null() }
+        {
+            // left out of a list glibc could only queue part of
+            Err(submitted.into())
+        } else {
+            req.result(Some(port)).map(AddrInfoIter::native)
+        });
+    }
+    if !running.is_empty() {
+        abandon(api, running);
     }
+    Some(results.into_iter().flatten().collect())
+}
+
+/// Keeps `running` alive on a thread until glibc completes them.
+fn abandon(api: &'static Api, running: Vec<Arc<Request>>) {
+    let running = Arc::new(running);
+    let spawned = std::thread::Builder::new()
+        .name("to_socket_addrs_timeout-glibc".into())
+        .spawn({
+            let running = running.clone();
+            let orphan = Orphan::new();
+            move || {
+                let list: Vec<_> = running
+                    .iter()
+                    .map(|req| req.as_ptr() as *const gaicb)
+                    .collect();
+                while running.iter().any(|req| req.in_progress()) {
+                    _ = unsafe {
+                        (api.gai_suspend)(list.as_ptr(), list.len() as _, core::ptr::null())
+                    };
+                }
+                drop(orphan);
+            }
+        });
+    if spawned.is_err() {
+        // glibc may still write into the requests, which are leaked rather than freed
+        core::mem::forget(running);
+    }
+}
+
+/// Wildcard addresses for `service`, from `getaddrinfo_a` with `AI_PASSIVE` and no name, or
+/// `None` where `getaddrinfo_a` is not available.
+pub(crate) fn bind_addrs(
+    service: &str,
+    timeout: Duration,
+) -> Option<std::io::Result<std::vec::IntoIter<SocketAddr>>> {
+    let api = api()?;
+    let hints = Hints {
+        flags: HintFlags::PASSIVE,
+        ..Hints::default()
+    };
+    Some(
+        hostname_to_cstring(service)
+            .and_then(|service| {
+                getaddrinfo_timeout(
+                    api,
+                    None,
+                    Some(service),
+                    addrinfo_hints(&hints),
+                    None,
+                    timeout,
+                )
+            })
+            .and_then(resolve_socket_addr),
+    )
+}
+
+/// The `getaddrinfo_a` entries for `host` and `service`, or `None` where `getaddrinfo_a` is not
+/// available.
+pub(crate) fn lookup_addrinfo(
+    host: Option<&str>,
+    service: Option<&str>,
+    hints: &Hints,
+    timeout: Duration,
+) -> Option<std::io::Result<Vec<AddrInfo>>> {
+    let api = api()?;
+    let lookup = || {
+        getaddrinfo_timeout(
+            api,
+            host.map(hostname_to_cstring).transpose()?,
+            service.map(hostname_to_cstring).transpose()?,
+            addrinfo_hints(hints),
+            None,
+            timeout,
+        )
+    };
+    Some(lookup().map(|lh| lh.records()))
 }
 
-impl ToSocketAddrsTimeout for (&str, u16) {
-    type Iter = std::vec::IntoIter<SocketAddr>;
+/// The platform resolver: glibc `getaddrinfo_a`, canceled with `gai_cancel` on timeout.
+///
+/// Where `getaddrinfo_a` is not available lookups run `getaddrinfo` on a pool of worker threads
+/// instead.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemResolver;
 
-    fn to_socket_addrs_timeout(
+impl ResolverBackend for SystemResolver {
+    fn resolve(
         &self,
+        host: &str,
+        port: u16,
         timeout: Duration,
-    ) -> ::std::io::Result<std::vec::IntoIter<SocketAddr>> {
-        let (host, port) = *self;
+    ) -> std::io::Result<std::vec::IntoIter<SocketAddr>> {
+        self.resolve_with_hints(host, port, &Hints::default(), timeout)
+    }
 
-        if let Ok(addr) = host.parse::<Ipv4Addr>() {
-            let addr = SocketAddrV4::new(addr, port);
-            return Ok(vec![SocketAddr::V4(addr)].into_iter());
-        }
-        if let Ok(addr) = host.parse::<Ipv6Addr>() {
-            let addr = SocketAddrV6::new(addr, port, 0, 0);
-            return Ok(vec![SocketAddr::V6(addr)].into_iter());
-        }
+    fn resolve_with_hints(
+        &self,
+        host: &str,
+        port: u16,
+        hints: &Hints,
+        timeout: Duration,
+    ) -> std::io::Result<std::vec::IntoIter<SocketAddr>> {
+        let Some(api) = api() else {
+            return fallback::SystemResolver.resolve_with_hints(host, port, hints, timeout);
+        };
+        getaddrinfo_timeout(
+            api,
+            Some(hostname_to_cstring(host)?),
+            None,
+            addrinfo_hints(hints),
+            Some(port),
+            timeout,
+        )
+        .and_then(resolve_socket_addr)
+    }
+
+    fn resolve_service(
+        &self,
+        host: &str,
+        service: &str,
+        hints: &Hints,
+        timeout: Duration,
+    ) -> std::io::Result<std::vec::IntoIter<SocketAddr>> {
+        let Some(api) = api() else {
+            return fallback::SystemResolver.resolve_service(host, service, hints, timeout);
+        };
+        getaddrinfo_timeout(
+            api,
+            Some(hostname_to_cstring(host)?),
+            Some(hostname_to_cstring(service)?),
+            addrinfo_hints(hints),
+            None,
+            timeout,
+        )
+        .and_then(resolve_socket_addr)
+    }
 
-        resolve_socket_addr((host, port, timeout).try_into()?)
+    fn resolve_canonical(
+        &self,
+        host: &str,
+        port: u16,
+        hints: &Hints,
+        timeout: Duration,
+    ) -> std::io::Result<CanonicalAddrs> {
+        let Some(api) = api() else {
+            return fallback::SystemResolver.resolve_canonical(host, port, hints, timeout);
+        };
+        let hints = Hints {
+            flags: hints.flags | HintFlags::CANONNAME,
+            ..*hints
+        };
+        let lh = getaddrinfo_timeout(
+            api,
+            Some(hostname_to_cstring(host)?),
+            None,
+            addrinfo_hints(&hints),
+            Some(port),
+            timeout,
+        )?;
+        Ok(CanonicalAddrs {
+            name: lh.canonical_name().unwrap_or_else(|| host.to_owned()),
+            addrs: resolve_socket_addr(lh)?.collect(),
+        })
     }
 }
@@ -0,0 +1,25 @@
+use std::{
+    io,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddrV4, SocketAddrV6},
+    time::Instant,
+};
+
+use crate::{dns::encode_name, multicast::Protocol};
+
+/// Whether LLMNR applies to `host`, a single label name.
+pub(crate) fn applies(host: &str) -> bool {
+    let host = host.strip_suffix('.').unwrap_or(host);
+    !host.is_empty() && !host.contains('.')
+}
+
+/// Resolves `host` with LLMNR, multicasting on 224.0.0.252 and ff02::1:3 until `deadline`.
+pub(crate) fn lookup(host: &str, id: u16, deadline: Instant) -> io::Result<Vec<IpAddr>> {
+    let protocol = Protocol {
+        group_v4: SocketAddrV4::new(Ipv4Addr::new(224, 0, 0, 252), 5355),
+        group_v6: SocketAddrV6::new(Ipv6Addr::new(0xff02, 0, 0, 0, 0, 0, 1, 3), 5355, 0, 0),
+        // link-local scope, as required by RFC 4795
+        ttl_v4: 1,
+        id,
+    };
+    protocol.query(&encode_name(host)?, deadline)
+}
@@ -0,0 +1,22 @@
+use std::{io, time::Duration};
+
+use crate::ResolveError;
+
+/// Records a lookup made by `backend` which took `elapsed` and failed with `error`, if it did.
+///
+/// Every lookup counts in `dns_lookups_total` and `dns_lookup_duration_seconds`, failures in
+/// `dns_lookup_failures_total` by the [`ResolveError`] variant they read into, `kind`, and
+/// timeouts in `dns_lookup_timeouts_total` as well.
+pub(crate) fn record(backend: &'static str, elapsed: Duration, error: Option<&io::Error>) {
+    ::metrics::counter!("dns_lookups_total", "backend" => backend).increment(1);
+    ::metrics::histogram!("dns_lookup_duration_seconds", "backend" => backend)
+        .record(elapsed.as_secs_f64());
+    if let Some(err) = error {
+        let kind = ResolveError::label_of(err);
+        if kind == "timeout" {
+            ::metrics::counter!("dns_lookup_timeouts_total", "backend" => backend).increment(1);
+        }
+        ::metrics::counter!("dns_lookup_failures_total", "backend" => backend, "kind" => kind)
+            .increment(1);
+    }
+}
@@ -0,0 +1,45 @@
+use std::{
+    io,
+    net::{Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6},
+    time::Duration,
+};
+
+use crate::{dns::encode_name, multicast::Protocol, ResolverBackend};
+
+const MDNS: Protocol = Protocol {
+    group_v4: SocketAddrV4::new(Ipv4Addr::new(224, 0, 0, 251), 5353),
+    group_v6: SocketAddrV6::new(Ipv6Addr::new(0xff02, 0, 0, 0, 0, 0, 0, 0xfb), 5353, 0, 0),
+    ttl_v4: 255,
+    id: 0,
+};
+
+/// Whether `host` belongs to the `.local` mDNS domain.
+pub(crate) fn is_local(host: &str) -> bool {
+    let host = host.strip_suffix('.').unwrap_or(host).as_bytes();
+    host.len() > 6 && host[host.len() - 6..].eq_ignore_ascii_case(b".local")
+}
+
+/// Resolver multicasting queries on the local link, for `.local` names.
+///
+/// A and AAAA queries are sent from an ephemeral port to 224.0.0.251 and ff02::fb, so that
+/// responders answer with unicast "legacy" responses. Addresses are collected until the
+/// caller's timeout, or shortly after the first answer arrives.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MdnsResolver;
+
+impl ResolverBackend for MdnsResolver {
+    fn resolve(
+        &self,
+        host: &str,
+        port: u16,
+        timeout: Duration,
+    ) -> io::Result<std::vec::IntoIter<SocketAddr>> {
+        let name = encode_name(host)?;
+        let addrs: Vec<_> = MDNS
+            .query(&name, crate::deadline_after(timeout))?
+            .into_iter()
+            .map(|ip| SocketAddr::new(ip, port))
+            .collect();
+        Ok(addrs.into_iter())
+    }
+}
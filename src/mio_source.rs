@@ -0,0 +1,74 @@
+use std::{io, os::fd::AsRawFd, task::Poll, time::Duration};
+
+use mio::{event::Source, unix::SourceFd, Interest, Registry, Token};
+
+use crate::{Readiness, Resolution, ResolvedAddrs};
+
+/// [`Resolution`] registering its [`readiness`](Resolution::readiness) with a [`mio::Poll`],
+/// becoming readable once the lookup may have completed.
+///
+/// It never times out by itself: the event loop gives up on the lookup by dropping it, which
+/// cancels the underlying query where the platform allows it.
+#[must_use = "dropping a MioSource cancels the lookup"]
+pub struct MioSource {
+    resolution: Resolution,
+    readiness: Readiness,
+}
+
+impl MioSource {
+    /// Wraps `resolution`, creating its readiness handle.
+    pub fn new(mut resolution: Resolution) -> io::Result<Self> {
+        let readiness = resolution.readiness()?;
+        Ok(Self {
+            resolution,
+            readiness,
+        })
+    }
+
+    /// Takes the outcome of the lookup without blocking, to be called once the source is
+    /// readable, see [`Resolution::poll`].
+    ///
+    /// # Panics
+    ///
+    /// When polled again after returning [`Poll::Ready`].
+    pub fn poll(&mut self) -> Poll<io::Result<ResolvedAddrs>> {
+        self.resolution.poll(Duration::ZERO)
+    }
+
+    /// Returns the wrapped resolution, which should be deregistered first.
+    pub fn into_inner(self) -> Resolution {
+        self.resolution
+    }
+}
+
+impl core::fmt::Debug for MioSource {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("MioSource")
+            .field("readiness", &self.readiness)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Source for MioSource {
+    fn register(
+        &mut self,
+        registry: &Registry,
+        token: Token,
+        interests: Interest,
+    ) -> io::Result<()> {
+        SourceFd(&self.readiness.as_raw_fd()).register(registry, token, interests)
+    }
+
+    fn reregister(
+        &mut self,
+        registry: &Registry,
+        token: Token,
+        interests: Interest,
+    ) -> io::Result<()> {
+        SourceFd(&self.readiness.as_raw_fd()).reregister(registry, token, interests)
+    }
+
+    fn deregister(&mut self, registry: &Registry) -> io::Result<()> {
+        SourceFd(&self.readiness.as_raw_fd()).deregister(registry)
+    }
+}
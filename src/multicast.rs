@@ -0,0 +1,131 @@
+use std::{
+    io,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6, UdpSocket},
+    time::{Duration, Instant},
+};
+
+use crate::dns::{build_query, names_eq, Message, CLASS_IN, TYPE_A, TYPE_AAAA};
+
+/// How long to keep listening for other records once a responder answered.
+const SETTLE: Duration = Duration::from_millis(100);
+
+/// Largest multicast response, bounded by the 9000 bytes Ethernet jumbo frames.
+const MAX_SIZE: usize = 9000;
+
+/// A link-local multicast name resolution protocol: mDNS or LLMNR.
+pub(crate) struct Protocol {
+    pub group_v4: SocketAddrV4,
+    pub group_v6: SocketAddrV6,
+    /// Multicast TTL of IPv4 queries.
+    pub ttl_v4: u32,
+    /// Query ID, echoed by responses.
+    pub id: u16,
+}
+
+impl Protocol {
+    /// Multicasts A and AAAA queries for the encoded `name` from an ephemeral port, collecting the
+    /// addresses answered until `deadline`, or shortly after the first answer.
+    pub fn query(&self, name: &[u8], mut deadline: Instant) -> io::Result<Vec<IpAddr>> {
+        let mut sockets = Vec::with_capacity(2);
+        let mut last_err = None;
+        for (local, group) in [
+            (
+                SocketAddr::from((Ipv4Addr::UNSPECIFIED, 0)),
+                SocketAddr::V4(self.group_v4),
+            ),
+            (
+                SocketAddr::from((Ipv6Addr::UNSPECIFIED, 0)),
+                SocketAddr::V6(self.group_v6),
+            ),
+        ] {
+            match self.send_queries(local, group, name) {
+                Ok(socket) => sockets.push(socket),
+                Err(err) => last_err = Some(err),
+            }
+        }
+        if sockets.is_empty() {
+            return Err(last_err.unwrap_or_else(|| io::ErrorKind::NotConnected.into()));
+        }
+
+        // with both sockets open, wait on each in turn for a short while
+        let slice = if sockets.len() > 1 {
+            Duration::from_millis(20)
+        } else {
+            Duration::MAX
+        };
+        let mut addrs = Vec::new();
+        let mut buf = vec![0u8; MAX_SIZE];
+        'outer: loop {
+            for socket in &sockets {
+                let Some(remaining) = deadline
+                    .checked_duration_since(Instant::now())
+                    .filter(|d| !d.is_zero())
+                else {
+                    break 'outer;
+                };
+                socket.set_read_timeout(Some(remaining.min(slice)))?;
+                let len = match socket.recv(&mut buf) {
+                    Ok(len) => len,
+                    Err(err)
+                        if matches!(
+                            err.kind(),
+                            io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                        ) =>
+                    {
+                        continue
+                    }
+                    Err(err) => return Err(err),
+                };
+
+                let found = addrs.len();
+                for ip in self.answer_ips(&buf[..len], name) {
+                    if !addrs.contains(&ip) {
+                        addrs.push(ip);
+                    }
+                }
+                if found == 0 && !addrs.is_empty() {
+                    deadline = deadline.min(Instant::now() + SETTLE);
+                }
+            }
+        }
+
+        if addrs.is_empty() {
+            Err(io::ErrorKind::TimedOut.into())
+        } else {
+            Ok(addrs)
+        }
+    }
+
+    fn send_queries(
+        &self,
+        local: SocketAddr,
+        group: SocketAddr,
+        name: &[u8],
+    ) -> io::Result<UdpSocket> {
+        let socket = UdpSocket::bind(local)?;
+        if group.is_ipv4() {
+            socket.set_multicast_ttl_v4(self.ttl_v4)?;
+        }
+        for qtype in [TYPE_AAAA, TYPE_A] {
+            socket.send_to(&build_query(self.id, 0, name, qtype, CLASS_IN), group)?;
+        }
+        Ok(socket)
+    }
+
+    /// Addresses of `name` in a response, from both the answer and additional sections.
+    fn answer_ips(&self, msg: &[u8], name: &[u8]) -> Vec<IpAddr> {
+        let Some(message) = Message::parse(msg) else {
+            return Vec::new();
+        };
+        if !message.is_response() || message.rcode().0 != 0 || message.id != self.id {
+            return Vec::new();
+        }
+        message
+            .answers
+            .iter()
+            .chain(&message.additional)
+            .filter(|record| names_eq(&record.name, name))
+            .filter_map(|record| record.ip())
+            .collect()
+    }
+}
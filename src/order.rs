@@ -0,0 +1,131 @@
+use std::{
+    collections::{HashSet, VecDeque},
+    net::SocketAddr,
+};
+
+/// Adapters for iterators of addresses, such as those lookups return.
+pub trait AddrIterExt: Iterator<Item = SocketAddr> + Sized {
+    /// The addresses alternating between IPv6 and IPv4, starting with IPv6, each family
+    /// keeping its order, as Happy Eyeballs ([RFC 8305]) tries them.
+    ///
+    /// Once a family runs out, the rest of the other follows.
+    ///
+    /// [RFC 8305]: https://www.rfc-editor.org/rfc/rfc8305#section-4
+    fn interleave_families(self) -> Interleave<Self> {
+        Interleave {
+            inner: self,
+            v4: VecDeque::new(),
+            v6: VecDeque::new(),
+            want_v6: true,
+        }
+    }
+
+    /// The addresses without those already returned, the same address reported for several
+    /// socket types or aliases for instance.
+    fn dedup_addrs(self) -> Dedup<Self> {
+        Dedup {
+            inner: self,
+            seen: HashSet::new(),
+        }
+    }
+}
+
+impl<I: Iterator<Item = SocketAddr>> AddrIterExt for I {}
+
+/// Iterator returned by [`AddrIterExt::interleave_families`].
+#[derive(Debug, Clone)]
+pub struct Interleave<I> {
+    inner: I,
+    /// Addresses read ahead while looking for one of the other family.
+    v4: VecDeque<SocketAddr>,
+    v6: VecDeque<SocketAddr>,
+    want_v6: bool,
+}
+
+impl<I: Iterator<Item = SocketAddr>> Interleave<I> {
+    /// The next address of the family asked for, reading ahead as needed.
+    fn take(&mut self, v6: bool) -> Option<SocketAddr> {
+        let queued = if v6 { &mut self.v6 } else { &mut self.v4 };
+        if let Some(addr) = queued.pop_front() {
+            return Some(addr);
+        }
+        for addr in self.inner.by_ref() {
+            if addr.is_ipv6() == v6 {
+                return Some(addr);
+            }
+            if v6 { &mut self.v4 } else { &mut self.v6 }.push_back(addr);
+        }
+        None
+    }
+}
+
+impl<I: Iterator<Item = SocketAddr>> Iterator for Interleave<I> {
+    type Item = SocketAddr;
+
+    fn next(&mut self) -> Option<SocketAddr> {
+        let addr = self
+            .take(self.want_v6)
+            .or_else(|| self.take(!self.want_v6))?;
+        self.want_v6 = addr.is_ipv4();
+        Some(addr)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let queued = self.v4.len() + self.v6.len();
+        let (low, high) = self.inner.size_hint();
+        (
+            low.saturating_add(queued),
+            high.and_then(|high| high.checked_add(queued)),
+        )
+    }
+}
+
+/// Iterator returned by [`AddrIterExt::dedup_addrs`].
+#[derive(Debug, Clone)]
+pub struct Dedup<I> {
+    inner: I,
+    seen: HashSet<SocketAddr>,
+}
+
+impl<I: Iterator<Item = SocketAddr>> Iterator for Dedup<I> {
+    type Item = SocketAddr;
+
+    fn next(&mut self) -> Option<SocketAddr> {
+        self.inner.by_ref().find(|&addr| self.seen.insert(addr))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, self.inner.size_hint().1)
+    }
+}
+
+/// Drops the items whose address an earlier one has, keeping the order.
+pub(crate) fn dedup<T>(items: &mut Vec<T>, addr: impl Fn(&T) -> SocketAddr) {
+    let mut seen = HashSet::with_capacity(items.len());
+    items.retain(|item| seen.insert(addr(item)));
+}
+
+/// Reorders `addrs` as [`AddrIterExt::interleave_families`] would return them, in place.
+pub(crate) fn interleave(addrs: &mut [SocketAddr]) {
+    let mut want_v6 = true;
+    for i in 0..addrs.len() {
+        // bringing the next address of the family wanted forward keeps the order of the others
+        if let Some(found) = addrs[i..].iter().position(|addr| addr.is_ipv6() == want_v6) {
+            addrs[i..=i + found].rotate_right(1);
+        }
+        want_v6 = addrs[i].is_ipv4();
+    }
+}
+
+/// The rank of each of `addrs` once interleaved as by [`AddrIterExt::interleave_families`].
+pub(crate) fn interleaved_ranks(addrs: impl Iterator<Item = SocketAddr>) -> Vec<usize> {
+    let (mut v4, mut v6) = (0, 0);
+    addrs
+        .map(|addr| {
+            let seen = if addr.is_ipv6() { &mut v6 } else { &mut v4 };
+            *seen += 1;
+            // IPv6 addresses at the even ranks, IPv4 ones at the odd ranks
+            2 * (*seen - 1) + usize::from(addr.is_ipv4())
+        })
+        .collect()
+}
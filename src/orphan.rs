@@ -0,0 +1,48 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static ORPHANS: AtomicUsize = AtomicUsize::new(0);
+
+/// Number of lookups abandoned by their caller that are still running in the background.
+///
+/// A lookup is orphaned when it times out or is dropped but the platform can not stop it, like a
+/// glibc request already being processed (`EAI_NOTCANCELED`) or a resolver thread stuck in
+/// `getaddrinfo`. Its resources are released, and the counter decremented, once it completes.
+pub fn orphaned_lookups() -> usize {
+    ORPHANS.load(Ordering::Relaxed)
+}
+
+/// Marks the lookup owning it as orphaned for as long as it is alive.
+#[cfg_attr(
+    any(
+        backend = "windows",
+        backend = "apple",
+        backend = "c_ares",
+        backend = "wasi",
+        backend = "wasm"
+    ),
+    allow(dead_code)
+)]
+pub(crate) struct Orphan(());
+
+#[cfg_attr(
+    any(
+        backend = "windows",
+        backend = "apple",
+        backend = "c_ares",
+        backend = "wasi",
+        backend = "wasm"
+    ),
+    allow(dead_code)
+)]
+impl Orphan {
+    pub fn new() -> Self {
+        ORPHANS.fetch_add(1, Ordering::Relaxed);
+        Self(())
+    }
+}
+
+impl Drop for Orphan {
+    fn drop(&mut self) {
+        ORPHANS.fetch_sub(1, Ordering::Relaxed);
+    }
+}
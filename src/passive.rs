@@ -0,0 +1,46 @@
+use std::{
+    io,
+    net::{Ipv4Addr, Ipv6Addr, SocketAddr},
+    time::Duration,
+};
+
+/// Wildcard addresses to bind a server on, for `service`: a port number or a service name such
+/// as `"https"`.
+///
+/// This is `getaddrinfo` with no host and `AI_PASSIVE`, where looking up the service name may
+/// consult network databases (NIS, LDAP) and so is bounded by `timeout`. Backends without such
+/// a lookup read the system services file instead.
+pub fn bind_addrs_timeout(
+    service: &str,
+    timeout: Duration,
+) -> io::Result<std::vec::IntoIter<SocketAddr>> {
+    if let Ok(port) = service.parse() {
+        return Ok(wildcard(port));
+    }
+
+    #[cfg(backend = "windows")]
+    return crate::sys::bind_addrs(service, timeout);
+
+    #[cfg(not(backend = "windows"))]
+    {
+        #[cfg(backend = "linux_glibc")]
+        if let Some(res) = crate::sys::bind_addrs(service, timeout) {
+            return res;
+        }
+        #[cfg(not(backend = "linux_glibc"))]
+        let _ = timeout;
+
+        crate::services::port(service, "tcp")
+            .map(wildcard)
+            .ok_or_else(crate::services::unknown)
+    }
+}
+
+/// The IPv4 and IPv6 unspecified addresses, in the order glibc gives them.
+fn wildcard(port: u16) -> std::vec::IntoIter<SocketAddr> {
+    vec![
+        SocketAddr::new(Ipv4Addr::UNSPECIFIED.into(), port),
+        SocketAddr::new(Ipv6Addr::UNSPECIFIED.into(), port),
+    ]
+    .into_iter()
+}
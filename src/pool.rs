@@ -0,0 +1,235 @@
+#![cfg_attr(
+    not(any(
+        backend = "android",
+        backend = "bsd",
+        backend = "fallback",
+        backend = "illumos",
+        backend = "linux_glibc"
+    )),
+    allow(dead_code)
+)]
+
+use std::{
+    collections::VecDeque,
+    io,
+    sync::{Condvar, Mutex, RwLock},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::ResolveError;
+
+/// What a lookup does when the worker pool has no room for it, see [`PoolConfig::overflow`],
+/// or its resolver as many lookups in flight as it allows, see
+/// [`ConcurrencyLimit::overflow`](crate::ConcurrencyLimit::overflow).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum PoolOverflow {
+    /// Wait for room, blocking the calling thread, until the timeout of the lookup elapses.
+    Block,
+    /// Fail right away with [`ResolveError::ResolverBusy`].
+    FailFast,
+}
+
+/// Sizing of the worker pool of the platform resolver, see [`set_pool_config`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct PoolConfig {
+    /// Most lookups running at once, 16 by default, 0 counting as 1.
+    pub max_workers: usize,
+    /// Most lookups waiting for a worker, unbounded by default.
+    pub max_queued: usize,
+    /// What a lookup does when as many wait already, [`PoolOverflow::Block`] by default.
+    pub overflow: PoolOverflow,
+}
+
+impl PoolConfig {
+    const DEFAULT: Self = Self {
+        max_workers: 16,
+        max_queued: usize::MAX,
+        overflow: PoolOverflow::Block,
+    };
+
+    /// Sets [`max_workers`](Self::max_workers).
+    pub fn max_workers(mut self, max: usize) -> Self {
+        self.max_workers = max;
+        self
+    }
+
+    /// Sets [`max_queued`](Self::max_queued).
+    pub fn max_queued(mut self, max: usize) -> Self {
+        self.max_queued = max;
+        self
+    }
+
+    /// Sets [`overflow`](Self::overflow).
+    pub fn overflow(mut self, overflow: PoolOverflow) -> Self {
+        self.overflow = overflow;
+        self
+    }
+}
+
+impl Default for PoolConfig {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+static CONFIG: RwLock<PoolConfig> = RwLock::new(PoolConfig::DEFAULT);
+
+/// The pools that started a worker, for [`close_all`].
+static STARTED: Mutex<Vec<&'static Pool>> = Mutex::new(Vec::new());
+
+/// Sizes the pool of worker threads the platform resolver runs `getaddrinfo` on, for the
+/// [`ThreadPool`](crate::BackendKind::ThreadPool) and
+/// [`ThreadFallback`](crate::BackendKind::ThreadFallback) backends; the others ignore it.
+///
+/// Lowering `max_workers` leaves the workers already started running.
+pub fn set_pool_config(config: PoolConfig) {
+    *CONFIG.write().unwrap_or_else(|e| e.into_inner()) = config;
+}
+
+fn current_config() -> PoolConfig {
+    let config = *CONFIG.read().unwrap_or_else(|e| e.into_inner());
+    PoolConfig {
+        max_workers: config.max_workers.max(1),
+        ..config
+    }
+}
+
+type Task = Box<dyn FnOnce() + Send>;
+
+/// Lazily started, bounded pool of long-lived worker threads fed by a queue, sized by
+/// [`PoolConfig`].
+///
+/// A worker is spawned for a task when none is idle and fewer than `max_workers` run, otherwise
+/// the task waits in the queue for the next free worker.
+pub(crate) struct Pool {
+    name: &'static str,
+    state: Mutex<State>,
+    available: Condvar,
+    /// Signaled when a worker takes a task from the queue or waits for one.
+    room: Condvar,
+}
+
+struct State {
+    queue: VecDeque<Task>,
+    workers: usize,
+    idle: usize,
+}
+
+impl State {
+    /// Whether a new task would wait beyond the queue length of `config`.
+    fn is_full(&self, config: &PoolConfig) -> bool {
+        self.workers >= config.max_workers
+            && self.queue.len() >= self.idle.saturating_add(config.max_queued)
+    }
+}
+
+impl Pool {
+    pub const fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            state: Mutex::new(State {
+                queue: VecDeque::new(),
+                workers: 0,
+                idle: 0,
+            }),
+            available: Condvar::new(),
+            room: Condvar::new(),
+        }
+    }
+
+    /// Queues `task`, which `timeout` bounds the wait for room of as [`PoolOverflow::Block`]
+    /// has it.
+    pub fn execute<F: FnOnce() + Send + 'static>(
+        &'static self,
+        task: F,
+        timeout: Option<Duration>,
+    ) -> io::Result<()> {
+        let deadline = timeout.map(crate::deadline_after);
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let mut config = current_config();
+        loop {
+            crate::shutdown::check()?;
+            if !state.is_full(&config) {
+                break;
+            }
+            if config.overflow == PoolOverflow::FailFast {
+                return Err(ResolveError::ResolverBusy.into());
+            }
+            state = match deadline {
+                Some(deadline) => {
+                    let left = deadline.saturating_duration_since(Instant::now());
+                    if left.is_zero() {
+                        return Err(io::ErrorKind::TimedOut.into());
+                    }
+                    self.room
+                        .wait_timeout(state, left)
+                        .unwrap_or_else(|e| e.into_inner())
+                        .0
+                }
+                None => self.room.wait(state).unwrap_or_else(|e| e.into_inner()),
+            };
+            config = current_config();
+        }
+
+        state.queue.push_back(Box::new(task));
+        if state.idle >= state.queue.len() || state.workers >= config.max_workers {
+            drop(state);
+            self.available.notify_one();
+            return Ok(());
+        }
+
+        let spawned = thread::Builder::new()
+            .name(self.name.into())
+            .spawn(move || self.work());
+        // with no new worker the task waits for a running one, which exists unless the very
+        // first spawn failed
+        if spawned.is_ok() {
+            state.workers += 1;
+            if state.workers == 1 {
+                STARTED.lock().unwrap_or_else(|e| e.into_inner()).push(self);
+            }
+        }
+        Ok(())
+    }
+
+    fn work(&self) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        loop {
+            match state.queue.pop_front() {
+                Some(task) => {
+                    drop(state);
+                    self.room.notify_one();
+                    task();
+                    state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+                }
+                None if crate::shutdown::is_shut_down() => {
+                    state.workers -= 1;
+                    return;
+                }
+                None => {
+                    state.idle += 1;
+                    self.room.notify_one();
+                    state = self
+                        .available
+                        .wait(state)
+                        .unwrap_or_else(|e| e.into_inner());
+                    state.idle -= 1;
+                }
+            }
+        }
+    }
+}
+
+/// Wakes the workers of every pool for the idle ones to exit, and the lookups waiting for room
+/// to fail, once [`shutdown`](crate::shutdown()) was called.
+pub(crate) fn close_all() {
+    for pool in STARTED.lock().unwrap_or_else(|e| e.into_inner()).iter() {
+        // taking the lock lets no worker in between its check and its wait miss the wakeup
+        drop(pool.state.lock().unwrap_or_else(|e| e.into_inner()));
+        pool.available.notify_all();
+        pool.room.notify_all();
+    }
+}
@@ -0,0 +1,187 @@
+use std::{
+    io,
+    sync::Arc,
+    task::{Wake, Waker},
+    thread::{self, Thread},
+};
+
+#[cfg(unix)]
+use std::os::fd::{AsFd, AsRawFd, BorrowedFd, FromRawFd, OwnedFd, RawFd};
+#[cfg(windows)]
+use std::os::windows::io::{
+    AsHandle, AsRawHandle, BorrowedHandle, FromRawHandle, OwnedHandle, RawHandle,
+};
+
+/// OS handle made ready once a [`Resolution`](crate::Resolution) may have completed, see
+/// [`Resolution::readiness`](crate::Resolution::readiness): an eventfd on Linux and Android,
+/// the read end of a pipe on the other Unix platforms, both becoming readable, and a
+/// manual-reset event on Windows, becoming signaled.
+///
+/// It stays ready until the resolution is polled again, and is closed once both this handle
+/// and the resolution are dropped.
+#[derive(Clone)]
+pub struct Readiness(Arc<Signal>);
+
+impl core::fmt::Debug for Readiness {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        #[cfg(unix)]
+        let handle = self.as_raw_fd();
+        #[cfg(windows)]
+        let handle = self.as_raw_handle();
+        f.debug_tuple("Readiness").field(&handle).finish()
+    }
+}
+
+impl Readiness {
+    pub(crate) fn new(signal: Arc<Signal>) -> Self {
+        Self(signal)
+    }
+}
+
+#[cfg(unix)]
+impl AsFd for Readiness {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.0.read.as_fd()
+    }
+}
+
+#[cfg(unix)]
+impl AsRawFd for Readiness {
+    fn as_raw_fd(&self) -> RawFd {
+        self.0.read.as_raw_fd()
+    }
+}
+
+#[cfg(windows)]
+impl AsHandle for Readiness {
+    fn as_handle(&self) -> BorrowedHandle<'_> {
+        self.0.event.as_handle()
+    }
+}
+
+#[cfg(windows)]
+impl AsRawHandle for Readiness {
+    fn as_raw_handle(&self) -> RawHandle {
+        self.0.event.as_raw_handle()
+    }
+}
+
+/// The primitive behind a [`Readiness`], set by the waker of the lookup.
+pub(crate) struct Signal {
+    #[cfg(unix)]
+    read: OwnedFd,
+    /// The eventfd being both ends.
+    #[cfg(all(unix, not(any(target_os = "linux", target_os = "android"))))]
+    write: OwnedFd,
+    #[cfg(windows)]
+    event: OwnedHandle,
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+impl Signal {
+    pub fn new() -> io::Result<Self> {
+        let fd = unsafe { libc::eventfd(0, libc::EFD_CLOEXEC | libc::EFD_NONBLOCK) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Self {
+            read: unsafe { OwnedFd::from_raw_fd(fd) },
+        })
+    }
+
+    pub fn set(&self) {
+        let one = 1u64;
+        // can only fail with the counter about to overflow, which leaves it readable anyway
+        unsafe { libc::write(self.read.as_raw_fd(), (&one as *const u64).cast(), 8) };
+    }
+
+    pub fn clear(&self) {
+        let mut count = 0u64;
+        unsafe { libc::read(self.read.as_raw_fd(), (&mut count as *mut u64).cast(), 8) };
+    }
+}
+
+#[cfg(all(unix, not(any(target_os = "linux", target_os = "android"))))]
+impl Signal {
+    pub fn new() -> io::Result<Self> {
+        let mut fds = [0; 2];
+        if unsafe { libc::pipe(fds.as_mut_ptr()) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let (read, write) = unsafe { (OwnedFd::from_raw_fd(fds[0]), OwnedFd::from_raw_fd(fds[1])) };
+        for fd in [&read, &write] {
+            let fd = fd.as_raw_fd();
+            let flags = unsafe { libc::fcntl(fd, libc::F_GETFL) };
+            if flags < 0
+                || unsafe { libc::fcntl(fd, libc::F_SETFL, flags | libc::O_NONBLOCK) } < 0
+                || unsafe { libc::fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC) } < 0
+            {
+                return Err(io::Error::last_os_error());
+            }
+        }
+        Ok(Self { read, write })
+    }
+
+    pub fn set(&self) {
+        // a full pipe is readable already
+        unsafe { libc::write(self.write.as_raw_fd(), [1u8].as_ptr().cast(), 1) };
+    }
+
+    pub fn clear(&self) {
+        let mut buf = [0u8; 64];
+        while unsafe { libc::read(self.read.as_raw_fd(), buf.as_mut_ptr().cast(), buf.len()) } > 0 {
+        }
+    }
+}
+
+#[cfg(windows)]
+impl Signal {
+    pub fn new() -> io::Result<Self> {
+        let event =
+            unsafe { windows::Win32::System::Threading::CreateEventW(None, true, false, None) }?;
+        Ok(Self {
+            event: unsafe { OwnedHandle::from_raw_handle(event.0) },
+        })
+    }
+
+    fn handle(&self) -> windows::Win32::Foundation::HANDLE {
+        windows::Win32::Foundation::HANDLE(self.event.as_raw_handle())
+    }
+
+    pub fn set(&self) {
+        _ = unsafe { windows::Win32::System::Threading::SetEvent(self.handle()) };
+    }
+
+    pub fn clear(&self) {
+        _ = unsafe { windows::Win32::System::Threading::ResetEvent(self.handle()) };
+    }
+}
+
+impl Wake for Signal {
+    fn wake(self: Arc<Self>) {
+        self.set();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.set();
+    }
+}
+
+/// Waker setting `signal` and unparking the current thread, for a lookup polled by both.
+struct Both(Arc<Signal>, Thread);
+
+impl Wake for Both {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.0.set();
+        self.1.unpark();
+    }
+}
+
+/// Waker for the current thread to block on a lookup with, `signal` being set as well.
+pub(crate) fn waker(signal: &Arc<Signal>) -> Waker {
+    Waker::from(Arc::new(Both(signal.clone(), thread::current())))
+}
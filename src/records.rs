@@ -0,0 +1,235 @@
+use std::{
+    io,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+    time::Duration,
+};
+
+use crate::{
+    dns::{self, Message, Record},
+    ResolvConf, StubResolver,
+};
+
+/// A DNS message, as answered to a [`raw_query`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct DnsMessage {
+    pub id: u16,
+    /// The header flags, including the opcode and the response code.
+    pub flags: u16,
+    pub questions: Vec<DnsQuestion>,
+    pub answers: Vec<DnsRecord>,
+    pub authority: Vec<DnsRecord>,
+    pub additional: Vec<DnsRecord>,
+    wire: Vec<u8>,
+}
+
+/// An entry of the question section of a [`DnsMessage`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct DnsQuestion {
+    pub name: String,
+    pub qtype: u16,
+    pub qclass: u16,
+}
+
+/// A resource record of a [`DnsMessage`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct DnsRecord {
+    pub name: String,
+    pub rtype: u16,
+    pub rclass: u16,
+    pub ttl: u32,
+    /// The record data as sent, where names may be compressed, see [`DnsMessage::name_at`].
+    pub data: Vec<u8>,
+    data_pos: usize,
+}
+
+impl DnsMessage {
+    pub(crate) fn parse(wire: Vec<u8>) -> Option<Self> {
+        let message = Message::parse(&wire)?;
+        let records = |records: &[Record]| {
+            records
+                .iter()
+                .map(|record| DnsRecord {
+                    name: dns::name_to_string(&record.name),
+                    rtype: record.rtype,
+                    rclass: record.class,
+                    ttl: record.ttl,
+                    data: record.rdata.to_vec(),
+                    data_pos: record.rdata_pos,
+                })
+                .collect()
+        };
+        Some(Self {
+            id: message.id,
+            flags: message.flags,
+            questions: message
+                .questions
+                .iter()
+                .map(|question| DnsQuestion {
+                    name: dns::name_to_string(&question.name),
+                    qtype: question.qtype,
+                    qclass: question.class,
+                })
+                .collect(),
+            answers: records(&message.answers),
+            authority: records(&message.authority),
+            additional: records(&message.additional),
+            wire,
+        })
+    }
+
+    /// The response code, the low four bits of the flags.
+    pub fn rcode(&self) -> u8 {
+        (self.flags & 0xf) as u8
+    }
+
+    /// The message in wire format.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.wire
+    }
+
+    /// Reads the name at `offset` in the data of `record`, one of this message's, following
+    /// compression pointers into the rest of the message.
+    pub fn name_at(&self, record: &DnsRecord, offset: usize) -> Option<String> {
+        if offset >= record.data.len() {
+            return None;
+        }
+        let (name, _) = dns::read_name(&self.wire, record.data_pos + offset)?;
+        Some(dns::name_to_string(&name))
+    }
+}
+
+/// A name's CNAME chain and the addresses it ends at.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CnameChain {
+    /// The name looked up followed by each alias target, the last being the canonical name.
+    pub names: Vec<String>,
+    /// The addresses of the canonical name, IPv6 first.
+    pub addrs: Vec<IpAddr>,
+}
+
+/// An HTTPS record, the SVCB record type describing how to reach an HTTPS origin.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct HttpsRecord {
+    /// 0 for an alias to `target`, otherwise the preference among the records, lowest first.
+    pub priority: u16,
+    /// The name the service is reached at, empty for the name queried.
+    pub target: String,
+    /// The `alpn` protocol identifiers.
+    pub alpn: Vec<Vec<u8>>,
+    /// Whether `no-default-alpn` is set, the default protocol not being supported.
+    pub no_default_alpn: bool,
+    /// The `port` to connect to, the default one when `None`.
+    pub port: Option<u16>,
+    pub ipv4_hint: Vec<Ipv4Addr>,
+    pub ipv6_hint: Vec<Ipv6Addr>,
+    /// The `ech` config list, for TLS Encrypted Client Hello.
+    pub ech: Option<Vec<u8>>,
+    /// Every parameter as sent, keys and values, the ones above included.
+    pub params: Vec<(u16, Vec<u8>)>,
+}
+
+impl HttpsRecord {
+    const ALPN: u16 = 1;
+    const NO_DEFAULT_ALPN: u16 = 2;
+    const PORT: u16 = 3;
+    const IPV4_HINT: u16 = 4;
+    const ECH: u16 = 5;
+    const IPV6_HINT: u16 = 6;
+
+    /// Decodes the data of `record`, one of `msg`'s, `None` if it is malformed.
+    pub(crate) fn parse(record: &Record, msg: &[u8]) -> Option<Self> {
+        let end = record.rdata_pos + record.rdata.len();
+        let priority = dns::read_u16(msg, record.rdata_pos)?;
+        let (target, mut pos) = dns::read_name(msg, record.rdata_pos + 2)?;
+        let mut https = Self {
+            priority,
+            target: dns::name_to_string(&target),
+            alpn: Vec::new(),
+            no_default_alpn: false,
+            port: None,
+            ipv4_hint: Vec::new(),
+            ipv6_hint: Vec::new(),
+            ech: None,
+            params: Vec::new(),
+        };
+        while pos < end {
+            let key = dns::read_u16(msg, pos)?;
+            let len = dns::read_u16(msg, pos + 2)? as usize;
+            let value = msg
+                .get(pos + 4..pos + 4 + len)
+                .filter(|_| pos + 4 + len <= end)?;
+            match key {
+                Self::ALPN => {
+                    let mut ids = value;
+                    while let Some((&len, rest)) = ids.split_first() {
+                        https.alpn.push(rest.get(..len as usize)?.to_vec());
+                        ids = &rest[len as usize..];
+                    }
+                }
+                Self::NO_DEFAULT_ALPN => https.no_default_alpn = true,
+                Self::PORT => https.port = Some(dns::read_u16(value, 0)?),
+                Self::IPV4_HINT => https.ipv4_hint.extend(
+                    value
+                        .chunks_exact(4)
+                        .map(|ip| Ipv4Addr::from(<[u8; 4]>::try_from(ip).unwrap())),
+                ),
+                Self::ECH => https.ech = Some(value.to_vec()),
+                Self::IPV6_HINT => https.ipv6_hint.extend(
+                    value
+                        .chunks_exact(16)
+                        .map(|ip| Ipv6Addr::from(<[u8; 16]>::try_from(ip).unwrap())),
+                ),
+                _ => {}
+            }
+            https.params.push((key, value.to_vec()));
+            pos += 4 + len;
+        }
+        Some(https)
+    }
+
+    /// Whether the record is in alias mode, only pointing at `target`.
+    pub fn is_alias(&self) -> bool {
+        self.priority == 0
+    }
+}
+
+/// The stub resolver record lookups without one go through.
+fn system() -> StubResolver {
+    StubResolver::from_resolv_conf(&ResolvConf::system())
+}
+
+/// The TXT records of `name`, queried with a [`StubResolver`] configured with
+/// [`ResolvConf::system`], giving up once `timeout` has elapsed.
+///
+/// See [`StubResolver::lookup_txt`].
+pub fn lookup_txt(name: &str, timeout: Duration) -> io::Result<Vec<Vec<u8>>> {
+    system().lookup_txt(name, timeout)
+}
+
+/// The CNAME chain of `name` and its addresses, queried with a [`StubResolver`] configured with
+/// [`ResolvConf::system`], giving up once `timeout` has elapsed.
+///
+/// See [`StubResolver::lookup_cname_chain`].
+pub fn lookup_cname_chain(name: &str, timeout: Duration) -> io::Result<CnameChain> {
+    system().lookup_cname_chain(name, timeout)
+}
+
+/// Queries the `rtype` records of `name` in `rclass` with a [`StubResolver`] configured with
+/// [`ResolvConf::system`], giving up once `timeout` has elapsed.
+///
+/// See [`StubResolver::raw_query`].
+pub fn raw_query(name: &str, rtype: u16, rclass: u16, timeout: Duration) -> io::Result<DnsMessage> {
+    system().raw_query(name, rtype, rclass, timeout)
+}
+
+/// The HTTPS records of `host`, queried with a [`StubResolver`] configured with
+/// [`ResolvConf::system`], giving up once `timeout` has elapsed.
+///
+/// See [`StubResolver::lookup_https_records`].
+pub fn lookup_https_records(host: &str, timeout: Duration) -> io::Result<Vec<HttpsRecord>> {
+    system().lookup_https_records(host, timeout)
+}
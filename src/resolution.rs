@@ -0,0 +1,182 @@
+use std::{io, net::SocketAddr, task::Poll, time::Duration};
+
+#[cfg(any(unix, windows))]
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Waker},
+};
+
+#[cfg(any(unix, windows))]
+use crate::readiness::{self, Readiness, Signal};
+use crate::{
+    future::block_on_deadline_with,
+    hooks::{LookupHooks, Observation},
+    services, ResolvedAddrs,
+};
+
+/// Handle to a lookup started with
+/// [`ToSocketAddrsTimeout::start_lookup`](crate::ToSocketAddrsTimeout::start_lookup).
+///
+/// The query keeps running in the background until it completes, [`Resolution::wait`] gives up
+/// on it, or it is canceled with [`Resolution::cancel`] or by dropping the handle.
+#[must_use = "dropping a Resolution cancels the lookup"]
+pub struct Resolution {
+    state: State,
+    /// Set up by [`Resolution::readiness`].
+    #[cfg(any(unix, windows))]
+    signal: Option<Arc<Signal>>,
+    observation: Option<Observation>,
+}
+
+enum State {
+    Ready(io::Result<std::vec::IntoIter<SocketAddr>>),
+    Pending(crate::sys::Lookup),
+    /// Handed out by [`Resolution::poll`].
+    Done,
+}
+
+impl Resolution {
+    fn new(state: State) -> Self {
+        Self {
+            state,
+            #[cfg(any(unix, windows))]
+            signal: None,
+            observation: None,
+        }
+    }
+
+    pub(crate) fn ready(res: io::Result<std::vec::IntoIter<SocketAddr>>) -> Self {
+        Self::new(State::Ready(res))
+    }
+
+    pub(crate) fn start(host: &str, port: u16) -> Self {
+        if let Some(addr) = crate::literal(host, port) {
+            return Self::ready(Ok(vec![addr].into_iter()));
+        }
+        let host = crate::hostname::to_ascii(host);
+        if let Err(err) = crate::shutdown::check() {
+            return Self::ready(Err(err));
+        }
+        // the lookup itself has no timeout, only waiting for it does
+        let observation =
+            Observation::start(&LookupHooks::NONE, crate::BACKEND, &host, Duration::MAX);
+        match crate::HostnameSyntax::Strict
+            .check(&host)
+            .map_err(Into::into)
+            .and_then(|()| crate::sys::lookup(crate::hostname::without_root(&host), port, None))
+        {
+            Ok(lookup) => Self {
+                observation,
+                ..Self::new(State::Pending(lookup))
+            },
+            Err(err) => {
+                if let Some(observation) = observation {
+                    observation.finish(Err(&err));
+                }
+                Self::ready(Err(err))
+            }
+        }
+    }
+
+    /// Settles the lookup with `res`, running the completion hooks.
+    fn complete(&mut self, res: io::Result<std::vec::IntoIter<SocketAddr>>) {
+        if let Some(observation) = self.observation.take() {
+            observation.finish(res.as_ref().map(ExactSizeIterator::len));
+        }
+        self.state = State::Ready(res);
+    }
+
+    pub(crate) fn start_str(s: &str) -> Self {
+        if let Ok(addr) = s.parse() {
+            return Self::ready(Ok(vec![addr].into_iter()));
+        }
+
+        let (host, port_str) = match crate::parse_host_port(s) {
+            Ok(split) => split,
+            Err(err) => return Self::ready(Err(err)),
+        };
+        // service names come from the services file, there is no background service lookup
+        let port = match services::parse_port(port_str) {
+            Ok(port) => port,
+            Err(err) => return Self::ready(Err(err)),
+        };
+        Self::start(host, port)
+    }
+
+    /// Abandons the lookup, canceling the underlying query (`gai_cancel` on glibc,
+    /// `GetAddrInfoExCancel` on Windows) where the platform supports it.
+    pub fn cancel(self) {
+        drop(self);
+    }
+
+    /// Waits up to `timeout` for the lookup to complete, canceling it if it does not.
+    pub fn wait(mut self, timeout: Duration) -> io::Result<std::vec::IntoIter<SocketAddr>> {
+        if let State::Pending(lookup) = &mut self.state {
+            let res = crate::future::block_on_deadline(lookup, crate::deadline_after(timeout))
+                .unwrap_or_else(|| Err(io::ErrorKind::TimedOut.into()));
+            self.complete(res);
+        }
+        match std::mem::replace(&mut self.state, State::Done) {
+            State::Ready(res) => res,
+            _ => panic!("Resolution waited for after poll completed it"),
+        }
+    }
+
+    /// Waits up to `extra_wait` for the lookup to complete, leaving it running if it does not,
+    /// for loops of their own to check on it a slice at a time; a zero `extra_wait` only checks.
+    ///
+    /// # Panics
+    ///
+    /// When polled or waited for again after returning [`Poll::Ready`].
+    pub fn poll(&mut self, extra_wait: Duration) -> Poll<io::Result<ResolvedAddrs>> {
+        if let State::Pending(lookup) = &mut self.state {
+            #[cfg(any(unix, windows))]
+            let waker = match &self.signal {
+                Some(signal) => {
+                    signal.clear();
+                    readiness::waker(signal)
+                }
+                None => crate::future::thread_waker(),
+            };
+            #[cfg(not(any(unix, windows)))]
+            let waker = crate::future::thread_waker();
+            match block_on_deadline_with(lookup, crate::deadline_after(extra_wait), &waker) {
+                Some(res) => self.complete(res),
+                None => return Poll::Pending,
+            }
+        }
+        match std::mem::replace(&mut self.state, State::Done) {
+            State::Ready(res) => Poll::Ready(res.map(Into::into)),
+            _ => panic!("Resolution polled after completion"),
+        }
+    }
+
+    /// Returns a handle that becomes ready once the lookup may have completed, for event loops
+    /// to wait on rather than blocking in [`poll`](Self::poll), which is then called with a zero
+    /// `extra_wait` to take the result or rearm the handle.
+    ///
+    /// # Errors
+    ///
+    /// When the handle cannot be created.
+    #[cfg(any(unix, windows))]
+    pub fn readiness(&mut self) -> io::Result<Readiness> {
+        let signal = match &self.signal {
+            Some(signal) => signal.clone(),
+            None => self.signal.insert(Arc::new(Signal::new()?)).clone(),
+        };
+        match &mut self.state {
+            State::Pending(lookup) => {
+                // the lookup now wakes the signal, which is set right away should it be done
+                let waker = Waker::from(signal.clone());
+                if let Poll::Ready(res) = Pin::new(lookup).poll(&mut Context::from_waker(&waker)) {
+                    self.complete(res);
+                    signal.set();
+                }
+            }
+            _ => signal.set(),
+        }
+        Ok(Readiness::new(signal))
+    }
+}
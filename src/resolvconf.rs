@@ -0,0 +1,177 @@
+use std::{
+    io,
+    net::{IpAddr, Ipv4Addr, SocketAddr, SocketAddrV6},
+    path::Path,
+    time::Duration,
+};
+
+/// Most nameservers honored, as in glibc.
+const MAXNS: usize = 3;
+
+/// Resolver configuration, as read from `/etc/resolv.conf`.
+///
+/// Unknown directives and options are ignored and out of range values are clamped, the way the
+/// libc resolver does.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ResolvConf {
+    /// `nameserver` addresses, at most three, on port 53.
+    pub nameservers: Vec<SocketAddr>,
+    /// Domains from the last `search` or `domain` directive.
+    pub search: Vec<String>,
+    /// `options ndots:n`, dots needed in a name for it to be tried as is first.
+    pub ndots: u8,
+    /// `options timeout:n`, how long to wait for a single nameserver.
+    pub timeout: Duration,
+    /// `options attempts:n`, passes over the nameservers before giving up.
+    pub attempts: u8,
+    /// `options rotate`, spread queries over the nameservers round-robin.
+    pub rotate: bool,
+}
+
+impl Default for ResolvConf {
+    /// The configuration used when `resolv.conf` is empty: the local nameserver and the libc
+    /// default options.
+    fn default() -> Self {
+        Self {
+            nameservers: vec![SocketAddr::new(Ipv4Addr::LOCALHOST.into(), 53)],
+            search: Vec::new(),
+            ndots: 1,
+            timeout: Duration::from_secs(5),
+            attempts: 2,
+            rotate: false,
+        }
+    }
+}
+
+impl ResolvConf {
+    /// Reads `/etc/resolv.conf`.
+    pub fn load() -> io::Result<Self> {
+        Self::from_path("/etc/resolv.conf")
+    }
+
+    /// The system configuration: `/etc/resolv.conf`, or on Windows the IPv4 DNS servers of the
+    /// network adapters, falling back to the [default](Self::default) when it can not be read.
+    pub fn system() -> Self {
+        #[cfg(windows)]
+        return Self {
+            nameservers: windows_nameservers().unwrap_or_else(|| Self::default().nameservers),
+            ..Self::default()
+        };
+        #[cfg(not(windows))]
+        Self::load().unwrap_or_default()
+    }
+
+    /// Reads the resolv.conf formatted file at `path`.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Ok(Self::parse(&std::fs::read_to_string(path)?))
+    }
+
+    /// Parses the contents of a resolv.conf file.
+    pub fn parse(text: &str) -> Self {
+        let mut conf = Self {
+            nameservers: Vec::new(),
+            ..Self::default()
+        };
+
+        for line in text.lines() {
+            let line = line.split(['#', ';']).next().unwrap_or_default();
+            let mut words = line.split_ascii_whitespace();
+            match words.next() {
+                Some("nameserver") => {
+                    if let Some(addr) = words.next().and_then(parse_nameserver) {
+                        if conf.nameservers.len() < MAXNS {
+                            conf.nameservers.push(addr);
+                        }
+                    }
+                }
+                Some("domain") => {
+                    if let Some(domain) = words.next() {
+                        conf.search = vec![domain.to_owned()];
+                    }
+                }
+                Some("search") => conf.search = words.map(str::to_owned).collect(),
+                Some("options") => {
+                    for option in words {
+                        conf.parse_option(option);
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        if conf.nameservers.is_empty() {
+            conf.nameservers = Self::default().nameservers;
+        }
+        conf
+    }
+
+    fn parse_option(&mut self, option: &str) {
+        let (name, value) = match option.split_once(':') {
+            Some((name, value)) => (name, value.parse::<u32>().ok()),
+            None => (option, None),
+        };
+        match (name, value) {
+            ("ndots", Some(n)) => self.ndots = n.min(15) as u8,
+            ("timeout", Some(n)) => self.timeout = Duration::from_secs(n.clamp(1, 30).into()),
+            ("attempts", Some(n)) => self.attempts = n.clamp(1, 5) as u8,
+            ("rotate", _) => self.rotate = true,
+            _ => (),
+        }
+    }
+}
+
+/// Parses an IP address, with an optional numeric IPv6 zone (`fe80::1%2`).
+fn parse_nameserver(s: &str) -> Option<SocketAddr> {
+    if let Ok(ip) = s.parse::<IpAddr>() {
+        return Some(SocketAddr::new(ip, 53));
+    }
+    let (ip, zone) = s.split_once('%')?;
+    Some(SocketAddr::V6(SocketAddrV6::new(
+        ip.parse().ok()?,
+        53,
+        0,
+        zone.parse().unwrap_or(0),
+    )))
+}
+
+/// The DNS servers from `DnsQueryConfig`, `None` if there are none or they can not be read.
+#[cfg(windows)]
+fn windows_nameservers() -> Option<Vec<SocketAddr>> {
+    use windows::Win32::NetworkManagement::Dns::{DnsConfigDnsServerList, DnsQueryConfig};
+    use windows_core::PCWSTR;
+
+    let mut len = 0u32;
+    unsafe {
+        DnsQueryConfig(
+            DnsConfigDnsServerList,
+            0,
+            PCWSTR::null(),
+            None,
+            None,
+            &mut len,
+        )
+    };
+    // an IP4_ARRAY: a u32 count followed by the addresses
+    let mut buf = vec![0u32; (len as usize).div_ceil(4).max(1)];
+    let ret = unsafe {
+        DnsQueryConfig(
+            DnsConfigDnsServerList,
+            0,
+            PCWSTR::null(),
+            None,
+            Some(buf.as_mut_ptr().cast()),
+            &mut len,
+        )
+    };
+    if ret != 0 {
+        return None;
+    }
+    let (&count, addrs) = buf.split_first()?;
+    let nameservers: Vec<_> = addrs
+        .iter()
+        .take((count as usize).min(MAXNS))
+        .map(|addr| SocketAddr::new(Ipv4Addr::from(addr.to_ne_bytes()).into(), 53))
+        .collect();
+    (!nameservers.is_empty()).then_some(nameservers)
+}
@@ -0,0 +1,124 @@
+use std::{iter::FusedIterator, net::SocketAddr};
+
+use crate::{addrinfo::INLINE, order, AddrInfoIter};
+
+/// The addresses a host name resolves to, in the order they are to be tried.
+///
+/// An iterator over the addresses, with combinators reordering those not returned yet. Those
+/// of the platform resolver are read off its list as they are needed, see [`AddrInfoIter`].
+#[derive(Debug, Clone)]
+pub struct ResolvedAddrs {
+    addrs: AddrInfoIter,
+}
+
+impl ResolvedAddrs {
+    /// The next address, without taking it.
+    pub fn first(&self) -> Option<SocketAddr> {
+        self.addrs.peek()
+    }
+
+    /// The addresses not returned yet.
+    pub fn into_vec(self) -> Vec<SocketAddr> {
+        self.addrs.collect()
+    }
+
+    /// IPv6 addresses first, each family keeping its order.
+    pub fn prefer_ipv6(mut self) -> Self {
+        self.addrs.owned_mut().sort_by_key(|addr| !addr.is_ipv6());
+        self
+    }
+
+    /// Without the addresses found earlier in the list.
+    pub fn dedup(mut self) -> Self {
+        if self.len() > INLINE {
+            let mut addrs = self.into_vec();
+            order::dedup(&mut addrs, |&addr| addr);
+            return addrs.into();
+        }
+        let addrs = self.addrs.owned_mut();
+        let mut kept = 0;
+        for i in 0..addrs.len() {
+            if !addrs[..kept].contains(&addrs[i]) {
+                addrs[kept] = addrs[i];
+                kept += 1;
+            }
+        }
+        self.addrs.truncate(kept);
+        self
+    }
+
+    /// In random order, to spread connections over the addresses of a host.
+    pub fn shuffle(mut self) -> Self {
+        let addrs = self.addrs.owned_mut();
+        for i in (1..addrs.len()).rev() {
+            let j = (crate::stub::random() % (i as u64 + 1)) as usize;
+            addrs.swap(i, j);
+        }
+        self
+    }
+
+    /// Alternating between IPv6 and IPv4, as
+    /// [`interleave_families`](crate::AddrIterExt::interleave_families) does.
+    pub fn interleave(mut self) -> Self {
+        order::interleave(self.addrs.owned_mut());
+        self
+    }
+}
+
+impl Default for ResolvedAddrs {
+    fn default() -> Self {
+        std::iter::empty().collect()
+    }
+}
+
+impl From<SocketAddr> for ResolvedAddrs {
+    fn from(addr: SocketAddr) -> Self {
+        AddrInfoIter::from(addr).into()
+    }
+}
+
+impl FromIterator<SocketAddr> for ResolvedAddrs {
+    fn from_iter<I: IntoIterator<Item = SocketAddr>>(iter: I) -> Self {
+        iter.into_iter().collect::<AddrInfoIter>().into()
+    }
+}
+
+impl From<Vec<SocketAddr>> for ResolvedAddrs {
+    fn from(addrs: Vec<SocketAddr>) -> Self {
+        addrs.into_iter().into()
+    }
+}
+
+impl From<std::vec::IntoIter<SocketAddr>> for ResolvedAddrs {
+    fn from(addrs: std::vec::IntoIter<SocketAddr>) -> Self {
+        AddrInfoIter::from(addrs).into()
+    }
+}
+
+impl From<AddrInfoIter> for ResolvedAddrs {
+    fn from(addrs: AddrInfoIter) -> Self {
+        Self { addrs }
+    }
+}
+
+impl From<ResolvedAddrs> for Vec<SocketAddr> {
+    fn from(addrs: ResolvedAddrs) -> Self {
+        addrs.into_vec()
+    }
+}
+
+impl Iterator for ResolvedAddrs {
+    type Item = SocketAddr;
+
+    fn next(&mut self) -> Option<SocketAddr> {
+        self.addrs.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.addrs.size_hint()
+    }
+}
+
+impl ExactSizeIterator for ResolvedAddrs {}
+
+impl FusedIterator for ResolvedAddrs {}
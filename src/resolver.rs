@@ -0,0 +1,1235 @@
+use std::{
+    io,
+    net::{IpAddr, SocketAddr},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    coalesce::Coalesced, hints, hooks::Hooked, hostname, limit::Limited, mdns, order,
+    retry::Retried, services, stats, sys::SystemResolver, BackendKind, Budget, ClientSubnet,
+    ConcurrencyLimit, FamilyPreference, HintFlags, Hints, HostnameSyntax, LookupHooks,
+    MdnsResolver, ResolvConf, ResolveError, ResolveStats, RetryPolicy, StubResolver,
+    ToSocketAddrsTimeout,
+};
+
+/// Resolves a host name to socket addresses.
+///
+/// Implemented by [`SystemResolver`] for the platform resolver; implement it to plug in a
+/// different one (a DNS proxy, a test stub, ...) behind [`Resolver`].
+pub trait ResolverBackend: Send + Sync {
+    /// Resolves `host`, which is never an IP literal, giving up once `timeout` has elapsed.
+    fn resolve(
+        &self,
+        host: &str,
+        port: u16,
+        timeout: Duration,
+    ) -> io::Result<std::vec::IntoIter<SocketAddr>>;
+
+    /// Like [`resolve`](Self::resolve), passing `hints` on to the resolver.
+    ///
+    /// The default implementation honors only [`Hints::family`] and the `ADDRCONFIG`,
+    /// `V4MAPPED` and `ALL` [flags](HintFlags), filtering and mapping what
+    /// [`resolve`](Self::resolve) returns, and fails right away with `NUMERICHOST`.
+    fn resolve_with_hints(
+        &self,
+        host: &str,
+        port: u16,
+        hints: &Hints,
+        timeout: Duration,
+    ) -> io::Result<std::vec::IntoIter<SocketAddr>> {
+        if hints.flags.contains(HintFlags::NUMERICHOST) {
+            return Err(hints::not_numeric());
+        }
+        let addrs = hints.filter(self.resolve(host, port, timeout)?);
+        if addrs.len() == 0 {
+            // as getaddrinfo reports a name without addresses of the family asked for
+            return Err(ResolveError::NoAddresses.into());
+        }
+        Ok(addrs)
+    }
+
+    /// Like [`resolve_with_hints`](Self::resolve_with_hints), with a service name such as
+    /// `"https"` in place of the port.
+    ///
+    /// The default implementation looks the service up in the system services file.
+    fn resolve_service(
+        &self,
+        host: &str,
+        service: &str,
+        hints: &Hints,
+        timeout: Duration,
+    ) -> io::Result<std::vec::IntoIter<SocketAddr>> {
+        let port = services::port(service, hints.service_proto()).ok_or_else(services::unknown)?;
+        self.resolve_with_hints(host, port, hints, timeout)
+    }
+
+    /// Like [`resolve_with_hints`](Self::resolve_with_hints), also asking for the canonical
+    /// name of `host` with [`HintFlags::CANONNAME`].
+    ///
+    /// The default implementation reports `host` itself as the canonical name.
+    fn resolve_canonical(
+        &self,
+        host: &str,
+        port: u16,
+        hints: &Hints,
+        timeout: Duration,
+    ) -> io::Result<CanonicalAddrs> {
+        Ok(CanonicalAddrs {
+            name: host.to_owned(),
+            addrs: self
+                .resolve_with_hints(host, port, hints, timeout)?
+                .collect(),
+        })
+    }
+
+    /// Like [`resolve_with_hints`](Self::resolve_with_hints), also reporting how long each
+    /// address may be cached.
+    ///
+    /// The default implementation reports no TTLs.
+    fn resolve_with_ttl(
+        &self,
+        host: &str,
+        port: u16,
+        hints: &Hints,
+        timeout: Duration,
+    ) -> io::Result<Vec<ResolvedAddr>> {
+        Ok(self
+            .resolve_with_hints(host, port, hints, timeout)?
+            .map(|addr| ResolvedAddr { addr, ttl: None })
+            .collect())
+    }
+}
+
+/// An address along with the time to live of the record it came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ResolvedAddr {
+    pub addr: SocketAddr,
+    /// How long the address may be cached, `None` when the backend does not know.
+    pub ttl: Option<Duration>,
+}
+
+/// Addresses of a host together with its canonical name, the target of its `CNAME` chain.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CanonicalAddrs {
+    /// The canonical name, the host itself when the resolver reports none.
+    pub name: String,
+    pub addrs: Vec<SocketAddr>,
+}
+
+impl CanonicalAddrs {
+    /// Answers IP literals, which are their own canonical name.
+    pub(crate) fn literal(host: &str, port: u16) -> Option<Self> {
+        Some(Self {
+            name: host.to_owned(),
+            addrs: vec![crate::literal(host, port)?],
+        })
+    }
+}
+
+/// Resolves [`ToSocketAddrsTimeout`] targets through a [`ResolverBackend`].
+#[derive(Clone)]
+pub struct Resolver {
+    backend: Arc<dyn ResolverBackend>,
+    kind: BackendKind,
+    /// Whether lookups go through a cache, see [`ResolverBuilder::cache`].
+    cached: bool,
+}
+
+impl Resolver {
+    /// Resolver using the platform resolver.
+    pub fn new() -> Self {
+        Self::builder().build()
+    }
+
+    /// Resolver using `backend` for host names.
+    pub fn with_backend<B: ResolverBackend + 'static>(backend: B) -> Self {
+        Self::builder().backend(backend).build()
+    }
+
+    /// Starts configuring a resolver.
+    pub fn builder() -> ResolverBuilder {
+        ResolverBuilder::default()
+    }
+
+    /// The backend making the lookups, that of [`SystemResolver`] being detected when the
+    /// resolver is built, see [`backend`](crate::backend()).
+    pub fn backend(&self) -> BackendKind {
+        self.kind
+    }
+
+    /// Resolves `target`, giving up once `timeout` has elapsed.
+    pub fn resolve<T: ToSocketAddrsTimeout + ?Sized>(
+        &self,
+        target: &T,
+        timeout: Duration,
+    ) -> io::Result<T::Iter> {
+        target.to_socket_addrs_with(&*self.backend, timeout)
+    }
+
+    /// [`resolve`](Self::resolve), telling how the lookup went along with its addresses.
+    pub fn resolve_with_stats<T: ToSocketAddrsTimeout + ?Sized>(
+        &self,
+        target: &T,
+        timeout: Duration,
+    ) -> io::Result<(T::Iter, ResolveStats)> {
+        let began = Instant::now();
+        let (result, counts) = stats::collect(|| self.resolve(target, timeout));
+        let elapsed = began.elapsed();
+        let stats = ResolveStats {
+            elapsed,
+            remaining: timeout.saturating_sub(elapsed),
+            backend: self.kind,
+            attempts: counts.attempts,
+            cached: counts.cached,
+        };
+        result.map(|addrs| (addrs, stats))
+    }
+
+    /// [`resolve`](Self::resolve) with the time left in `budget`, see
+    /// [`to_socket_addrs_within`](ToSocketAddrsTimeout::to_socket_addrs_within).
+    pub fn resolve_within<T: ToSocketAddrsTimeout + ?Sized>(
+        &self,
+        target: &T,
+        budget: &Budget,
+    ) -> io::Result<T::Iter> {
+        budget.spend(|timeout| self.resolve(target, timeout))
+    }
+
+    /// Resolves `host` along with its canonical name, giving up once `timeout` has elapsed.
+    pub fn resolve_with_canonical(
+        &self,
+        host: &str,
+        port: u16,
+        timeout: Duration,
+    ) -> io::Result<CanonicalAddrs> {
+        match CanonicalAddrs::literal(host, port) {
+            Some(literal) => Ok(literal),
+            None => self
+                .backend
+                .resolve_canonical(host, port, &Hints::default(), timeout),
+        }
+    }
+
+    /// Resolves `host` along with the TTLs of its addresses, giving up once `timeout` has
+    /// elapsed.
+    ///
+    /// IP literals have no TTL.
+    pub fn resolve_with_ttl(
+        &self,
+        host: &str,
+        port: u16,
+        timeout: Duration,
+    ) -> io::Result<Vec<ResolvedAddr>> {
+        match crate::literal(host, port) {
+            Some(addr) => Ok(vec![ResolvedAddr { addr, ttl: None }]),
+            None => self
+                .backend
+                .resolve_with_ttl(host, port, &Hints::default(), timeout),
+        }
+    }
+
+    /// Resolves `hosts` one after the other on a background thread, giving up on a host once
+    /// `timeout` has elapsed, so that later lookups find them in the cache, see
+    /// `ResolverBuilder::cache`.
+    ///
+    /// Returns right away. Does nothing for a resolver without a cache.
+    pub fn prefetch(&self, hosts: &[&str], timeout: Duration) {
+        if !self.cached {
+            return;
+        }
+        let hosts: Vec<_> = hosts
+            .iter()
+            .filter(|host| crate::literal(host, 0).is_none())
+            .map(|&host| host.to_owned())
+            .collect();
+        if hosts.is_empty() {
+            return;
+        }
+        let backend = self.backend.clone();
+        // a failed spawn only leaves the cache cold
+        _ = std::thread::Builder::new()
+            .name("to_socket_addrs_timeout-prefetch".into())
+            .spawn(move || {
+                for host in hosts {
+                    if crate::shutdown::is_shut_down() {
+                        break;
+                    }
+                    _ = backend.resolve_with_ttl(&host, 0, &Hints::default(), timeout);
+                }
+            });
+    }
+}
+
+impl Default for Resolver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl core::fmt::Debug for Resolver {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Resolver")
+            .field("backend", &self.kind)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Configures a [`Resolver`], created with [`Resolver::builder`].
+pub struct ResolverBuilder {
+    backend: Backend,
+    search: bool,
+    max_search_domains: usize,
+    mdns: bool,
+    addrconfig: bool,
+    disable_idn_encoding: bool,
+    numeric_only: bool,
+    https_hints: bool,
+    client_subnet: Option<ClientSubnet>,
+    coalesce: bool,
+    family: FamilyPreference,
+    dedup: bool,
+    syntax: HostnameSyntax,
+    keep_trailing_dot: bool,
+    retry: Option<RetryPolicy>,
+    concurrency_limit: Option<ConcurrencyLimit>,
+    hooks: LookupHooks,
+    #[cfg(feature = "cache")]
+    cache: Option<crate::CacheConfig>,
+}
+
+enum Backend {
+    System,
+    Custom(Arc<dyn ResolverBackend>),
+    Stub(ResolvConf),
+}
+
+impl Default for ResolverBuilder {
+    fn default() -> Self {
+        Self {
+            backend: Backend::System,
+            search: true,
+            max_search_domains: usize::MAX,
+            mdns: false,
+            addrconfig: false,
+            disable_idn_encoding: false,
+            numeric_only: false,
+            https_hints: false,
+            client_subnet: None,
+            coalesce: true,
+            family: FamilyPreference::Unspecified,
+            dedup: true,
+            syntax: HostnameSyntax::Strict,
+            keep_trailing_dot: false,
+            retry: None,
+            concurrency_limit: None,
+            hooks: LookupHooks::NONE,
+            #[cfg(feature = "cache")]
+            cache: None,
+        }
+    }
+}
+
+impl ResolverBuilder {
+    /// Uses `backend` for host names, instead of the platform resolver.
+    pub fn backend<B: ResolverBackend + 'static>(mut self, backend: B) -> Self {
+        self.backend = Backend::Custom(Arc::new(backend));
+        self
+    }
+
+    /// Uses a [`StubResolver`] configured from `conf`.
+    pub fn resolv_conf(mut self, conf: &ResolvConf) -> Self {
+        self.backend = Backend::Stub(conf.clone());
+        self
+    }
+
+    /// Whether the [`resolv_conf`](Self::resolv_conf) stub resolver expands names with the
+    /// search domains, `true` by default.
+    pub fn search(mut self, enabled: bool) -> Self {
+        self.search = enabled;
+        self
+    }
+
+    /// Caps how many search domains the [`resolv_conf`](Self::resolv_conf) stub resolver tries.
+    pub fn max_search_domains(mut self, max: usize) -> Self {
+        self.max_search_domains = max;
+        self
+    }
+
+    /// Sets the EDNS Client Subnet option the [`resolv_conf`](Self::resolv_conf) stub resolver
+    /// sends, see [`StubResolver::client_subnet`].
+    pub fn client_subnet(mut self, subnet: Option<ClientSubnet>) -> Self {
+        self.client_subnet = subnet;
+        self
+    }
+
+    /// Whether names ending in `.local` are resolved with [`MdnsResolver`], `false` by default.
+    pub fn mdns(mut self, enabled: bool) -> Self {
+        self.mdns = enabled;
+        self
+    }
+
+    /// Whether lookups only return addresses of the families configured on the host, setting
+    /// [`HintFlags::ADDRCONFIG`], `false` by default.
+    pub fn addrconfig(mut self, enabled: bool) -> Self {
+        self.addrconfig = enabled;
+        self
+    }
+
+    /// Whether the platform resolver looks names up as given, without converting them to
+    /// Punycode itself, setting [`HintFlags::DISABLE_IDN_ENCODING`], `false` by default.
+    ///
+    /// Only Windows converts them. Names other than ASCII pass the host name checks with
+    /// [`HostnameSyntax::Any`] only, unless the `idna` feature converts them first.
+    pub fn disable_idn_encoding(mut self, enabled: bool) -> Self {
+        self.disable_idn_encoding = enabled;
+        self
+    }
+
+    /// Whether only IP literals are accepted, `false` by default.
+    ///
+    /// Any other host fails right away without reaching the backend, as with
+    /// [`HintFlags::NUMERICHOST`], so the resolver never sends a query.
+    pub fn numeric_only(mut self, enabled: bool) -> Self {
+        self.numeric_only = enabled;
+        self
+    }
+
+    /// Whether the `ipv4hint` and `ipv6hint` addresses of each host's HTTPS records are added to
+    /// its addresses, `false` by default.
+    ///
+    /// The records are queried alongside the addresses, with the [`resolv_conf`](Self::resolv_conf)
+    /// stub resolver or else one configured with [`ResolvConf::system`]; lookups of HTTPS records
+    /// that fail, or are not answered shortly after the addresses, are ignored. Only the hints of
+    /// service records for the host itself are used.
+    pub fn https_hints(mut self, enabled: bool) -> Self {
+        self.https_hints = enabled;
+        self
+    }
+
+    /// Whether concurrent lookups of the same host, port and hints share a single one, `true`
+    /// by default.
+    ///
+    /// Each caller still waits no longer than its own timeout, and looks the host up again
+    /// itself when the shared lookup timed out before it. Service and canonical name lookups are
+    /// never shared.
+    pub fn coalesce(mut self, enabled: bool) -> Self {
+        self.coalesce = enabled;
+        self
+    }
+
+    /// Keeps the addresses found in memory until their TTL expires, or the
+    /// [default](crate::CacheConfig::default_ttl) one when the backend does not report it.
+    ///
+    /// Lookups are cached by host, family and [flags](HintFlags); canonical name lookups always
+    /// reach the backend, as do the HTTPS record queries of [`https_hints`](Self::https_hints).
+    /// Clones of the resolver share the cache. The lookups of the thread based
+    /// [`ThreadFallback`](crate::BackendKind::ThreadFallback) backend that time out still fill
+    /// it when they complete, for the lookup tried again to find their addresses.
+    #[cfg(feature = "cache")]
+    pub fn cache(mut self, config: crate::CacheConfig) -> Self {
+        self.cache = Some(config);
+        self
+    }
+
+    /// Which address families are returned and in which order, both in the order the backend
+    /// gives them by default.
+    ///
+    /// `V4Only` and `V6Only` ask the backend for that family alone, as [`Hints::family`] does,
+    /// unless a lookup asks for one itself. IP literals are returned as they are.
+    pub fn family_preference(mut self, preference: FamilyPreference) -> Self {
+        self.family = preference;
+        self
+    }
+
+    /// Whether addresses found more than once, as with [`SocketType::Any`](crate::SocketType::Any)
+    /// or hosts listed under several aliases, are returned only the first time, `true` by
+    /// default.
+    pub fn dedup(mut self, enabled: bool) -> Self {
+        self.dedup = enabled;
+        self
+    }
+
+    /// How strictly host names are checked before they are looked up, failing right away with
+    /// an [`InvalidHostname`](crate::InvalidHostname) error rather than after a lookup that
+    /// cannot succeed, [`HostnameSyntax::Strict`] by default.
+    pub fn hostname_syntax(mut self, syntax: HostnameSyntax) -> Self {
+        self.syntax = syntax;
+        self
+    }
+
+    /// Whether the system resolver is handed host names ending in a dot as they are, `false`
+    /// by default.
+    ///
+    /// Such names are fully qualified: the stub resolver looks them up without the search
+    /// domains. The platform resolvers do not match them against the hosts file, so the dot is
+    /// removed before they see them unless kept with this.
+    pub fn keep_trailing_dot(mut self, enabled: bool) -> Self {
+        self.keep_trailing_dot = enabled;
+        self
+    }
+
+    /// Makes the lookups failing temporarily again, within their timeout, as `policy` has it;
+    /// lookups are made once by default.
+    ///
+    /// Cached addresses are those of the lookup that succeeded, and coalesced lookups share its
+    /// retries.
+    pub fn retry(mut self, policy: RetryPolicy) -> Self {
+        self.retry = Some(policy);
+        self
+    }
+
+    /// Keeps the lookups the resolver hands to its backend at once within `limit`, those in
+    /// excess waiting, within their timeout, or failing as it has it; lookups are not limited
+    /// by default.
+    ///
+    /// Lookups answered by the cache, or by the same lookup of another thread, take no part in
+    /// the limit, retries do. Clones of the resolver share it.
+    pub fn concurrency_limit(mut self, limit: ConcurrencyLimit) -> Self {
+        self.concurrency_limit = Some(limit);
+        self
+    }
+
+    /// Runs `hooks` around the lookups of the resolver, from before the host name is checked to
+    /// once the addresses are arranged, see [`LookupHooks`].
+    pub fn hooks(mut self, hooks: LookupHooks) -> Self {
+        self.hooks = hooks;
+        self
+    }
+
+    /// Creates the resolver.
+    pub fn build(self) -> Resolver {
+        if self.numeric_only {
+            return Resolver {
+                backend: Arc::new(NumericOnly),
+                kind: BackendKind::NumericOnly,
+                cached: false,
+            };
+        }
+        #[cfg(feature = "cache")]
+        let cached = self.cache.is_some();
+        #[cfg(not(feature = "cache"))]
+        let cached = false;
+        let mut stub = None;
+        let (name, kind) = match self.backend {
+            Backend::System => (crate::BACKEND, crate::backend()),
+            Backend::Custom(_) => ("custom", BackendKind::Custom),
+            Backend::Stub(_) => ("stub", BackendKind::Stub),
+        };
+        let backend: Arc<dyn ResolverBackend> = match self.backend {
+            Backend::System if self.keep_trailing_dot => Arc::new(SystemResolver),
+            Backend::System => Arc::new(StripRoot(SystemResolver)),
+            Backend::Custom(backend) => backend,
+            Backend::Stub(conf) => {
+                let max = if self.search {
+                    self.max_search_domains
+                } else {
+                    0
+                };
+                let resolver = StubResolver::from_resolv_conf(&conf)
+                    .max_search_domains(max)
+                    .client_subnet(self.client_subnet);
+                stub = Some(resolver.clone());
+                Arc::new(resolver)
+            }
+        };
+        let backend: Arc<dyn ResolverBackend> = Arc::new(NonBlocking(backend));
+        let backend = match self.retry {
+            Some(policy) => Arc::new(Retried::new(backend, policy)),
+            None => backend,
+        };
+        let backend = match self.concurrency_limit {
+            Some(limit) => Arc::new(Limited::new(backend, limit)),
+            None => backend,
+        };
+        #[cfg(feature = "cache")]
+        let backend = match self.cache {
+            Some(config) => Arc::new(crate::cache::Cached::new(backend, config)),
+            None => backend,
+        };
+        let backend = if self.https_hints {
+            Arc::new(WithHttpsHints {
+                inner: backend,
+                stub: Arc::new(
+                    stub.unwrap_or_else(|| StubResolver::from_resolv_conf(&ResolvConf::system())),
+                ),
+            })
+        } else {
+            backend
+        };
+        let backend = if self.mdns {
+            Arc::new(LocalRouted { inner: backend })
+        } else {
+            backend
+        };
+        let mut flags = HintFlags::empty();
+        if self.addrconfig {
+            flags |= HintFlags::ADDRCONFIG;
+        }
+        if self.disable_idn_encoding {
+            flags |= HintFlags::DISABLE_IDN_ENCODING;
+        }
+        let backend = if flags == HintFlags::empty() {
+            backend
+        } else {
+            Arc::new(WithFlags {
+                inner: backend,
+                flags,
+            })
+        };
+        let backend = if self.family == FamilyPreference::Unspecified && !self.dedup {
+            backend
+        } else {
+            Arc::new(Arranged {
+                inner: backend,
+                preference: self.family,
+                dedup: self.dedup,
+            })
+        };
+        let backend = if self.coalesce {
+            Arc::new(Coalesced::new(backend))
+        } else {
+            backend
+        };
+        let backend = if self.syntax == HostnameSyntax::Any {
+            backend
+        } else {
+            Arc::new(Validated {
+                inner: backend,
+                syntax: self.syntax,
+            })
+        };
+        Resolver {
+            backend: Arc::new(Hooked {
+                inner: backend,
+                backend: name,
+                hooks: self.hooks,
+            }),
+            kind,
+            cached,
+        }
+    }
+}
+
+/// Sends `.local` names to mDNS, everything else to `inner`.
+struct LocalRouted {
+    inner: Arc<dyn ResolverBackend>,
+}
+
+impl ResolverBackend for LocalRouted {
+    fn resolve(
+        &self,
+        host: &str,
+        port: u16,
+        timeout: Duration,
+    ) -> io::Result<std::vec::IntoIter<SocketAddr>> {
+        if mdns::is_local(host) {
+            NonBlocking(&MdnsResolver).resolve(host, port, timeout)
+        } else {
+            self.inner.resolve(host, port, timeout)
+        }
+    }
+
+    fn resolve_with_hints(
+        &self,
+        host: &str,
+        port: u16,
+        hints: &Hints,
+        timeout: Duration,
+    ) -> io::Result<std::vec::IntoIter<SocketAddr>> {
+        if mdns::is_local(host) {
+            NonBlocking(&MdnsResolver).resolve_with_hints(host, port, hints, timeout)
+        } else {
+            self.inner.resolve_with_hints(host, port, hints, timeout)
+        }
+    }
+
+    fn resolve_service(
+        &self,
+        host: &str,
+        service: &str,
+        hints: &Hints,
+        timeout: Duration,
+    ) -> io::Result<std::vec::IntoIter<SocketAddr>> {
+        if mdns::is_local(host) {
+            NonBlocking(&MdnsResolver).resolve_service(host, service, hints, timeout)
+        } else {
+            self.inner.resolve_service(host, service, hints, timeout)
+        }
+    }
+
+    fn resolve_canonical(
+        &self,
+        host: &str,
+        port: u16,
+        hints: &Hints,
+        timeout: Duration,
+    ) -> io::Result<CanonicalAddrs> {
+        if mdns::is_local(host) {
+            NonBlocking(&MdnsResolver).resolve_canonical(host, port, hints, timeout)
+        } else {
+            self.inner.resolve_canonical(host, port, hints, timeout)
+        }
+    }
+
+    fn resolve_with_ttl(
+        &self,
+        host: &str,
+        port: u16,
+        hints: &Hints,
+        timeout: Duration,
+    ) -> io::Result<Vec<ResolvedAddr>> {
+        if mdns::is_local(host) {
+            NonBlocking(&MdnsResolver).resolve_with_ttl(host, port, hints, timeout)
+        } else {
+            self.inner.resolve_with_ttl(host, port, hints, timeout)
+        }
+    }
+}
+
+/// The backend `B` points to, failing lookups with a zero timeout with
+/// [`WouldBlock`](io::ErrorKind::WouldBlock) instead of handing them on.
+///
+/// Sits below the cache, which answers those it can.
+pub(crate) struct NonBlocking<B>(pub B);
+
+impl<B> NonBlocking<B> {
+    fn check(&self, timeout: Duration) -> io::Result<()> {
+        if timeout.is_zero() {
+            Err(would_block())
+        } else {
+            stats::attempted();
+            Ok(())
+        }
+    }
+}
+
+/// Error for a lookup with a zero timeout that nothing could answer without blocking.
+pub(crate) fn would_block() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::WouldBlock,
+        "host is neither an IP literal nor cached",
+    )
+}
+
+impl<B> ResolverBackend for NonBlocking<B>
+where
+    B: std::ops::Deref + Send + Sync,
+    B::Target: ResolverBackend,
+{
+    fn resolve(
+        &self,
+        host: &str,
+        port: u16,
+        timeout: Duration,
+    ) -> io::Result<std::vec::IntoIter<SocketAddr>> {
+        self.check(timeout)?;
+        self.0.resolve(host, port, timeout)
+    }
+
+    fn resolve_with_hints(
+        &self,
+        host: &str,
+        port: u16,
+        hints: &Hints,
+        timeout: Duration,
+    ) -> io::Result<std::vec::IntoIter<SocketAddr>> {
+        self.check(timeout)?;
+        self.0.resolve_with_hints(host, port, hints, timeout)
+    }
+
+    fn resolve_service(
+        &self,
+        host: &str,
+        service: &str,
+        hints: &Hints,
+        timeout: Duration,
+    ) -> io::Result<std::vec::IntoIter<SocketAddr>> {
+        self.check(timeout)?;
+        self.0.resolve_service(host, service, hints, timeout)
+    }
+
+    fn resolve_canonical(
+        &self,
+        host: &str,
+        port: u16,
+        hints: &Hints,
+        timeout: Duration,
+    ) -> io::Result<CanonicalAddrs> {
+        self.check(timeout)?;
+        self.0.resolve_canonical(host, port, hints, timeout)
+    }
+
+    fn resolve_with_ttl(
+        &self,
+        host: &str,
+        port: u16,
+        hints: &Hints,
+        timeout: Duration,
+    ) -> io::Result<Vec<ResolvedAddr>> {
+        self.check(timeout)?;
+        self.0.resolve_with_ttl(host, port, hints, timeout)
+    }
+}
+
+/// The platform resolver as the [`ToSocketAddrsTimeout`] impls use it: host names checked
+/// strictly, zero timeouts answered without blocking, lookups seen by the global hooks.
+pub(crate) const SYSTEM: Hooked<&Validated<&NonBlocking<&StripRoot<SystemResolver>>>> = Hooked {
+    inner: &Validated {
+        inner: &NonBlocking(&StripRoot(SystemResolver)),
+        syntax: HostnameSyntax::Strict,
+    },
+    backend: crate::BACKEND,
+    hooks: LookupHooks::NONE,
+};
+
+/// Rejects the host names not following `syntax` before `inner` sees them.
+pub(crate) struct Validated<B> {
+    inner: B,
+    syntax: HostnameSyntax,
+}
+
+impl<B> ResolverBackend for Validated<B>
+where
+    B: std::ops::Deref + Send + Sync,
+    B::Target: ResolverBackend,
+{
+    fn resolve(
+        &self,
+        host: &str,
+        port: u16,
+        timeout: Duration,
+    ) -> io::Result<std::vec::IntoIter<SocketAddr>> {
+        self.syntax.check(host)?;
+        self.inner.resolve(host, port, timeout)
+    }
+
+    fn resolve_with_hints(
+        &self,
+        host: &str,
+        port: u16,
+        hints: &Hints,
+        timeout: Duration,
+    ) -> io::Result<std::vec::IntoIter<SocketAddr>> {
+        self.syntax.check(host)?;
+        self.inner.resolve_with_hints(host, port, hints, timeout)
+    }
+
+    fn resolve_service(
+        &self,
+        host: &str,
+        service: &str,
+        hints: &Hints,
+        timeout: Duration,
+    ) -> io::Result<std::vec::IntoIter<SocketAddr>> {
+        self.syntax.check(host)?;
+        self.inner.resolve_service(host, service, hints, timeout)
+    }
+
+    fn resolve_canonical(
+        &self,
+        host: &str,
+        port: u16,
+        hints: &Hints,
+        timeout: Duration,
+    ) -> io::Result<CanonicalAddrs> {
+        self.syntax.check(host)?;
+        self.inner.resolve_canonical(host, port, hints, timeout)
+    }
+
+    fn resolve_with_ttl(
+        &self,
+        host: &str,
+        port: u16,
+        hints: &Hints,
+        timeout: Duration,
+    ) -> io::Result<Vec<ResolvedAddr>> {
+        self.syntax.check(host)?;
+        self.inner.resolve_with_ttl(host, port, hints, timeout)
+    }
+}
+
+/// Hands host names to the backend without their trailing dot, see
+/// [`ResolverBuilder::keep_trailing_dot`].
+pub(crate) struct StripRoot<B>(B);
+
+impl<B: ResolverBackend> ResolverBackend for StripRoot<B> {
+    fn resolve(
+        &self,
+        host: &str,
+        port: u16,
+        timeout: Duration,
+    ) -> io::Result<std::vec::IntoIter<SocketAddr>> {
+        self.0.resolve(hostname::without_root(host), port, timeout)
+    }
+
+    fn resolve_with_hints(
+        &self,
+        host: &str,
+        port: u16,
+        hints: &Hints,
+        timeout: Duration,
+    ) -> io::Result<std::vec::IntoIter<SocketAddr>> {
+        self.0
+            .resolve_with_hints(hostname::without_root(host), port, hints, timeout)
+    }
+
+    fn resolve_service(
+        &self,
+        host: &str,
+        service: &str,
+        hints: &Hints,
+        timeout: Duration,
+    ) -> io::Result<std::vec::IntoIter<SocketAddr>> {
+        self.0
+            .resolve_service(hostname::without_root(host), service, hints, timeout)
+    }
+
+    fn resolve_canonical(
+        &self,
+        host: &str,
+        port: u16,
+        hints: &Hints,
+        timeout: Duration,
+    ) -> io::Result<CanonicalAddrs> {
+        self.0
+            .resolve_canonical(hostname::without_root(host), port, hints, timeout)
+    }
+
+    fn resolve_with_ttl(
+        &self,
+        host: &str,
+        port: u16,
+        hints: &Hints,
+        timeout: Duration,
+    ) -> io::Result<Vec<ResolvedAddr>> {
+        self.0
+            .resolve_with_ttl(hostname::without_root(host), port, hints, timeout)
+    }
+}
+
+/// Fails every lookup, literals being answered before reaching the backend.
+struct NumericOnly;
+
+impl ResolverBackend for NumericOnly {
+    fn resolve(
+        &self,
+        _host: &str,
+        _port: u16,
+        _timeout: Duration,
+    ) -> io::Result<std::vec::IntoIter<SocketAddr>> {
+        Err(hints::not_numeric())
+    }
+}
+
+/// Asks `inner` for the families of `preference`, in its order, dropping repeated addresses
+/// with `dedup`.
+struct Arranged {
+    inner: Arc<dyn ResolverBackend>,
+    preference: FamilyPreference,
+    dedup: bool,
+}
+
+impl Arranged {
+    fn arrange<T>(&self, items: &mut Vec<T>, addr: impl Fn(&T) -> SocketAddr) {
+        if self.dedup {
+            order::dedup(items, &addr);
+        }
+        self.preference.sort(items, addr);
+    }
+}
+
+impl ResolverBackend for Arranged {
+    fn resolve(
+        &self,
+        host: &str,
+        port: u16,
+        timeout: Duration,
+    ) -> io::Result<std::vec::IntoIter<SocketAddr>> {
+        self.resolve_with_hints(host, port, &Hints::default(), timeout)
+    }
+
+    fn resolve_with_hints(
+        &self,
+        host: &str,
+        port: u16,
+        hints: &Hints,
+        timeout: Duration,
+    ) -> io::Result<std::vec::IntoIter<SocketAddr>> {
+        let hints = self.preference.apply(hints);
+        let mut addrs: Vec<_> = self
+            .inner
+            .resolve_with_hints(host, port, &hints, timeout)?
+            .collect();
+        self.arrange(&mut addrs, |&addr| addr);
+        Ok(addrs.into_iter())
+    }
+
+    fn resolve_service(
+        &self,
+        host: &str,
+        service: &str,
+        hints: &Hints,
+        timeout: Duration,
+    ) -> io::Result<std::vec::IntoIter<SocketAddr>> {
+        let hints = self.preference.apply(hints);
+        let mut addrs: Vec<_> = self
+            .inner
+            .resolve_service(host, service, &hints, timeout)?
+            .collect();
+        self.arrange(&mut addrs, |&addr| addr);
+        Ok(addrs.into_iter())
+    }
+
+    fn resolve_canonical(
+        &self,
+        host: &str,
+        port: u16,
+        hints: &Hints,
+        timeout: Duration,
+    ) -> io::Result<CanonicalAddrs> {
+        let hints = self.preference.apply(hints);
+        let mut canonical = self.inner.resolve_canonical(host, port, &hints, timeout)?;
+        self.arrange(&mut canonical.addrs, |&addr| addr);
+        Ok(canonical)
+    }
+
+    fn resolve_with_ttl(
+        &self,
+        host: &str,
+        port: u16,
+        hints: &Hints,
+        timeout: Duration,
+    ) -> io::Result<Vec<ResolvedAddr>> {
+        let hints = self.preference.apply(hints);
+        let mut resolved = self.inner.resolve_with_ttl(host, port, &hints, timeout)?;
+        self.arrange(&mut resolved, |r| r.addr);
+        Ok(resolved)
+    }
+}
+
+/// Adds `flags` to the hints of every lookup.
+struct WithFlags {
+    inner: Arc<dyn ResolverBackend>,
+    flags: HintFlags,
+}
+
+impl ResolverBackend for WithFlags {
+    fn resolve(
+        &self,
+        host: &str,
+        port: u16,
+        timeout: Duration,
+    ) -> io::Result<std::vec::IntoIter<SocketAddr>> {
+        self.resolve_with_hints(host, port, &Hints::default(), timeout)
+    }
+
+    fn resolve_with_hints(
+        &self,
+        host: &str,
+        port: u16,
+        hints: &Hints,
+        timeout: Duration,
+    ) -> io::Result<std::vec::IntoIter<SocketAddr>> {
+        self.inner
+            .resolve_with_hints(host, port, &self.add_to(hints), timeout)
+    }
+
+    fn resolve_service(
+        &self,
+        host: &str,
+        service: &str,
+        hints: &Hints,
+        timeout: Duration,
+    ) -> io::Result<std::vec::IntoIter<SocketAddr>> {
+        self.inner
+            .resolve_service(host, service, &self.add_to(hints), timeout)
+    }
+
+    fn resolve_canonical(
+        &self,
+        host: &str,
+        port: u16,
+        hints: &Hints,
+        timeout: Duration,
+    ) -> io::Result<CanonicalAddrs> {
+        self.inner
+            .resolve_canonical(host, port, &self.add_to(hints), timeout)
+    }
+
+    fn resolve_with_ttl(
+        &self,
+        host: &str,
+        port: u16,
+        hints: &Hints,
+        timeout: Duration,
+    ) -> io::Result<Vec<ResolvedAddr>> {
+        self.inner
+            .resolve_with_ttl(host, port, &self.add_to(hints), timeout)
+    }
+}
+
+impl WithFlags {
+    fn add_to(&self, hints: &Hints) -> Hints {
+        Hints {
+            flags: hints.flags | self.flags,
+            ..*hints
+        }
+    }
+}
+
+/// Adds the addresses hinted by the HTTPS records of each host to those `inner` finds.
+struct WithHttpsHints {
+    inner: Arc<dyn ResolverBackend>,
+    stub: Arc<StubResolver>,
+}
+
+/// How long the HTTPS records of a host are waited for once its addresses are found.
+const HTTPS_HINTS_GRACE: Duration = Duration::from_millis(50);
+
+impl ResolverBackend for WithHttpsHints {
+    fn resolve(
+        &self,
+        host: &str,
+        port: u16,
+        timeout: Duration,
+    ) -> io::Result<std::vec::IntoIter<SocketAddr>> {
+        let resolved = self.merged(host, port, &Hints::default(), timeout, || {
+            Ok(untimed(self.inner.resolve(host, port, timeout)?))
+        })?;
+        Ok(addrs_of(resolved))
+    }
+
+    fn resolve_with_hints(
+        &self,
+        host: &str,
+        port: u16,
+        hints: &Hints,
+        timeout: Duration,
+    ) -> io::Result<std::vec::IntoIter<SocketAddr>> {
+        let resolved = self.merged(host, port, hints, timeout, || {
+            Ok(untimed(
+                self.inner.resolve_with_hints(host, port, hints, timeout)?,
+            ))
+        })?;
+        Ok(addrs_of(resolved))
+    }
+
+    fn resolve_service(
+        &self,
+        host: &str,
+        service: &str,
+        hints: &Hints,
+        timeout: Duration,
+    ) -> io::Result<std::vec::IntoIter<SocketAddr>> {
+        let port = service
+            .parse()
+            .ok()
+            .or_else(|| services::port(service, hints.service_proto()));
+        match port {
+            Some(port) => {
+                let resolved = self.merged(host, port, hints, timeout, || {
+                    Ok(untimed(
+                        self.inner.resolve_service(host, service, hints, timeout)?,
+                    ))
+                })?;
+                Ok(addrs_of(resolved))
+            }
+            None => self.inner.resolve_service(host, service, hints, timeout),
+        }
+    }
+
+    fn resolve_canonical(
+        &self,
+        host: &str,
+        port: u16,
+        hints: &Hints,
+        timeout: Duration,
+    ) -> io::Result<CanonicalAddrs> {
+        self.inner.resolve_canonical(host, port, hints, timeout)
+    }
+
+    fn resolve_with_ttl(
+        &self,
+        host: &str,
+        port: u16,
+        hints: &Hints,
+        timeout: Duration,
+    ) -> io::Result<Vec<ResolvedAddr>> {
+        self.merged(host, port, hints, timeout, || {
+            self.inner.resolve_with_ttl(host, port, hints, timeout)
+        })
+    }
+}
+
+impl WithHttpsHints {
+    /// Runs `lookup` while querying the HTTPS records of `host` for `port` on a thread of their
+    /// own, then appends the hinted addresses `hints` allow that it did not find, without a TTL,
+    /// should the records come in at most [`HTTPS_HINTS_GRACE`] after it.
+    fn merged(
+        &self,
+        host: &str,
+        port: u16,
+        hints: &Hints,
+        timeout: Duration,
+        lookup: impl FnOnce() -> io::Result<Vec<ResolvedAddr>>,
+    ) -> io::Result<Vec<ResolvedAddr>> {
+        // port 0 is no origin, as when prefetching; records are never cached
+        if port == 0 || timeout.is_zero() || mdns::is_local(host) {
+            return lookup();
+        }
+        let name = match port {
+            443 => host.to_owned(),
+            port => format!("_{port}._https.{host}"),
+        };
+        let (tx, rx) = std::sync::mpsc::sync_channel(1);
+        let stub = self.stub.clone();
+        let spawned = std::thread::Builder::new()
+            .name("to_socket_addrs_timeout-https".into())
+            .spawn(move || {
+                // the receiver is gone once the lookup stopped waiting for the records
+                _ = tx.send(stub.lookup_https_records(&name, timeout));
+            });
+        let mut addrs = lookup()?;
+        if spawned.is_err() {
+            return Ok(addrs);
+        }
+        let Ok(Ok(records)) = rx.recv_timeout(HTTPS_HINTS_GRACE) else {
+            return Ok(addrs);
+        };
+        let host = host.trim_end_matches('.');
+        let hinted: Vec<_> = records
+            .into_iter()
+            .filter(|record| {
+                !record.is_alias()
+                    && (record.target.is_empty() || record.target.eq_ignore_ascii_case(host))
+            })
+            .flat_map(|record| {
+                let v6 = record.ipv6_hint.into_iter().map(IpAddr::from);
+                v6.chain(record.ipv4_hint.into_iter().map(IpAddr::from))
+            })
+            .map(|ip| SocketAddr::new(ip, port))
+            .collect();
+        for addr in hints.filter(hinted.into_iter()) {
+            if !addrs.iter().any(|r| r.addr == addr) {
+                addrs.push(ResolvedAddr { addr, ttl: None });
+            }
+        }
+        Ok(addrs)
+    }
+}
+
+fn untimed(addrs: std::vec::IntoIter<SocketAddr>) -> Vec<ResolvedAddr> {
+    addrs.map(|addr| ResolvedAddr { addr, ttl: None }).collect()
+}
+
+fn addrs_of(resolved: Vec<ResolvedAddr>) -> std::vec::IntoIter<SocketAddr> {
+    let addrs: Vec<_> = resolved.into_iter().map(|r| r.addr).collect();
+    addrs.into_iter()
+}
+
+impl core::fmt::Debug for ResolverBuilder {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("ResolverBuilder").finish_non_exhaustive()
+    }
+}
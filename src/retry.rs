@@ -0,0 +1,163 @@
+use std::{
+    io,
+    net::SocketAddr,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use crate::{CanonicalAddrs, Hints, ResolvedAddr, ResolverBackend};
+
+/// How a [`Resolver`](crate::Resolver) tries lookups again after a temporary failure, see
+/// [`ResolverBuilder::retry`](crate::ResolverBuilder::retry).
+///
+/// Failures of the [`ResourceBusy`](io::ErrorKind::ResourceBusy) kind, such as `EAI_AGAIN`
+/// and `WSATRY_AGAIN` ([`ResolveError::TemporaryFailure`](crate::ResolveError::TemporaryFailure)), and
+/// interrupted lookups are retried, waiting twice as long before each retry as before the one
+/// before. Retries never outlast the timeout of the lookup: once the wait would take it past
+/// it, the last failure is returned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct RetryPolicy {
+    /// Most lookups made after the first one, 2 by default.
+    pub max_retries: u32,
+    /// Wait before the first retry, 50 milliseconds by default.
+    pub initial_backoff: Duration,
+    /// Longest wait between two lookups, 1 second by default.
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 2,
+            initial_backoff: Duration::from_millis(50),
+            max_backoff: Duration::from_secs(1),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Sets [`max_retries`](Self::max_retries).
+    pub fn max_retries(mut self, max: u32) -> Self {
+        self.max_retries = max;
+        self
+    }
+
+    /// Sets [`initial_backoff`](Self::initial_backoff).
+    pub fn initial_backoff(mut self, backoff: Duration) -> Self {
+        self.initial_backoff = backoff;
+        self
+    }
+
+    /// Sets [`max_backoff`](Self::max_backoff).
+    pub fn max_backoff(mut self, backoff: Duration) -> Self {
+        self.max_backoff = backoff;
+        self
+    }
+}
+
+/// `inner` with the lookups failing temporarily made again as `policy` has it.
+pub(crate) struct Retried {
+    inner: Arc<dyn ResolverBackend>,
+    policy: RetryPolicy,
+}
+
+impl Retried {
+    pub fn new(inner: Arc<dyn ResolverBackend>, policy: RetryPolicy) -> Self {
+        Self { inner, policy }
+    }
+
+    /// Runs `lookup` with the time left of `timeout` until it succeeds, fails for good or runs
+    /// out of retries.
+    fn retry<T>(
+        &self,
+        timeout: Duration,
+        mut lookup: impl FnMut(Duration) -> io::Result<T>,
+    ) -> io::Result<T> {
+        let deadline = crate::deadline_after(timeout);
+        let mut backoff = self.policy.initial_backoff;
+        let mut result = lookup(timeout);
+        for _ in 0..self.policy.max_retries {
+            match &result {
+                Err(err) if is_temporary(err) => (),
+                _ => break,
+            }
+            let left = deadline.saturating_duration_since(Instant::now());
+            if left <= backoff {
+                break;
+            }
+            // std cannot sleep in browsers, where the retries follow each other right away
+            #[cfg(not(backend = "wasm"))]
+            std::thread::sleep(backoff);
+            result = lookup(left - backoff);
+            backoff = backoff.saturating_mul(2).min(self.policy.max_backoff);
+        }
+        result
+    }
+}
+
+fn is_temporary(err: &io::Error) -> bool {
+    matches!(
+        err.kind(),
+        io::ErrorKind::ResourceBusy | io::ErrorKind::Interrupted
+    )
+}
+
+impl ResolverBackend for Retried {
+    fn resolve(
+        &self,
+        host: &str,
+        port: u16,
+        timeout: Duration,
+    ) -> io::Result<std::vec::IntoIter<SocketAddr>> {
+        self.retry(timeout, |timeout| self.inner.resolve(host, port, timeout))
+    }
+
+    fn resolve_with_hints(
+        &self,
+        host: &str,
+        port: u16,
+        hints: &Hints,
+        timeout: Duration,
+    ) -> io::Result<std::vec::IntoIter<SocketAddr>> {
+        self.retry(timeout, |timeout| {
+            self.inner.resolve_with_hints(host, port, hints, timeout)
+        })
+    }
+
+    fn resolve_service(
+        &self,
+        host: &str,
+        service: &str,
+        hints: &Hints,
+        timeout: Duration,
+    ) -> io::Result<std::vec::IntoIter<SocketAddr>> {
+        self.retry(timeout, |timeout| {
+            self.inner.resolve_service(host, service, hints, timeout)
+        })
+    }
+
+    fn resolve_canonical(
+        &self,
+        host: &str,
+        port: u16,
+        hints: &Hints,
+        timeout: Duration,
+    ) -> io::Result<CanonicalAddrs> {
+        self.retry(timeout, |timeout| {
+            self.inner.resolve_canonical(host, port, hints, timeout)
+        })
+    }
+
+    fn resolve_with_ttl(
+        &self,
+        host: &str,
+        port: u16,
+        hints: &Hints,
+        timeout: Duration,
+    ) -> io::Result<Vec<ResolvedAddr>> {
+        self.retry(timeout, |timeout| {
+            self.inner.resolve_with_ttl(host, port, hints, timeout)
+        })
+    }
+}
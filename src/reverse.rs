@@ -0,0 +1,211 @@
+use std::{
+    io,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6},
+    time::Duration,
+};
+
+#[cfg(any(unix, windows))]
+use crate::AddressInfoError;
+use crate::{ResolvConf, StubResolver};
+
+/// Reverse resolution of an address to a host name, bounded by a timeout.
+///
+/// Lookups run `getnameinfo` (`GetNameInfoW` on Windows) on a helper thread, which is abandoned
+/// on timeout and counted by [`orphaned_lookups`](crate::orphaned_lookups) until it returns.
+/// Other targets report [`Unsupported`](io::ErrorKind::Unsupported).
+pub trait ToHostnameTimeout {
+    /// The host name the address resolves to, giving up once `timeout` has elapsed.
+    ///
+    /// Addresses without a name are an error rather than being returned in numeric form.
+    fn to_hostname_timeout(&self, timeout: Duration) -> io::Result<String>;
+}
+
+impl ToHostnameTimeout for SocketAddr {
+    fn to_hostname_timeout(&self, timeout: Duration) -> io::Result<String> {
+        name_info_timeout(*self, timeout)
+    }
+}
+
+impl ToHostnameTimeout for SocketAddrV4 {
+    fn to_hostname_timeout(&self, timeout: Duration) -> io::Result<String> {
+        SocketAddr::V4(*self).to_hostname_timeout(timeout)
+    }
+}
+
+impl ToHostnameTimeout for SocketAddrV6 {
+    fn to_hostname_timeout(&self, timeout: Duration) -> io::Result<String> {
+        SocketAddr::V6(*self).to_hostname_timeout(timeout)
+    }
+}
+
+impl ToHostnameTimeout for IpAddr {
+    fn to_hostname_timeout(&self, timeout: Duration) -> io::Result<String> {
+        SocketAddr::new(*self, 0).to_hostname_timeout(timeout)
+    }
+}
+
+impl ToHostnameTimeout for Ipv4Addr {
+    fn to_hostname_timeout(&self, timeout: Duration) -> io::Result<String> {
+        IpAddr::V4(*self).to_hostname_timeout(timeout)
+    }
+}
+
+impl ToHostnameTimeout for Ipv6Addr {
+    fn to_hostname_timeout(&self, timeout: Duration) -> io::Result<String> {
+        IpAddr::V6(*self).to_hostname_timeout(timeout)
+    }
+}
+
+impl<T: ToHostnameTimeout + ?Sized> ToHostnameTimeout for &T {
+    fn to_hostname_timeout(&self, timeout: Duration) -> io::Result<String> {
+        (**self).to_hostname_timeout(timeout)
+    }
+}
+
+/// All the names the PTR records of `ip` point to, queried with a [`StubResolver`] configured
+/// with [`ResolvConf::system`], giving up once `timeout` has elapsed.
+///
+/// See [`StubResolver::lookup_ptr`].
+pub fn lookup_ptr(ip: IpAddr, timeout: Duration) -> io::Result<Vec<String>> {
+    StubResolver::from_resolv_conf(&ResolvConf::system()).lookup_ptr(ip, timeout)
+}
+
+#[cfg(any(unix, windows))]
+fn name_info_timeout(addr: SocketAddr, timeout: Duration) -> io::Result<String> {
+    use std::sync::{
+        mpsc::{self, RecvTimeoutError},
+        Arc, OnceLock,
+    };
+
+    use crate::orphan::Orphan;
+
+    let (tx, rx) = mpsc::sync_channel(1);
+    let orphan = Arc::new(OnceLock::new());
+    {
+        let orphan = orphan.clone();
+        std::thread::spawn(move || {
+            _ = tx.send(name_info(addr));
+            drop(orphan);
+        });
+    }
+    match rx.recv_timeout(timeout) {
+        Ok(res) => res,
+        Err(RecvTimeoutError::Timeout) => {
+            orphan.get_or_init(Orphan::new);
+            Err(io::ErrorKind::TimedOut.into())
+        }
+        Err(RecvTimeoutError::Disconnected) => unreachable!(),
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+fn name_info_timeout(_addr: SocketAddr, _timeout: Duration) -> io::Result<String> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "reverse lookups are not supported on this platform",
+    ))
+}
+
+/// Longest host name `getnameinfo` returns, `NI_MAXHOST`.
+#[cfg(any(unix, windows))]
+const MAX_HOST: usize = 1025;
+
+#[cfg(unix)]
+fn name_info(addr: SocketAddr) -> io::Result<String> {
+    let mut storage: libc::sockaddr_storage = unsafe { core::mem::zeroed() };
+    let len = match addr {
+        SocketAddr::V4(addr) => {
+            let sin = unsafe { &mut *(&mut storage as *mut _ as *mut libc::sockaddr_in) };
+            sin.sin_family = libc::AF_INET as _;
+            sin.sin_port = addr.port().to_be();
+            sin.sin_addr.s_addr = u32::from_ne_bytes(addr.ip().octets());
+            core::mem::size_of::<libc::sockaddr_in>()
+        }
+        SocketAddr::V6(addr) => {
+            let sin6 = unsafe { &mut *(&mut storage as *mut _ as *mut libc::sockaddr_in6) };
+            sin6.sin6_family = libc::AF_INET6 as _;
+            sin6.sin6_port = addr.port().to_be();
+            sin6.sin6_flowinfo = addr.flowinfo();
+            sin6.sin6_addr.s6_addr = addr.ip().octets();
+            sin6.sin6_scope_id = addr.scope_id();
+            core::mem::size_of::<libc::sockaddr_in6>()
+        }
+    };
+    #[cfg(any(
+        target_vendor = "apple",
+        target_os = "freebsd",
+        target_os = "openbsd",
+        target_os = "netbsd",
+        target_os = "dragonfly"
+    ))]
+    {
+        storage.ss_len = len as u8;
+    }
+
+    let mut host = [0 as ::core::ffi::c_char; MAX_HOST];
+    let ret = unsafe {
+        libc::getnameinfo(
+            &storage as *const _ as *const libc::sockaddr,
+            len as libc::socklen_t,
+            host.as_mut_ptr(),
+            host.len() as _,
+            core::ptr::null_mut(),
+            0,
+            libc::NI_NAMEREQD as _,
+        )
+    };
+    match ret {
+        0 => Ok(unsafe { ::core::ffi::CStr::from_ptr(host.as_ptr()) }
+            .to_string_lossy()
+            .into_owned()),
+        libc::EAI_SYSTEM => Err(io::Error::last_os_error()),
+        code => Err(AddressInfoError::from_raw_code(code).into()),
+    }
+}
+
+#[cfg(windows)]
+fn name_info(addr: SocketAddr) -> io::Result<String> {
+    use windows::Win32::Networking::WinSock::{
+        socklen_t, GetNameInfoW, WSAGetLastError, AF_INET, AF_INET6, NI_NAMEREQD, SOCKADDR,
+        SOCKADDR_IN, SOCKADDR_IN6, SOCKADDR_STORAGE,
+    };
+
+    // makes Rust call WSAStartup
+    _ = std::net::UdpSocket::bind((Ipv4Addr::LOCALHOST, 0));
+
+    let mut storage = SOCKADDR_STORAGE::default();
+    let len = match addr {
+        SocketAddr::V4(addr) => {
+            let sin = unsafe { &mut *(&mut storage as *mut _ as *mut SOCKADDR_IN) };
+            sin.sin_family = AF_INET;
+            sin.sin_port = addr.port().to_be();
+            sin.sin_addr.S_un.S_addr = u32::from_ne_bytes(addr.ip().octets());
+            core::mem::size_of::<SOCKADDR_IN>()
+        }
+        SocketAddr::V6(addr) => {
+            let sin6 = unsafe { &mut *(&mut storage as *mut _ as *mut SOCKADDR_IN6) };
+            sin6.sin6_family = AF_INET6;
+            sin6.sin6_port = addr.port().to_be();
+            sin6.sin6_flowinfo = addr.flowinfo();
+            sin6.sin6_addr.u.Byte = addr.ip().octets();
+            sin6.Anonymous.sin6_scope_id = addr.scope_id();
+            core::mem::size_of::<SOCKADDR_IN6>()
+        }
+    };
+
+    let mut host = [0u16; MAX_HOST];
+    let ret = unsafe {
+        GetNameInfoW(
+            &storage as *const _ as *const SOCKADDR,
+            socklen_t(len as _),
+            Some(&mut host),
+            None,
+            NI_NAMEREQD as _,
+        )
+    };
+    if ret != 0 {
+        return Err(AddressInfoError::from_raw_code(unsafe { WSAGetLastError() }.0).into());
+    }
+    let end = host.iter().position(|&c| c == 0).unwrap_or(host.len());
+    Ok(String::from_utf16_lossy(&host[..end]))
+}
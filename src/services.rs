@@ -0,0 +1,53 @@
+use std::{io, path::PathBuf};
+
+/// Port of the service `name`, or one of its aliases, for `proto` (`"tcp"`, `"udp"`) in the
+/// system services file.
+pub(crate) fn port(name: &str, proto: &str) -> Option<u16> {
+    let text = std::fs::read_to_string(system_path()).ok()?;
+    lookup(&text, name, proto)
+}
+
+/// `service` as a port number, or else looked up in the system services file as a TCP service.
+pub(crate) fn parse_port(service: &str) -> io::Result<u16> {
+    if let Ok(port) = service.parse() {
+        return Ok(port);
+    }
+    if service.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "invalid port value",
+        ));
+    }
+    port(service, "tcp").ok_or_else(unknown)
+}
+
+pub(crate) fn unknown() -> io::Error {
+    io::Error::new(io::ErrorKind::NotFound, "unknown service name")
+}
+
+fn lookup(text: &str, name: &str, proto: &str) -> Option<u16> {
+    text.lines().find_map(|line| {
+        let line = line.split('#').next().unwrap_or_default();
+        let mut words = line.split_ascii_whitespace();
+        let official = words.next()?;
+        let (port, entry_proto) = words.next()?.split_once('/')?;
+        if !entry_proto.eq_ignore_ascii_case(proto) {
+            return None;
+        }
+        std::iter::once(official)
+            .chain(words)
+            .any(|alias| alias.eq_ignore_ascii_case(name))
+            .then(|| port.parse().ok())?
+    })
+}
+
+#[cfg(windows)]
+fn system_path() -> PathBuf {
+    let root = std::env::var_os("SystemRoot").unwrap_or_else(|| "C:\\Windows".into());
+    PathBuf::from(root).join("System32\\drivers\\etc\\services")
+}
+
+#[cfg(not(windows))]
+fn system_path() -> PathBuf {
+    PathBuf::from("/etc/services")
+}
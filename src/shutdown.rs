@@ -0,0 +1,46 @@
+use std::{
+    io,
+    sync::atomic::{AtomicBool, Ordering},
+    time::{Duration, Instant},
+};
+
+use crate::ResolveError;
+
+static SHUT_DOWN: AtomicBool = AtomicBool::new(false);
+
+/// Stops the lookups of the process for good, waiting up to `timeout` for those left running
+/// in the background to return, and returns how many were abandoned still running, see
+/// [`orphaned_lookups`](crate::orphaned_lookups).
+///
+/// Lookups started afterwards fail with [`ResolveError::ShutDown`], and the idle worker
+/// threads of the platform resolver exit. Lookups already queued for a worker still run.
+pub fn shutdown(timeout: Duration) -> usize {
+    SHUT_DOWN.store(true, Ordering::SeqCst);
+    crate::pool::close_all();
+
+    let deadline = crate::deadline_after(timeout);
+    loop {
+        let left = crate::orphaned_lookups();
+        let now = Instant::now();
+        if left == 0 || now >= deadline || cfg!(backend = "wasm") {
+            return left;
+        }
+        // the background lookups announce nothing when they return
+        #[cfg(not(backend = "wasm"))]
+        std::thread::sleep((deadline - now).min(Duration::from_millis(10)));
+    }
+}
+
+/// Whether [`shutdown`] was called.
+pub(crate) fn is_shut_down() -> bool {
+    SHUT_DOWN.load(Ordering::SeqCst)
+}
+
+/// Fails once [`shutdown`] was called.
+pub(crate) fn check() -> io::Result<()> {
+    if is_shut_down() {
+        Err(ResolveError::ShutDown.into())
+    } else {
+        Ok(())
+    }
+}
@@ -0,0 +1,59 @@
+use std::{cell::Cell, time::Duration};
+
+use crate::BackendKind;
+
+/// How a lookup of [`Resolver::resolve_with_stats`](crate::Resolver::resolve_with_stats) went.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct ResolveStats {
+    /// Time the lookup took.
+    pub elapsed: Duration,
+    /// The part of the timeout the lookup left.
+    pub remaining: Duration,
+    /// The backend of the resolver, see [`Resolver::backend`](crate::Resolver::backend).
+    pub backend: BackendKind,
+    /// Lookups the backend was asked for, retries included: 0 when the addresses came from
+    /// the cache, from an IP literal or from the same lookup made by another thread.
+    pub attempts: u32,
+    /// Whether the addresses came from the cache of the resolver.
+    pub cached: bool,
+}
+
+/// What the lookup layers tell about the lookup running on this thread, see [`collect`].
+#[derive(Clone, Copy, Default)]
+pub(crate) struct Counts {
+    pub attempts: u32,
+    pub cached: bool,
+}
+
+thread_local! {
+    static COLLECTING: Cell<Option<Counts>> = const { Cell::new(None) };
+}
+
+/// Runs `lookup`, counting what the layers it goes through report.
+pub(crate) fn collect<T>(lookup: impl FnOnce() -> T) -> (T, Counts) {
+    let outer = COLLECTING.replace(Some(Counts::default()));
+    let result = lookup();
+    let counts = COLLECTING.replace(outer).unwrap_or_default();
+    (result, counts)
+}
+
+fn update(f: impl FnOnce(&mut Counts)) {
+    COLLECTING.with(|collecting| {
+        if let Some(mut counts) = collecting.get() {
+            f(&mut counts);
+            collecting.set(Some(counts));
+        }
+    });
+}
+
+/// Counts a lookup handed to the backend.
+pub(crate) fn attempted() {
+    update(|counts| counts.attempts += 1);
+}
+
+/// Notes addresses served from the cache.
+#[cfg(feature = "cache")]
+pub(crate) fn cache_hit() {
+    update(|counts| counts.cached = true);
+}
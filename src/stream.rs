@@ -0,0 +1,107 @@
+use std::{
+    collections::VecDeque,
+    future::Future,
+    io,
+    net::SocketAddr,
+    pin::Pin,
+    task::{Context, Poll},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::future::thread_waker;
+
+/// Blocking iterator yielding addresses as soon as the backend produces them.
+///
+/// Where the platform allows it, IPv6 and IPv4 addresses are looked up separately and each
+/// family is yielded as soon as its lookup completes, so a connector can start with the first
+/// answer while the other one is still in flight.
+///
+/// Errors are only yielded, once, when the stream ends without having produced any address; an
+/// expired timeout is reported as [`io::ErrorKind::TimedOut`].
+pub struct ResolveStream {
+    pending: Vec<crate::sys::Lookup>,
+    ready: VecDeque<SocketAddr>,
+    error: Option<io::Error>,
+    yielded: bool,
+    deadline: Instant,
+}
+
+/// Resolves `host`, yielding addresses incrementally until `timeout` has elapsed.
+pub fn resolve_stream(host: &str, port: u16, timeout: Duration) -> ResolveStream {
+    let mut stream = ResolveStream {
+        pending: Vec::new(),
+        ready: VecDeque::new(),
+        error: None,
+        yielded: false,
+        deadline: crate::deadline_after(timeout),
+    };
+
+    if let Some(addr) = crate::literal(host, port) {
+        stream.ready.push_back(addr);
+    } else {
+        let host = crate::hostname::to_ascii(host);
+        match crate::shutdown::check().and_then(|()| {
+            crate::sys::lookup_split(crate::hostname::without_root(&host), port, Some(timeout))
+        }) {
+            Ok(pending) => stream.pending = pending,
+            Err(err) => stream.error = Some(err),
+        }
+    }
+
+    stream
+}
+
+impl ResolveStream {
+    fn poll_pending(&mut self, cx: &mut Context<'_>) {
+        let mut i = 0;
+        while i < self.pending.len() {
+            match Pin::new(&mut self.pending[i]).poll(cx) {
+                Poll::Ready(res) => {
+                    drop(self.pending.swap_remove(i));
+                    match res {
+                        Ok(addrs) => self.ready.extend(addrs),
+                        Err(err) => {
+                            self.error.get_or_insert(err);
+                        }
+                    }
+                }
+                Poll::Pending => i += 1,
+            }
+        }
+    }
+}
+
+impl Iterator for ResolveStream {
+    type Item = io::Result<SocketAddr>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let waker = thread_waker();
+        let mut cx = Context::from_waker(&waker);
+
+        loop {
+            if let Some(addr) = self.ready.pop_front() {
+                self.yielded = true;
+                return Some(Ok(addr));
+            }
+            if self.pending.is_empty() {
+                let err = self.error.take()?;
+                return if self.yielded { None } else { Some(Err(err)) };
+            }
+
+            self.poll_pending(&mut cx);
+            if !self.ready.is_empty() || self.pending.is_empty() {
+                continue;
+            }
+
+            match self.deadline.checked_duration_since(Instant::now()) {
+                Some(timeout) => thread::park_timeout(timeout),
+                None => {
+                    // dropping the lookups cancels them
+                    self.pending.clear();
+                    self.error = Some(io::ErrorKind::TimedOut.into());
+                }
+            }
+        }
+    }
+}
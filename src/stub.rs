@@ -0,0 +1,1108 @@
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+    io::{self, Read, Write},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, TcpStream, UdpSocket},
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        mpsc, Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use crate::{
+    dns::{
+        self, build_query, encode_name, names_eq, push_opt, Message, Rcode, Record, CLASS_IN,
+        FLAG_AD, FLAG_RD, OPT_ECS, TYPE_A, TYPE_AAAA, TYPE_CNAME, TYPE_HTTPS, TYPE_PTR, TYPE_TXT,
+    },
+    hints, hostaliases, hosts, llmnr, CnameChain, DnsMessage, Family, HintFlags, Hints, Hosts,
+    HttpsRecord, ResolvConf, ResolveError, ResolvedAddr, ResolverBackend,
+};
+
+/// Queries made following a CNAME chain the nameservers leave unfinished.
+const MAX_CNAME_QUERIES: usize = 8;
+
+/// Largest UDP response accepted, as advertised with EDNS, the size avoiding fragmentation.
+const UDP_SIZE: u16 = 1232;
+
+/// Resolver speaking DNS directly to a list of nameservers.
+///
+/// Names listed in the system hosts file are answered from it, see [`hosts`](Self::hosts).
+/// Others are mapped through the `HOSTALIASES` file, or else expanded with the
+/// [`search`](Self::search) domains, and looked up with A and AAAA queries sent over UDP,
+/// retried over TCP when the answer is truncated. Candidate names are tried one after the other.
+/// Each attempt against a nameserver is bounded by [`attempt_timeout`](Self::attempt_timeout),
+/// the whole lookup by the caller's timeout, the nameservers being tried the given number of
+/// [`attempts`](Self::attempts) starting with the one that has answered fastest so far, or in
+/// turn with [`rotate`](Self::rotate), or all at once with [`race`](Self::race). Single label names the nameservers do not know
+/// can then be resolved on the local link with LLMNR, see [`llmnr`](Self::llmnr).
+///
+/// With [`dnssec`](Self::dnssec), only answers the nameservers validated are accepted. Queries
+/// carry the EDNS Client Subnet option given to [`client_subnet`](Self::client_subnet).
+///
+/// Every query is sent from a new socket bound to a random port, with a random ID, which
+/// responses must match; [`randomize_case`](Self::randomize_case) adds the case of the name.
+#[derive(Debug, Clone)]
+pub struct StubResolver {
+    nameservers: Vec<SocketAddr>,
+    attempt_timeout: Duration,
+    attempts: usize,
+    rotate: bool,
+    race: bool,
+    stats: Arc<ServerStats>,
+    hosts: HostsSource,
+    search: Vec<String>,
+    ndots: u8,
+    max_search_domains: usize,
+    llmnr: bool,
+    dnssec: bool,
+    client_subnet: Option<ClientSubnet>,
+    randomize_case: bool,
+}
+
+/// How the nameservers answered, shared by a resolver and its clones.
+#[derive(Debug)]
+struct ServerStats {
+    /// Smoothed response time of each nameserver, a failed attempt counting as a whole
+    /// attempt timeout, `None` until it is first tried.
+    rtts: Mutex<Vec<Option<Duration>>>,
+    /// The nameserver the next lookup starts with, with `rotate`.
+    next: AtomicUsize,
+}
+
+#[derive(Debug, Clone)]
+enum HostsSource {
+    System,
+    Custom(Arc<Hosts>),
+    Disabled,
+}
+
+impl StubResolver {
+    /// Resolver querying `nameservers` in order.
+    pub fn new<I: IntoIterator<Item = SocketAddr>>(nameservers: I) -> Self {
+        let nameservers: Vec<_> = nameservers.into_iter().collect();
+        Self {
+            stats: Arc::new(ServerStats {
+                rtts: Mutex::new(vec![None; nameservers.len()]),
+                next: AtomicUsize::new(0),
+            }),
+            nameservers,
+            attempt_timeout: Duration::from_secs(2),
+            attempts: 2,
+            rotate: false,
+            race: false,
+            hosts: HostsSource::System,
+            search: Vec::new(),
+            ndots: 1,
+            max_search_domains: usize::MAX,
+            llmnr: cfg!(windows),
+            dnssec: false,
+            client_subnet: None,
+            randomize_case: false,
+        }
+    }
+
+    /// Resolver querying the nameservers of `conf`, waiting its `timeout` for each, making its
+    /// `attempts` and rotating with its `rotate`, and expanding names with its search domains.
+    pub fn from_resolv_conf(conf: &ResolvConf) -> Self {
+        Self::new(conf.nameservers.iter().copied())
+            .attempt_timeout(conf.timeout)
+            .attempts(conf.attempts)
+            .rotate(conf.rotate)
+            .search(conf.search.iter().cloned())
+            .ndots(conf.ndots)
+    }
+
+    /// Sets how long to wait for a single nameserver, 2 seconds by default.
+    pub fn attempt_timeout(mut self, timeout: Duration) -> Self {
+        self.attempt_timeout = timeout;
+        self
+    }
+
+    /// Sets how many passes over the nameservers are made before giving up, 2 by default and
+    /// at least 1.
+    pub fn attempts(mut self, attempts: u8) -> Self {
+        self.attempts = attempts.max(1).into();
+        self
+    }
+
+    /// Whether lookups start with each nameserver in turn, spreading the load, rather than with
+    /// the one that has answered fastest so far, `false` by default.
+    pub fn rotate(mut self, enabled: bool) -> Self {
+        self.rotate = enabled;
+        self
+    }
+
+    /// Whether queries are sent to every nameserver at once, the first answer being taken,
+    /// `false` by default.
+    ///
+    /// That trades traffic for latency when some of the nameservers are slow or unreliable,
+    /// and leaves [`rotate`](Self::rotate) without effect. Each nameserver is queried from a
+    /// thread of its own, those still waiting once a lookup is answered ending with their
+    /// attempt.
+    pub fn race(mut self, enabled: bool) -> Self {
+        self.race = enabled;
+        self
+    }
+
+    /// Sets the host table consulted before querying the nameservers, `None` to disable it.
+    ///
+    /// By default the system hosts file is used, read again whenever it changes.
+    pub fn hosts(mut self, hosts: Option<Hosts>) -> Self {
+        self.hosts = match hosts {
+            Some(hosts) => HostsSource::Custom(Arc::new(hosts)),
+            None => HostsSource::Disabled,
+        };
+        self
+    }
+
+    /// Sets the domains appended to names with fewer than [`ndots`](Self::ndots) dots.
+    pub fn search<I, S>(mut self, domains: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.search = domains.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Sets how many dots a name needs to be tried as is before the search domains, 1 by default.
+    pub fn ndots(mut self, ndots: u8) -> Self {
+        self.ndots = ndots;
+        self
+    }
+
+    /// Caps how many search domains are tried, `0` disables the expansion.
+    pub fn max_search_domains(mut self, max: usize) -> Self {
+        self.max_search_domains = max;
+        self
+    }
+
+    /// Whether single label names not found by the nameservers are queried with LLMNR, as the
+    /// Windows resolver does, enabled by default on Windows only.
+    ///
+    /// The multicast exchange takes whatever is left of the caller's timeout.
+    pub fn llmnr(mut self, enabled: bool) -> Self {
+        self.llmnr = enabled;
+        self
+    }
+
+    /// Whether answers must be authenticated with DNSSEC, `false` by default.
+    ///
+    /// Queries then ask for DNSSEC records and for the `AD` flag, which a validating nameserver
+    /// sets on the answers it could authenticate, and answers without it are rejected with
+    /// [`DnssecError::Unauthenticated`]. Failures the nameserver reports as failed validation
+    /// are [`DnssecError::Bogus`], both being [`InvalidData`](io::ErrorKind::InvalidData)
+    /// errors. Validation is left to the nameservers, which must be trusted, as is the path
+    /// to them, typically by running on the host itself.
+    pub fn dnssec(mut self, required: bool) -> Self {
+        self.dnssec = required;
+        self
+    }
+
+    /// Sets the EDNS Client Subnet option sent with every query, none by default.
+    ///
+    /// Nameservers may then answer with addresses suited to the subnet rather than to their own
+    /// location. [`ClientSubnet::anonymous`] asks them not to reveal any part of the client
+    /// address when querying others on its behalf.
+    pub fn client_subnet(mut self, subnet: Option<ClientSubnet>) -> Self {
+        self.client_subnet = subnet;
+        self
+    }
+
+    /// Whether names are sent with the case of their letters randomized, `false` by default.
+    ///
+    /// Responses must then repeat the name in the same case, which makes forging them harder;
+    /// nameservers that do not preserve the case of the question can no longer be used.
+    pub fn randomize_case(mut self, enabled: bool) -> Self {
+        self.randomize_case = enabled;
+        self
+    }
+
+    /// Resolves `host` with `subnet` as the EDNS Client Subnet option in place of the one set
+    /// with [`client_subnet`](Self::client_subnet), giving up once `timeout` has elapsed.
+    pub fn resolve_with_client_subnet(
+        &self,
+        host: &str,
+        port: u16,
+        subnet: Option<ClientSubnet>,
+        timeout: Duration,
+    ) -> io::Result<std::vec::IntoIter<SocketAddr>> {
+        self.clone()
+            .client_subnet(subnet)
+            .resolve(host, port, timeout)
+    }
+
+    /// Addresses of `host` with the TTLs of the records they came from, unknown for the hosts
+    /// file and LLMNR, asking the nameservers for the record types of `qtypes`.
+    fn lookup(
+        &self,
+        host: &str,
+        qtypes: &[u16],
+        timeout: Duration,
+    ) -> io::Result<Vec<(IpAddr, Option<Duration>)>> {
+        let listed = match self.hosts {
+            HostsSource::System => hosts::system().lookup(host).to_vec(),
+            HostsSource::Custom(ref hosts) => hosts.lookup(host).to_vec(),
+            HostsSource::Disabled => Vec::new(),
+        };
+        if !listed.is_empty() {
+            return Ok(listed.into_iter().map(|ip| (ip, None)).collect());
+        }
+
+        if self.nameservers.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "no nameservers configured",
+            ));
+        }
+        encode_name(host)?;
+        let deadline = crate::deadline_after(timeout);
+
+        let mut nodata = false;
+        let mut nxdomain = false;
+        let mut last_err = None;
+        let candidates = match hostaliases::resolve(host) {
+            Some(alias) => vec![alias],
+            None => self.candidates(host),
+        };
+        for candidate in candidates {
+            // a search domain may make the name too long
+            let Ok(name) = encode_name(&candidate) else {
+                continue;
+            };
+            match self.query(&name, qtypes, deadline) {
+                Ok(Outcome::Found(addrs)) => return Ok(addrs),
+                Ok(Outcome::NoData) => nodata = true,
+                Ok(Outcome::NXDomain) => nxdomain = true,
+                Err(err) if err.kind() == io::ErrorKind::TimedOut => return Err(err),
+                Err(err) => last_err = Some(err),
+            }
+        }
+
+        if self.llmnr && llmnr::applies(host) {
+            match llmnr::lookup(host, random_id(), deadline) {
+                Ok(addrs) => return Ok(addrs.into_iter().map(|ip| (ip, None)).collect()),
+                Err(err) if err.kind() == io::ErrorKind::TimedOut => (),
+                Err(err) => last_err = Some(err),
+            }
+        }
+
+        if nodata {
+            Err(ResolveError::NoAddresses.into())
+        } else if nxdomain {
+            Err(ResolveError::HostNotFound.into())
+        } else {
+            Err(last_err.unwrap_or_else(|| io::ErrorKind::TimedOut.into()))
+        }
+    }
+
+    /// Names to try for `host`, in order, as `res_search` does.
+    fn candidates(&self, host: &str) -> Vec<String> {
+        if host.ends_with('.') {
+            return vec![host.to_owned()];
+        }
+
+        let searched = self
+            .search
+            .iter()
+            .take(self.max_search_domains)
+            .map(|domain| format!("{host}.{}", domain.trim_end_matches('.')));
+        if host.matches('.').count() >= self.ndots as usize {
+            std::iter::once(host.to_owned()).chain(searched).collect()
+        } else {
+            searched.chain(std::iter::once(host.to_owned())).collect()
+        }
+    }
+
+    /// Queries the nameservers for the addresses of the encoded `name`, with a query for each
+    /// of `qtypes`.
+    fn query(&self, name: &[u8], qtypes: &[u16], deadline: Instant) -> io::Result<Outcome> {
+        let mut queries: Vec<_> = qtypes.iter().map(|&qtype| Query::new(qtype)).collect();
+        let last_err = self.ask(name, &mut queries, deadline);
+
+        let mut addrs = Vec::new();
+        let mut nxdomain = false;
+        let mut answered = true;
+        for query in queries {
+            match query.answer {
+                Some(response) if response.rcode == Rcode::NoError => addrs.extend(
+                    response
+                        .addrs()
+                        .into_iter()
+                        .map(|(ip, ttl)| (ip, Some(Duration::from_secs(ttl.into())))),
+                ),
+                Some(_) => nxdomain = true,
+                None => answered = false,
+            }
+        }
+
+        if !addrs.is_empty() {
+            Ok(Outcome::Found(addrs))
+        } else if nxdomain {
+            Ok(Outcome::NXDomain)
+        } else if answered {
+            Ok(Outcome::NoData)
+        } else if Instant::now() >= deadline {
+            Err(io::ErrorKind::TimedOut.into())
+        } else {
+            Err(last_err.unwrap_or_else(|| io::ErrorKind::TimedOut.into()))
+        }
+    }
+
+    /// Sends `queries` for the encoded `name` to the nameservers in turn until all are
+    /// answered or `deadline` passes, returning the last error met.
+    fn ask(&self, name: &[u8], queries: &mut [Query], deadline: Instant) -> Option<io::Error> {
+        if self.race {
+            return self.ask_all(name, queries, deadline);
+        }
+        let order = self.server_order();
+        let mut last_err = None;
+        'attempts: for _ in 0..self.attempts {
+            for &index in &order {
+                let now = Instant::now();
+                if now >= deadline {
+                    break 'attempts;
+                }
+                let attempt_deadline = deadline.min(crate::deadline_after(self.attempt_timeout));
+                let result =
+                    self.exchange(self.nameservers[index], name, queries, attempt_deadline);
+                let answered = queries.iter().all(|q| q.answer.is_some());
+                self.record_rtt(
+                    index,
+                    match result {
+                        Ok(()) if answered => now.elapsed(),
+                        _ => self.attempt_timeout,
+                    },
+                );
+                if let Err(err) = result {
+                    last_err = Some(err);
+                }
+                if answered {
+                    break 'attempts;
+                }
+            }
+        }
+        last_err
+    }
+
+    /// [`ask`](Self::ask), sending the unanswered `queries` to all the nameservers at once and
+    /// taking the first answer to each.
+    fn ask_all(&self, name: &[u8], queries: &mut [Query], deadline: Instant) -> Option<io::Error> {
+        // shared by the exchanges, each on a thread of its own
+        let resolver = Arc::new(self.clone());
+        let name: Arc<[u8]> = name.into();
+        let unanswered = |queries: &[Query]| -> Vec<_> {
+            queries
+                .iter()
+                .filter(|q| q.answer.is_none())
+                .map(|q| Query::with_class(q.qtype, q.class))
+                .collect()
+        };
+        let mut last_err = None;
+        for _ in 0..self.attempts {
+            let now = Instant::now();
+            if now >= deadline {
+                break;
+            }
+            let attempt_deadline = deadline.min(crate::deadline_after(self.attempt_timeout));
+            let (tx, rx) = mpsc::channel();
+            for index in 0..self.nameservers.len() {
+                let attempt = {
+                    let (resolver, name, tx) = (resolver.clone(), name.clone(), tx.clone());
+                    let pending = unanswered(queries);
+                    move || {
+                        _ = tx.send(resolver.exchange_with(
+                            index,
+                            &name,
+                            pending,
+                            now,
+                            attempt_deadline,
+                        ))
+                    }
+                };
+                // without threads, the nameservers are asked one after the other
+                if std::thread::Builder::new().spawn(attempt).is_err() {
+                    let pending = unanswered(queries);
+                    _ = tx.send(self.exchange_with(index, &name, pending, now, attempt_deadline));
+                }
+            }
+            drop(tx);
+
+            while queries.iter().any(|q| q.answer.is_none()) {
+                let wait = attempt_deadline.saturating_duration_since(Instant::now());
+                // disconnected once every exchange is over
+                let Ok((result, answered)) = rx.recv_timeout(wait) else {
+                    break;
+                };
+                if let Err(err) = result {
+                    last_err = Some(err);
+                }
+                for answer in answered {
+                    let Some(query) = queries.iter_mut().find(|q| {
+                        q.answer.is_none() && q.qtype == answer.qtype && q.class == answer.class
+                    }) else {
+                        continue;
+                    };
+                    query.answer = answer.answer;
+                }
+            }
+            if queries.iter().all(|q| q.answer.is_some()) {
+                break;
+            }
+        }
+        last_err
+    }
+
+    /// [`exchange`](Self::exchange) of `queries` with the nameserver at `index`, recording the
+    /// time since `start` it took to answer them all.
+    fn exchange_with(
+        &self,
+        index: usize,
+        name: &[u8],
+        mut queries: Vec<Query>,
+        start: Instant,
+        deadline: Instant,
+    ) -> (io::Result<()>, Vec<Query>) {
+        let result = self.exchange(self.nameservers[index], name, &mut queries, deadline);
+        let answered = queries.iter().all(|q| q.answer.is_some());
+        self.record_rtt(
+            index,
+            match result {
+                Ok(()) if answered => start.elapsed(),
+                _ => self.attempt_timeout,
+            },
+        );
+        (result, queries)
+    }
+
+    /// Indices of the nameservers in the order a lookup tries them, starting with the next in
+    /// turn with [`rotate`](Self::rotate) or else the fastest so far, the others following.
+    fn server_order(&self) -> Vec<usize> {
+        let count = self.nameservers.len();
+        if count == 0 {
+            return Vec::new();
+        }
+        let first = if self.rotate {
+            self.stats.next.fetch_add(1, Ordering::Relaxed) % count
+        } else {
+            let rtts = self.stats.rtts.lock().unwrap_or_else(|e| e.into_inner());
+            rtts.iter()
+                .enumerate()
+                .filter_map(|(index, rtt)| Some((rtt.as_ref()?, index)))
+                .min()
+                .map_or(0, |(_, index)| index)
+        };
+        (0..count).map(|i| (first + i) % count).collect()
+    }
+
+    /// Folds the time the nameserver at `index` took to answer into its smoothed response time.
+    fn record_rtt(&self, index: usize, sample: Duration) {
+        let mut rtts = self.stats.rtts.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(rtt) = rtts.get_mut(index) {
+            *rtt = Some(match *rtt {
+                Some(rtt) => rtt.saturating_mul(7).saturating_add(sample) / 8,
+                None => sample,
+            });
+        }
+    }
+
+    /// Queries the nameservers for the `qtype` records of `name`, as is, in `class`, returning
+    /// the `NoError` or `NXDomain` response.
+    fn query_records(
+        &self,
+        name: &str,
+        qtype: u16,
+        class: u16,
+        timeout: Duration,
+    ) -> io::Result<Response> {
+        if self.nameservers.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "no nameservers configured",
+            ));
+        }
+        let encoded = encode_name(name)?;
+        let deadline = crate::deadline_after(timeout);
+        let mut queries = [Query::with_class(qtype, class)];
+        let last_err = self.ask(&encoded, &mut queries, deadline);
+        let [Query { answer, .. }] = queries;
+        match answer {
+            Some(response) => Ok(response),
+            None if Instant::now() >= deadline => Err(io::ErrorKind::TimedOut.into()),
+            None => Err(last_err.unwrap_or_else(|| io::ErrorKind::TimedOut.into())),
+        }
+    }
+
+    /// All the names the PTR records of `ip` point to, giving up once `timeout` has elapsed.
+    ///
+    /// Unlike [`ToHostnameTimeout`](crate::ToHostnameTimeout), which gives a single name,
+    /// every record is returned, in the order the nameserver sent them. An address without PTR
+    /// records yields an empty list; one whose reverse name does not exist is an error.
+    pub fn lookup_ptr(&self, ip: IpAddr, timeout: Duration) -> io::Result<Vec<String>> {
+        let name = dns::reverse_name(ip);
+        let response = self.query_records(&name, TYPE_PTR, CLASS_IN, timeout)?;
+        if response.rcode != Rcode::NoError {
+            return Err(response.rcode.into());
+        }
+        Ok(response.answers(TYPE_PTR, |record, msg| {
+            Some(dns::name_to_string(&record.rdata_name(msg)?))
+        }))
+    }
+
+    /// The TXT records of `name`, each with its strings joined, giving up once `timeout` has
+    /// elapsed.
+    ///
+    /// `name` is queried as is, without the search domains. A name without TXT records yields an
+    /// empty list; one that does not exist is an error.
+    pub fn lookup_txt(&self, name: &str, timeout: Duration) -> io::Result<Vec<Vec<u8>>> {
+        let response = self.query_records(name, TYPE_TXT, CLASS_IN, timeout)?;
+        if response.rcode != Rcode::NoError {
+            return Err(response.rcode.into());
+        }
+        Ok(response.answers(TYPE_TXT, |record, _| dns::txt_data(record.rdata)))
+    }
+
+    /// The HTTPS records of `host`, by priority, giving up once `timeout` has elapsed.
+    ///
+    /// `host` is queried as is, without the search domains; for an origin on a port other than
+    /// 443 that is `_<port>._https.<host>`. A name without HTTPS records yields an empty list;
+    /// one that does not exist is an error.
+    pub fn lookup_https_records(
+        &self,
+        host: &str,
+        timeout: Duration,
+    ) -> io::Result<Vec<HttpsRecord>> {
+        let response = self.query_records(host, TYPE_HTTPS, CLASS_IN, timeout)?;
+        if response.rcode != Rcode::NoError {
+            return Err(response.rcode.into());
+        }
+        let mut records = response.answers(TYPE_HTTPS, HttpsRecord::parse);
+        records.sort_by_key(|record| record.priority);
+        Ok(records)
+    }
+
+    /// Queries the nameservers for the `rtype` records of `name` in `rclass`, giving up once
+    /// `timeout` has elapsed, for record types without a dedicated lookup.
+    ///
+    /// `name` is queried as is, without the search domains. The response is returned as long as
+    /// it is `NoError` or `NXDomain`, other response codes being errors.
+    pub fn raw_query(
+        &self,
+        name: &str,
+        rtype: u16,
+        rclass: u16,
+        timeout: Duration,
+    ) -> io::Result<DnsMessage> {
+        let response = self.query_records(name, rtype, rclass, timeout)?;
+        DnsMessage::parse(response.msg)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "invalid DNS response"))
+    }
+
+    /// The CNAME chain of `name` and the addresses it ends at, giving up once `timeout` has
+    /// elapsed.
+    ///
+    /// `name` is queried as is, without the search domains or the hosts file. When the
+    /// nameserver stops partway through the chain, its last name is queried in turn. A chain
+    /// ending at a name without addresses is returned with none.
+    pub fn lookup_cname_chain(&self, name: &str, timeout: Duration) -> io::Result<CnameChain> {
+        if self.nameservers.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "no nameservers configured",
+            ));
+        }
+        let deadline = crate::deadline_after(timeout);
+        let mut chain = vec![encode_name(name)?];
+        let mut addrs = Vec::new();
+        for _ in 0..MAX_CNAME_QUERIES {
+            let mut queries = [Query::new(TYPE_AAAA), Query::new(TYPE_A)];
+            let last_err = self.ask(chain.last().unwrap(), &mut queries, deadline);
+            let responses: Vec<_> = queries.into_iter().filter_map(|q| q.answer).collect();
+            if responses.is_empty() {
+                return Err(if Instant::now() >= deadline {
+                    io::ErrorKind::TimedOut.into()
+                } else {
+                    last_err.unwrap_or_else(|| io::ErrorKind::TimedOut.into())
+                });
+            }
+
+            let aliases: Vec<_> = responses
+                .iter()
+                .flat_map(|response| {
+                    response.answers(TYPE_CNAME, |record, msg| {
+                        Some((record.name.clone(), record.rdata_name(msg)?))
+                    })
+                })
+                .collect();
+            let queried = chain.len();
+            // every alias is followed at most once, which also stops loops
+            while let Some((_, target)) = aliases
+                .iter()
+                .filter(|(owner, _)| names_eq(owner, chain.last().unwrap()))
+                .find(|(_, target)| !chain.iter().any(|name| names_eq(name, target)))
+            {
+                chain.push(target.clone());
+            }
+            if chain.len() == 1 && responses.iter().all(|r| r.rcode == Rcode::NXDomain) {
+                return Err(ResolveError::HostNotFound.into());
+            }
+
+            let canonical = chain.last().unwrap();
+            addrs.extend(responses.iter().flat_map(|response| {
+                response.answers(response.qtype, |record, _| {
+                    names_eq(&record.name, canonical).then(|| record.ip())?
+                })
+            }));
+            if !addrs.is_empty() || chain.len() == queried {
+                break;
+            }
+        }
+
+        Ok(CnameChain {
+            names: chain.iter().map(|name| dns::name_to_string(name)).collect(),
+            addrs,
+        })
+    }
+}
+
+/// The address record types to ask for with `hints`, IPv6 with IPv4-mapped addresses needing
+/// both.
+fn address_qtypes(hints: &Hints) -> &'static [u16] {
+    match hints.family {
+        Family::Ipv4 => &[TYPE_A],
+        Family::Ipv6 if !hints.flags.contains(HintFlags::V4MAPPED) => &[TYPE_AAAA],
+        _ => &[TYPE_AAAA, TYPE_A],
+    }
+}
+
+/// Result of looking up a single name.
+enum Outcome {
+    Found(Vec<(IpAddr, Option<Duration>)>),
+    NoData,
+    NXDomain,
+}
+
+impl ResolverBackend for StubResolver {
+    fn resolve(
+        &self,
+        host: &str,
+        port: u16,
+        timeout: Duration,
+    ) -> io::Result<std::vec::IntoIter<SocketAddr>> {
+        let addrs: Vec<_> = self
+            .lookup(host, &[TYPE_AAAA, TYPE_A], timeout)?
+            .into_iter()
+            .map(|(ip, _)| SocketAddr::new(ip, port))
+            .collect();
+        Ok(addrs.into_iter())
+    }
+
+    fn resolve_with_hints(
+        &self,
+        host: &str,
+        port: u16,
+        hints: &Hints,
+        timeout: Duration,
+    ) -> io::Result<std::vec::IntoIter<SocketAddr>> {
+        let addrs: Vec<_> = self
+            .resolve_with_ttl(host, port, hints, timeout)?
+            .into_iter()
+            .map(|resolved| resolved.addr)
+            .collect();
+        Ok(addrs.into_iter())
+    }
+
+    fn resolve_with_ttl(
+        &self,
+        host: &str,
+        port: u16,
+        hints: &Hints,
+        timeout: Duration,
+    ) -> io::Result<Vec<ResolvedAddr>> {
+        if hints.flags.contains(HintFlags::NUMERICHOST) {
+            return Err(hints::not_numeric());
+        }
+        let resolved = self
+            .lookup(host, address_qtypes(hints), timeout)?
+            .into_iter()
+            .map(|(ip, ttl)| ResolvedAddr {
+                addr: SocketAddr::new(ip, port),
+                ttl,
+            })
+            .collect();
+        let resolved = hints.filter_resolved(resolved);
+        if resolved.is_empty() {
+            return Err(ResolveError::NoAddresses.into());
+        }
+        Ok(resolved)
+    }
+}
+
+struct Query {
+    qtype: u16,
+    class: u16,
+    id: u16,
+    inflight: bool,
+    /// Final answer, either `NoError` or `NXDomain`.
+    answer: Option<Response>,
+}
+
+impl Query {
+    fn new(qtype: u16) -> Self {
+        Self::with_class(qtype, CLASS_IN)
+    }
+
+    fn with_class(qtype: u16, class: u16) -> Self {
+        Self {
+            qtype,
+            class,
+            id: 0,
+            inflight: false,
+            answer: None,
+        }
+    }
+}
+
+struct Response {
+    id: u16,
+    qtype: u16,
+    class: u16,
+    truncated: bool,
+    rcode: Rcode,
+    /// Whether the `AD` flag is set.
+    authenticated: bool,
+    extended_error: Option<u16>,
+    msg: Vec<u8>,
+}
+
+impl Response {
+    /// Whether the nameserver failed to validate the answer, as told by an extended DNS error
+    /// from `DNSSEC Bogus` to `NSEC Missing`.
+    fn bogus(&self) -> bool {
+        self.rcode == Rcode::ServFail && matches!(self.extended_error, Some(6..=12))
+    }
+
+    /// Reads the `rtype` answers of class IN with `read`, which is also given the message, in
+    /// order and skipping those it can not read.
+    fn answers<T>(&self, rtype: u16, mut read: impl FnMut(&Record, &[u8]) -> Option<T>) -> Vec<T> {
+        let Some(message) = Message::parse(&self.msg) else {
+            return Vec::new();
+        };
+        message
+            .answers
+            .iter()
+            .filter(|record| record.rtype == rtype && record.class == CLASS_IN)
+            .filter_map(|record| read(record, &self.msg))
+            .collect()
+    }
+
+    /// Addresses answered, with their TTLs in seconds.
+    fn addrs(&self) -> Vec<(IpAddr, u32)> {
+        self.answers(self.qtype, |record, _| {
+            // RFC 2181: a TTL with the top bit set is to be taken as zero
+            let ttl = if record.ttl > i32::MAX as u32 {
+                0
+            } else {
+                record.ttl
+            };
+            Some((record.ip()?, ttl))
+        })
+    }
+}
+
+/// Exchanges with a single nameserver.
+impl StubResolver {
+    /// Builds a query for the encoded `name`, asking for DNSSEC with [`dnssec`](Self::dnssec)
+    /// and carrying the [`client_subnet`](Self::client_subnet).
+    fn build_query(&self, id: u16, name: &[u8], qtype: u16, class: u16) -> Vec<u8> {
+        if !self.dnssec && self.client_subnet.is_none() {
+            return build_query(id, FLAG_RD, name, qtype, class);
+        }
+        let flags = if self.dnssec {
+            FLAG_RD | FLAG_AD
+        } else {
+            FLAG_RD
+        };
+        let mut query = build_query(id, flags, name, qtype, class);
+        let options = self
+            .client_subnet
+            .map(|subnet| subnet.option())
+            .unwrap_or_default();
+        push_opt(&mut query, UDP_SIZE, self.dnssec, &options);
+        query
+    }
+
+    /// Sends the unanswered `queries` to `server` over UDP and collects the answers.
+    fn exchange(
+        &self,
+        server: SocketAddr,
+        name: &[u8],
+        queries: &mut [Query],
+        deadline: Instant,
+    ) -> io::Result<()> {
+        let local: IpAddr = match server {
+            SocketAddr::V4(_) => Ipv4Addr::UNSPECIFIED.into(),
+            SocketAddr::V6(_) => Ipv6Addr::UNSPECIFIED.into(),
+        };
+        let socket = bind_random_port(local)?;
+        socket.connect(server)?;
+
+        let mixed;
+        let name = if self.randomize_case {
+            mixed = mix_case(name);
+            &mixed[..]
+        } else {
+            name
+        };
+
+        for query in queries.iter_mut().filter(|q| q.answer.is_none()) {
+            query.id = random_id();
+            socket.send(&self.build_query(query.id, name, query.qtype, query.class))?;
+            query.inflight = true;
+        }
+
+        let mut result = Ok(());
+        let mut buf = [0u8; UDP_SIZE as usize];
+        while queries.iter().any(|q| q.inflight) {
+            socket.set_read_timeout(Some(remaining(deadline)?))?;
+            let len = socket.recv(&mut buf).map_err(timed_out)?;
+            let Some(response) = self.parse_response(&buf[..len], name) else {
+                continue;
+            };
+            let Some(query) = queries.iter_mut().find(|q| {
+                q.inflight
+                    && q.id == response.id
+                    && q.qtype == response.qtype
+                    && q.class == response.class
+            }) else {
+                continue;
+            };
+            query.inflight = false;
+
+            let response = if response.truncated {
+                match self.query_tcp(server, name, query, deadline) {
+                    Ok(response) => response,
+                    Err(err) => {
+                        result = Err(err);
+                        continue;
+                    }
+                }
+            } else {
+                response
+            };
+            match response.rcode {
+                _ if self.dnssec && response.bogus() => result = Err(DnssecError::Bogus.into()),
+                Rcode::NoError | Rcode::NXDomain if self.dnssec && !response.authenticated => {
+                    result = Err(DnssecError::Unauthenticated.into())
+                }
+                Rcode::NoError | Rcode::NXDomain => query.answer = Some(response),
+                rcode => result = Err(rcode.into()),
+            }
+        }
+        result
+    }
+
+    fn query_tcp(
+        &self,
+        server: SocketAddr,
+        name: &[u8],
+        query: &Query,
+        deadline: Instant,
+    ) -> io::Result<Response> {
+        let (qtype, class) = (query.qtype, query.class);
+        let mut stream = TcpStream::connect_timeout(&server, remaining(deadline)?)?;
+
+        let id = random_id();
+        let query = self.build_query(id, name, qtype, class);
+        let mut framed = Vec::with_capacity(query.len() + 2);
+        framed.extend_from_slice(&(query.len() as u16).to_be_bytes());
+        framed.extend_from_slice(&query);
+        stream.set_write_timeout(Some(remaining(deadline)?))?;
+        stream.write_all(&framed).map_err(timed_out)?;
+
+        let mut len = [0u8; 2];
+        stream.set_read_timeout(Some(remaining(deadline)?))?;
+        stream.read_exact(&mut len).map_err(timed_out)?;
+        let mut msg = vec![0u8; u16::from_be_bytes(len) as usize];
+        stream.set_read_timeout(Some(remaining(deadline)?))?;
+        stream.read_exact(&mut msg).map_err(timed_out)?;
+
+        self.parse_response(&msg, name)
+            .filter(|r| r.id == id && r.qtype == qtype && r.class == class)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "invalid DNS response"))
+    }
+
+    /// Parses a response to a query for `name`, returning `None` for anything else, including a
+    /// name in another case with [`randomize_case`](Self::randomize_case).
+    fn parse_response(&self, msg: &[u8], name: &[u8]) -> Option<Response> {
+        let message = Message::parse(msg)?;
+        let [ref question] = message.questions[..] else {
+            return None;
+        };
+        let same_name = if self.randomize_case {
+            question.name == name
+        } else {
+            names_eq(&question.name, name)
+        };
+        if !message.is_response() || !same_name {
+            return None;
+        }
+        Some(Response {
+            id: message.id,
+            qtype: question.qtype,
+            class: question.class,
+            truncated: message.truncated(),
+            rcode: message.rcode(),
+            authenticated: message.authenticated(),
+            extended_error: message.extended_error(),
+            msg: msg.to_vec(),
+        })
+    }
+}
+
+fn remaining(deadline: Instant) -> io::Result<Duration> {
+    deadline
+        .checked_duration_since(Instant::now())
+        .filter(|d| !d.is_zero())
+        .ok_or_else(|| io::ErrorKind::TimedOut.into())
+}
+
+/// Socket timeouts are reported as `WouldBlock` on Unix.
+fn timed_out(err: io::Error) -> io::Error {
+    match err.kind() {
+        io::ErrorKind::WouldBlock => io::ErrorKind::TimedOut.into(),
+        _ => err,
+    }
+}
+
+fn random_id() -> u16 {
+    random() as u16
+}
+
+/// Random bits from the randomly keyed hasher of the standard library.
+pub(crate) fn random() -> u64 {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u64(COUNTER.fetch_add(1, Ordering::Relaxed));
+    hasher.finish()
+}
+
+/// Binds a UDP socket to `local` on a random unprivileged port, leaving the choice to the
+/// system when the ports tried are taken.
+fn bind_random_port(local: IpAddr) -> io::Result<UdpSocket> {
+    for _ in 0..8 {
+        let port = 1024 + (random() % (65536 - 1024)) as u16;
+        if let Ok(socket) = UdpSocket::bind((local, port)) {
+            return Ok(socket);
+        }
+    }
+    UdpSocket::bind((local, 0))
+}
+
+/// The encoded `name` with each letter randomly in upper or lower case, length bytes never
+/// being letters.
+fn mix_case(name: &[u8]) -> Vec<u8> {
+    let mut bits = 0;
+    name.iter()
+        .enumerate()
+        .map(|(i, &byte)| {
+            if i % 64 == 0 {
+                bits = random();
+            }
+            match bits >> (i % 64) & 1 {
+                0 => byte.to_ascii_lowercase(),
+                _ => byte.to_ascii_uppercase(),
+            }
+        })
+        .collect()
+}
+
+/// The client subnet a [`StubResolver`] sends in the EDNS Client Subnet option, see
+/// [`StubResolver::client_subnet`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ClientSubnet {
+    addr: IpAddr,
+    prefix: u8,
+}
+
+impl ClientSubnet {
+    /// The subnet of the first `prefix` bits of `addr`, at most the length of the address.
+    pub fn new(addr: IpAddr, prefix: u8) -> Self {
+        let bits = match addr {
+            IpAddr::V4(_) => 32,
+            IpAddr::V6(_) => 128,
+        };
+        let prefix = prefix.min(bits);
+        let addr = match addr {
+            IpAddr::V4(ip) => IpAddr::V4(
+                (u32::from(ip) & u32::MAX.checked_shl(32 - prefix as u32).unwrap_or(0)).into(),
+            ),
+            IpAddr::V6(ip) => IpAddr::V6(
+                (u128::from(ip) & u128::MAX.checked_shl(128 - prefix as u32).unwrap_or(0)).into(),
+            ),
+        };
+        Self { addr, prefix }
+    }
+
+    /// The empty `/0` subnet, asking nameservers not to send any part of the client address on.
+    pub const fn anonymous() -> Self {
+        Self {
+            addr: IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            prefix: 0,
+        }
+    }
+
+    /// The address of the subnet, its bits past the prefix cleared.
+    pub fn addr(&self) -> IpAddr {
+        self.addr
+    }
+
+    pub fn prefix(&self) -> u8 {
+        self.prefix
+    }
+
+    /// The encoded option, the address cut to the bytes the prefix covers.
+    fn option(&self) -> Vec<u8> {
+        let (family, octets) = match self.addr {
+            IpAddr::V4(ip) => (1u16, ip.octets().to_vec()),
+            IpAddr::V6(ip) => (2, ip.octets().to_vec()),
+        };
+        let octets = &octets[..(self.prefix as usize).div_ceil(8)];
+        let mut option = Vec::with_capacity(8 + octets.len());
+        option.extend_from_slice(&OPT_ECS.to_be_bytes());
+        option.extend_from_slice(&(4 + octets.len() as u16).to_be_bytes());
+        option.extend_from_slice(&family.to_be_bytes());
+        option.extend_from_slice(&[self.prefix, 0]);
+        option.extend_from_slice(octets);
+        option
+    }
+}
+
+/// An answer rejected by a [`StubResolver`] requiring DNSSEC, see [`StubResolver::dnssec`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum DnssecError {
+    /// The nameserver did not authenticate the answer, the zone being unsigned or the
+    /// nameserver not validating.
+    Unauthenticated,
+    /// The nameserver found the answer to be forged or broken.
+    Bogus,
+}
+
+impl core::fmt::Display for DnssecError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Unauthenticated => f.write_str("answer not authenticated with DNSSEC"),
+            Self::Bogus => f.write_str("answer failed DNSSEC validation"),
+        }
+    }
+}
+
+impl std::error::Error for DnssecError {}
+
+impl From<DnssecError> for io::Error {
+    fn from(value: DnssecError) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, value)
+    }
+}
@@ -0,0 +1,114 @@
+use std::{
+    future::Future,
+    net::SocketAddr,
+    pin::Pin,
+    sync::{Arc, OnceLock},
+    task::{ready, Context, Poll},
+    thread,
+    time::Duration,
+};
+
+use crate::{
+    completion::Completion, orphan::Orphan, Hints, ResolvConf, ResolvedAddr, ResolverBackend,
+    StubResolver,
+};
+
+/// Timeout of lookups without one, the nameserver attempt timeouts still bound them.
+const NO_TIMEOUT: Duration = Duration::from_secs(u32::MAX as u64);
+
+/// The stub resolver configured from `/etc/resolv.conf`, read again for every lookup.
+fn resolver() -> StubResolver {
+    StubResolver::from_resolv_conf(&ResolvConf::load().unwrap_or_default())
+}
+
+/// State shared with the resolver thread, which holds a reference until it returns.
+struct Job {
+    completion: Completion<std::io::Result<std::vec::IntoIter<SocketAddr>>>,
+    orphan: OnceLock<Orphan>,
+}
+
+/// Lookup running the stub resolver on a helper thread, which returns by the lookup's deadline.
+pub(crate) struct Lookup {
+    job: Arc<Job>,
+    done: bool,
+}
+
+impl Future for Lookup {
+    type Output = std::io::Result<std::vec::IntoIter<SocketAddr>>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let res = ready!(self.job.completion.poll(cx));
+        self.done = true;
+        Poll::Ready(res)
+    }
+}
+
+impl Drop for Lookup {
+    fn drop(&mut self) {
+        if !self.done {
+            self.job.orphan.get_or_init(Orphan::new);
+        }
+    }
+}
+
+pub(crate) fn lookup(host: &str, port: u16, timeout: Option<Duration>) -> std::io::Result<Lookup> {
+    let job = Arc::new(Job {
+        completion: Completion::new(),
+        orphan: OnceLock::new(),
+    });
+    let host = host.to_owned();
+    let timeout = timeout.unwrap_or(NO_TIMEOUT);
+    thread::spawn({
+        let job = job.clone();
+        move || {
+            job.completion
+                .complete(resolver().resolve(&host, port, timeout))
+        }
+    });
+    Ok(Lookup { job, done: false })
+}
+
+/// The stub resolver always asks for both families, so there is nothing to split.
+pub(crate) fn lookup_split(
+    host: &str,
+    port: u16,
+    timeout: Option<Duration>,
+) -> std::io::Result<Vec<Lookup>> {
+    Ok(vec![lookup(host, port, timeout)?])
+}
+
+/// The platform resolver on musl, or with the `force-stub` feature: a [`StubResolver`] configured from `/etc/resolv.conf`, run on
+/// the calling thread and bounded by the timeout.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemResolver;
+
+impl ResolverBackend for SystemResolver {
+    fn resolve(
+        &self,
+        host: &str,
+        port: u16,
+        timeout: Duration,
+    ) -> std::io::Result<std::vec::IntoIter<SocketAddr>> {
+        resolver().resolve(host, port, timeout)
+    }
+
+    fn resolve_with_hints(
+        &self,
+        host: &str,
+        port: u16,
+        hints: &Hints,
+        timeout: Duration,
+    ) -> std::io::Result<std::vec::IntoIter<SocketAddr>> {
+        resolver().resolve_with_hints(host, port, hints, timeout)
+    }
+
+    fn resolve_with_ttl(
+        &self,
+        host: &str,
+        port: u16,
+        hints: &Hints,
+        timeout: Duration,
+    ) -> std::io::Result<Vec<ResolvedAddr>> {
+        resolver().resolve_with_ttl(host, port, hints, timeout)
+    }
+}
@@ -0,0 +1,193 @@
+use std::{
+    io::{self, BufRead, BufReader, Write},
+    net::{IpAddr, Ipv6Addr, SocketAddr, SocketAddrV6},
+    os::unix::net::UnixStream,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    dns::Rcode,
+    json::{self, Json},
+    sys::SystemResolver,
+    CanonicalAddrs, Hints, ResolveError, ResolverBackend,
+};
+
+/// The `io.systemd.Resolve` varlink socket.
+const SOCKET: &str = "/run/systemd/resolve/io.systemd.Resolve";
+
+/// Resolver asking `systemd-resolved` over its varlink interface.
+///
+/// Lookups go through `io.systemd.Resolve.ResolveHostname`, and are canceled by closing the
+/// connection once the caller's timeout elapses. When the socket is absent, because
+/// `systemd-resolved` is not running, [`SystemResolver`] is used instead.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemdResolver;
+
+impl ResolverBackend for SystemdResolver {
+    fn resolve(
+        &self,
+        host: &str,
+        port: u16,
+        timeout: Duration,
+    ) -> io::Result<std::vec::IntoIter<SocketAddr>> {
+        match query(host, port, timeout)? {
+            Some(answer) => Ok(answer.addrs.into_iter()),
+            None => SystemResolver.resolve(host, port, timeout),
+        }
+    }
+
+    fn resolve_canonical(
+        &self,
+        host: &str,
+        port: u16,
+        hints: &Hints,
+        timeout: Duration,
+    ) -> io::Result<CanonicalAddrs> {
+        match query(host, port, timeout)? {
+            Some(answer) => Ok(CanonicalAddrs {
+                addrs: hints.filter(answer.addrs.into_iter()).collect(),
+                ..answer
+            }),
+            None => SystemResolver.resolve_canonical(host, port, hints, timeout),
+        }
+    }
+}
+
+/// Resolves `host` with `systemd-resolved`, `None` when it is not running.
+fn query(host: &str, port: u16, timeout: Duration) -> io::Result<Option<CanonicalAddrs>> {
+    let deadline = crate::deadline_after(timeout);
+    let stream = match UnixStream::connect(SOCKET) {
+        Ok(stream) => stream,
+        Err(err)
+            if matches!(
+                err.kind(),
+                io::ErrorKind::NotFound | io::ErrorKind::ConnectionRefused
+            ) =>
+        {
+            return Ok(None);
+        }
+        Err(err) => return Err(err),
+    };
+
+    let reply = call(stream, host, deadline).map_err(|err| match err.kind() {
+        io::ErrorKind::WouldBlock => io::ErrorKind::TimedOut.into(),
+        _ => err,
+    })?;
+    parse_reply(&reply, host, port).map(Some)
+}
+
+/// Sends the `ResolveHostname` call and reads its NUL terminated reply.
+fn call(stream: UnixStream, host: &str, deadline: Instant) -> io::Result<Vec<u8>> {
+    let mut request =
+        String::from(r#"{"method":"io.systemd.Resolve.ResolveHostname","parameters":{"name":"#);
+    json::write_str(&mut request, host);
+    request.push_str("}}\0");
+
+    stream.set_write_timeout(Some(remaining(deadline)?))?;
+    (&stream).write_all(request.as_bytes())?;
+
+    let mut reader = BufReader::new(&stream);
+    let mut reply = Vec::new();
+    loop {
+        stream.set_read_timeout(Some(remaining(deadline)?))?;
+        let len = reader.read_until(0, &mut reply)?;
+        match reply.last() {
+            Some(0) => {
+                reply.pop();
+                return Ok(reply);
+            }
+            _ if len == 0 => return Err(io::ErrorKind::UnexpectedEof.into()),
+            _ => (),
+        }
+    }
+}
+
+fn remaining(deadline: Instant) -> io::Result<Duration> {
+    deadline
+        .checked_duration_since(Instant::now())
+        .filter(|d| !d.is_zero())
+        .ok_or_else(|| io::ErrorKind::TimedOut.into())
+}
+
+/// The canonical name and addresses of a `ResolveHostname` reply for `host`, link-local ones
+/// scoped to the interface they were found on.
+fn parse_reply(reply: &[u8], host: &str, port: u16) -> io::Result<CanonicalAddrs> {
+    let invalid = || io::Error::new(io::ErrorKind::InvalidData, "invalid varlink reply");
+
+    let reply = std::str::from_utf8(reply)
+        .ok()
+        .and_then(Json::parse)
+        .ok_or_else(invalid)?;
+    if let Some(error) = reply.get("error") {
+        let error = error.as_str().ok_or_else(invalid)?;
+        return Err(resolve_error(error, reply.get("parameters")));
+    }
+
+    let name = reply
+        .get("parameters")
+        .and_then(|params| params.get("name"))
+        .and_then(Json::as_str)
+        .unwrap_or(host)
+        .to_owned();
+    let addresses = reply
+        .get("parameters")
+        .and_then(|params| params.get("addresses"))
+        .and_then(Json::as_array)
+        .ok_or_else(invalid)?;
+    addresses
+        .iter()
+        .map(|entry| {
+            let bytes = entry
+                .get("address")
+                .and_then(Json::as_array)
+                .ok_or_else(invalid)?
+                .iter()
+                .map(|b| b.as_u64().and_then(|b| u8::try_from(b).ok()))
+                .collect::<Option<Vec<u8>>>()
+                .ok_or_else(invalid)?;
+            let ip = match <[u8; 16]>::try_from(bytes.as_slice()) {
+                Ok(v6) => IpAddr::V6(v6.into()),
+                Err(_) => IpAddr::V4(
+                    <[u8; 4]>::try_from(bytes.as_slice())
+                        .map_err(|_| invalid())?
+                        .into(),
+                ),
+            };
+            let ifindex = entry
+                .get("ifindex")
+                .and_then(Json::as_u64)
+                .and_then(|i| u32::try_from(i).ok())
+                .unwrap_or(0);
+            Ok(match ip {
+                IpAddr::V6(ip) if is_link_local(&ip) => {
+                    SocketAddr::V6(SocketAddrV6::new(ip, port, 0, ifindex))
+                }
+                ip => SocketAddr::new(ip, port),
+            })
+        })
+        .collect::<io::Result<_>>()
+        .map(|addrs| CanonicalAddrs { name, addrs })
+}
+
+/// Maps a `io.systemd.Resolve` error to an [`io::Error`].
+fn resolve_error(error: &str, params: Option<&Json>) -> io::Error {
+    match error.strip_prefix("io.systemd.Resolve.").unwrap_or(error) {
+        "NoSuchResourceRecord" => ResolveError::NoAddresses.into(),
+        "QueryTimedOut" | "MaxAttemptsReached" => io::ErrorKind::TimedOut.into(),
+        "DNSError" => {
+            match params
+                .and_then(|p| p.get("rcode"))
+                .and_then(Json::as_u64)
+                .and_then(|rcode| u8::try_from(rcode).ok())
+            {
+                Some(rcode) => Rcode(rcode).into(),
+                None => io::Error::other(error.to_owned()),
+            }
+        }
+        _ => io::Error::other(error.to_owned()),
+    }
+}
+
+fn is_link_local(ip: &Ipv6Addr) -> bool {
+    ip.segments()[0] & 0xffc0 == 0xfe80
+}
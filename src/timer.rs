@@ -0,0 +1,127 @@
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap},
+    future::Future,
+    pin::Pin,
+    sync::{Condvar, Mutex, OnceLock},
+    task::{Context, Poll, Waker},
+    thread,
+    time::Instant,
+};
+
+/// Deadlines waited on by a single lazily started background thread.
+struct Timer {
+    state: Mutex<State>,
+    cond: Condvar,
+}
+
+#[derive(Default)]
+struct State {
+    next_id: u64,
+    deadlines: BinaryHeap<Reverse<(Instant, u64)>>,
+    wakers: HashMap<u64, Waker>,
+}
+
+static TIMER: OnceLock<Option<&'static Timer>> = OnceLock::new();
+
+/// The timer, `None` where threads are not supported.
+fn timer() -> Option<&'static Timer> {
+    *TIMER.get_or_init(|| {
+        let timer: &'static Timer = Box::leak(Box::new(Timer {
+            state: Mutex::new(State::default()),
+            cond: Condvar::new(),
+        }));
+        thread::Builder::new()
+            .name("to_socket_addrs_timeout-timer".into())
+            .spawn(move || timer.run())
+            .ok()
+            .map(|_| timer)
+    })
+}
+
+impl Timer {
+    fn run(&self) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        loop {
+            let now = Instant::now();
+            while let Some(&Reverse((deadline, id))) = state.deadlines.peek() {
+                if deadline > now {
+                    break;
+                }
+                state.deadlines.pop();
+                if let Some(waker) = state.wakers.remove(&id) {
+                    waker.wake();
+                }
+            }
+
+            state = match state.deadlines.peek() {
+                Some(&Reverse((deadline, _))) => {
+                    self.cond
+                        .wait_timeout(state, deadline.saturating_duration_since(now))
+                        .unwrap_or_else(|e| e.into_inner())
+                        .0
+                }
+                None => self.cond.wait(state).unwrap_or_else(|e| e.into_inner()),
+            };
+        }
+    }
+}
+
+/// Future completing once `deadline` has passed.
+pub(crate) struct Sleep {
+    deadline: Instant,
+    id: Option<u64>,
+}
+
+impl Sleep {
+    pub fn until(deadline: Instant) -> Self {
+        Self { deadline, id: None }
+    }
+}
+
+impl Future for Sleep {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if Instant::now() >= self.deadline {
+            return Poll::Ready(());
+        }
+
+        let Some(timer) = timer() else {
+            // nothing can wake the task at the deadline, so have it polled again
+            cx.waker().wake_by_ref();
+            return Poll::Pending;
+        };
+        let mut state = timer.state.lock().unwrap_or_else(|e| e.into_inner());
+        match self.id {
+            Some(id) => {
+                // the timer thread drops the waker when the deadline fires
+                state.wakers.insert(id, cx.waker().clone());
+            }
+            None => {
+                let id = state.next_id;
+                state.next_id += 1;
+                let earliest = state
+                    .deadlines
+                    .peek()
+                    .is_none_or(|&Reverse((d, _))| self.deadline < d);
+                state.deadlines.push(Reverse((self.deadline, id)));
+                state.wakers.insert(id, cx.waker().clone());
+                self.id = Some(id);
+                if earliest {
+                    timer.cond.notify_one();
+                }
+            }
+        }
+        Poll::Pending
+    }
+}
+
+impl Drop for Sleep {
+    fn drop(&mut self) {
+        if let (Some(id), Some(Some(timer))) = (self.id, TIMER.get()) {
+            let mut state = timer.state.lock().unwrap_or_else(|e| e.into_inner());
+            state.wakers.remove(&id);
+        }
+    }
+}
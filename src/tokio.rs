@@ -0,0 +1,198 @@
+use std::{
+    future::{ready, Future},
+    io,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6},
+    option,
+    sync::{Arc, OnceLock},
+    time::Duration,
+};
+
+use crate::{
+    hooks::{LookupHooks, Observation},
+    orphan::Orphan,
+    services, ResolvedAddrs, ToSocketAddrsTimeout,
+};
+
+/// Asynchronous version of [`ToSocketAddrsTimeout`].
+///
+/// Lookups are driven by the platform completion notifications instead of blocking a worker, the
+/// timeout is enforced with [`tokio::time::timeout`] and requires a runtime with the time driver
+/// enabled. Dropping the future cancels the underlying query where the platform allows it.
+pub trait ToSocketAddrsTimeoutAsync {
+    type Iter: Iterator<Item = SocketAddr>;
+
+    fn to_socket_addrs_timeout(
+        &self,
+        timeout: Duration,
+    ) -> impl Future<Output = io::Result<Self::Iter>> + Send;
+}
+
+/// Resolves `host` like `tokio::net::lookup_host` does, giving up once `timeout` has elapsed.
+pub async fn lookup_host_timeout<T: ToSocketAddrsTimeoutAsync>(
+    host: T,
+    timeout: Duration,
+) -> io::Result<impl Iterator<Item = SocketAddr>> {
+    host.to_socket_addrs_timeout(timeout).await
+}
+
+/// Resolves `host` with the blocking [`ToSocketAddrsTimeout::to_socket_addrs_timeout`] on the
+/// blocking thread pool of the runtime, giving up once `timeout` has elapsed.
+///
+/// The lookup is given the same timeout, though a thread stuck past it is counted by
+/// [`orphaned_lookups`](crate::orphaned_lookups) from when the future gives up on it or is
+/// dropped until it returns. Requires a runtime with the time driver enabled.
+pub async fn resolve_blocking_offloaded<T>(host: T, timeout: Duration) -> io::Result<T::Iter>
+where
+    T: ToSocketAddrsTimeout + Send + 'static,
+    T::Iter: Send + 'static,
+{
+    let orphan = Arc::new(OnceLock::new());
+    let task = ::tokio::task::spawn_blocking({
+        let orphan = orphan.clone();
+        move || {
+            let res = host.to_socket_addrs_timeout(timeout);
+            drop(orphan);
+            res
+        }
+    });
+    let _abandon = Abandon(orphan);
+    match ::tokio::time::timeout(timeout, task).await {
+        Ok(Ok(res)) => res,
+        Ok(Err(err)) => Err(io::Error::other(err)),
+        Err(_) => Err(io::ErrorKind::TimedOut.into()),
+    }
+}
+
+/// Orphans the blocking lookup of [`resolve_blocking_offloaded`] should it outlive the future,
+/// its thread holding the other references.
+struct Abandon(Arc<OnceLock<Orphan>>);
+
+impl Drop for Abandon {
+    fn drop(&mut self) {
+        if Arc::strong_count(&self.0) > 1 {
+            self.0.get_or_init(Orphan::new);
+        }
+    }
+}
+
+async fn resolve_timeout(host: &str, port: u16, timeout: Duration) -> io::Result<ResolvedAddrs> {
+    if let Some(addr) = crate::literal(host, port) {
+        return Ok(addr.into());
+    }
+    let host = crate::hostname::to_ascii(host);
+    crate::shutdown::check()?;
+    let observation = Observation::start(&LookupHooks::NONE, crate::BACKEND, &host, timeout);
+    let res = async {
+        crate::HostnameSyntax::Strict.check(&host)?;
+        if timeout.is_zero() {
+            return Err(crate::resolver::would_block());
+        }
+        let lookup = crate::sys::lookup(crate::hostname::without_root(&host), port, Some(timeout))?;
+        match ::tokio::time::timeout(timeout, lookup).await {
+            Ok(res) => res.map(ResolvedAddrs::from),
+            Err(_) => Err(io::ErrorKind::TimedOut.into()),
+        }
+    }
+    .await;
+    if let Some(observation) = observation {
+        observation.finish(res.as_ref().map(ExactSizeIterator::len));
+    }
+    res
+}
+
+impl<'a> ToSocketAddrsTimeoutAsync for &'a [SocketAddr] {
+    type Iter = std::iter::Cloned<std::slice::Iter<'a, SocketAddr>>;
+
+    fn to_socket_addrs_timeout(
+        &self,
+        timeout: Duration,
+    ) -> impl Future<Output = io::Result<Self::Iter>> + Send {
+        ready(ToSocketAddrsTimeout::to_socket_addrs_timeout(self, timeout))
+    }
+}
+
+impl<T: ToSocketAddrsTimeoutAsync + ?Sized> ToSocketAddrsTimeoutAsync for &T {
+    type Iter = T::Iter;
+
+    fn to_socket_addrs_timeout(
+        &self,
+        timeout: Duration,
+    ) -> impl Future<Output = io::Result<T::Iter>> + Send {
+        (**self).to_socket_addrs_timeout(timeout)
+    }
+}
+
+macro_rules! impl_ready {
+    ($($ty:ty),* $(,)?) => {
+        $(
+            impl ToSocketAddrsTimeoutAsync for $ty {
+                type Iter = option::IntoIter<SocketAddr>;
+
+                fn to_socket_addrs_timeout(
+                    &self,
+                    timeout: Duration,
+                ) -> impl Future<Output = io::Result<Self::Iter>> + Send {
+                    ready(ToSocketAddrsTimeout::to_socket_addrs_timeout(self, timeout))
+                }
+            }
+        )*
+    };
+}
+
+impl_ready!(
+    SocketAddr,
+    SocketAddrV4,
+    SocketAddrV6,
+    (IpAddr, u16),
+    (Ipv4Addr, u16),
+    (Ipv6Addr, u16),
+);
+
+impl ToSocketAddrsTimeoutAsync for str {
+    type Iter = ResolvedAddrs;
+
+    async fn to_socket_addrs_timeout(&self, timeout: Duration) -> io::Result<Self::Iter> {
+        if let Ok(addr) = self.parse::<SocketAddr>() {
+            return Ok(addr.into());
+        }
+
+        let (host, port_str) = crate::parse_host_port(self)?;
+        let port = services::parse_port(port_str)?;
+
+        resolve_timeout(host, port, timeout).await
+    }
+}
+
+impl ToSocketAddrsTimeoutAsync for (&str, u16) {
+    type Iter = ResolvedAddrs;
+
+    fn to_socket_addrs_timeout(
+        &self,
+        timeout: Duration,
+    ) -> impl Future<Output = io::Result<Self::Iter>> + Send {
+        resolve_timeout(self.0, self.1, timeout)
+    }
+}
+
+impl ToSocketAddrsTimeoutAsync for String {
+    type Iter = ResolvedAddrs;
+
+    #[inline]
+    fn to_socket_addrs_timeout(
+        &self,
+        timeout: Duration,
+    ) -> impl Future<Output = io::Result<Self::Iter>> + Send {
+        ToSocketAddrsTimeoutAsync::to_socket_addrs_timeout(&**self, timeout)
+    }
+}
+
+impl ToSocketAddrsTimeoutAsync for (String, u16) {
+    type Iter = ResolvedAddrs;
+
+    fn to_socket_addrs_timeout(
+        &self,
+        timeout: Duration,
+    ) -> impl Future<Output = io::Result<Self::Iter>> + Send {
+        resolve_timeout(&self.0, self.1, timeout)
+    }
+}
@@ -0,0 +1,321 @@
+use std::{
+    future::Future,
+    io,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    pin::Pin,
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use crate::{ResolveError, ResolverBackend};
+
+#[link(wasm_import_module = "wasi:sockets/instance-network@0.2.0")]
+extern "C" {
+    #[link_name = "instance-network"]
+    fn instance_network() -> i32;
+}
+
+#[link(wasm_import_module = "wasi:sockets/network@0.2.0")]
+extern "C" {
+    #[link_name = "[resource-drop]network"]
+    fn drop_network(handle: i32);
+}
+
+#[link(wasm_import_module = "wasi:sockets/ip-name-lookup@0.2.0")]
+extern "C" {
+    #[link_name = "resolve-addresses"]
+    fn resolve_addresses(network: i32, name: *const u8, name_len: usize, ret: *mut RetArea);
+
+    #[link_name = "[method]resolve-address-stream.resolve-next-address"]
+    fn resolve_next_address(stream: i32, ret: *mut RetArea);
+
+    #[link_name = "[method]resolve-address-stream.subscribe"]
+    fn subscribe_stream(stream: i32) -> i32;
+
+    #[link_name = "[resource-drop]resolve-address-stream"]
+    fn drop_stream(handle: i32);
+}
+
+#[link(wasm_import_module = "wasi:clocks/monotonic-clock@0.2.0")]
+extern "C" {
+    #[link_name = "subscribe-duration"]
+    fn subscribe_duration(nanos: u64) -> i32;
+}
+
+#[link(wasm_import_module = "wasi:io/poll@0.2.0")]
+extern "C" {
+    #[link_name = "poll"]
+    fn poll(pollables: *const i32, len: usize, ret: *mut RetArea);
+
+    #[link_name = "[resource-drop]pollable"]
+    fn drop_pollable(handle: i32);
+}
+
+/// Space for the canonical ABI return values of the imports above.
+#[repr(C, align(4))]
+struct RetArea([u8; 24]);
+
+impl RetArea {
+    const fn new() -> Self {
+        Self([0; 24])
+    }
+
+    fn u8(&self, offset: usize) -> u8 {
+        self.0[offset]
+    }
+
+    fn u16(&self, offset: usize) -> u16 {
+        u16::from_le_bytes([self.0[offset], self.0[offset + 1]])
+    }
+
+    fn i32(&self, offset: usize) -> i32 {
+        i32::from_le_bytes(self.0[offset..offset + 4].try_into().unwrap())
+    }
+}
+
+/// `wasi:sockets/network.error-code`.
+#[repr(transparent)]
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct ErrorCode(u8);
+
+#[allow(non_upper_case_globals)]
+impl ErrorCode {
+    /// Unknown error.
+    pub const Unknown: Self = Self(0);
+    /// Access denied.
+    pub const AccessDenied: Self = Self(1);
+    /// The operation is not supported.
+    pub const NotSupported: Self = Self(2);
+    /// One of the arguments is invalid.
+    pub const InvalidArgument: Self = Self(3);
+    /// Not enough memory to complete the operation.
+    pub const OutOfMemory: Self = Self(4);
+    /// The operation timed out before it could finish completely.
+    pub const Timeout: Self = Self(5);
+    /// The operation is not ready yet.
+    pub const WouldBlock: Self = Self(8);
+    /// Name does not exist or has no suitable associated IP addresses.
+    pub const NameUnresolvable: Self = Self(18);
+    /// A temporary failure in name resolution occurred.
+    pub const TemporaryResolverFailure: Self = Self(19);
+    /// A permanent failure in name resolution occurred.
+    pub const PermanentResolverFailure: Self = Self(20);
+}
+
+impl core::fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            Self::Unknown => f.write_str("unknown error"),
+            Self::AccessDenied => f.write_str("access denied"),
+            Self::NotSupported => f.write_str("operation not supported"),
+            Self::InvalidArgument => f.write_str("invalid argument"),
+            Self::OutOfMemory => f.write_str("out of memory"),
+            Self::Timeout => f.write_str("operation timed out"),
+            Self::WouldBlock => f.write_str("operation would block"),
+            Self::NameUnresolvable => f.write_str("name unresolvable"),
+            Self::TemporaryResolverFailure => f.write_str("temporary failure in name resolution"),
+            Self::PermanentResolverFailure => f.write_str("permanent failure in name resolution"),
+            _ => write!(f, "error code {}", self.0),
+        }
+    }
+}
+
+impl core::fmt::Debug for ErrorCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            Self::Unknown => f.write_str("ErrorCode::Unknown"),
+            Self::AccessDenied => f.write_str("ErrorCode::AccessDenied"),
+            Self::NotSupported => f.write_str("ErrorCode::NotSupported"),
+            Self::InvalidArgument => f.write_str("ErrorCode::InvalidArgument"),
+            Self::OutOfMemory => f.write_str("ErrorCode::OutOfMemory"),
+            Self::Timeout => f.write_str("ErrorCode::Timeout"),
+            Self::WouldBlock => f.write_str("ErrorCode::WouldBlock"),
+            Self::NameUnresolvable => f.write_str("ErrorCode::NameUnresolvable"),
+            Self::TemporaryResolverFailure => f.write_str("ErrorCode::TemporaryResolverFailure"),
+            Self::PermanentResolverFailure => f.write_str("ErrorCode::PermanentResolverFailure"),
+            _ => write!(f, "ErrorCode({})", self.0),
+        }
+    }
+}
+
+impl std::error::Error for ErrorCode {}
+
+impl From<ErrorCode> for io::Error {
+    fn from(value: ErrorCode) -> Self {
+        match value {
+            ErrorCode::Timeout => io::ErrorKind::TimedOut.into(),
+            ErrorCode::AccessDenied => io::Error::new(io::ErrorKind::PermissionDenied, value),
+            ErrorCode::NotSupported => io::Error::new(io::ErrorKind::Unsupported, value),
+            ErrorCode::InvalidArgument => io::Error::new(io::ErrorKind::InvalidInput, value),
+            ErrorCode::OutOfMemory => io::Error::new(io::ErrorKind::OutOfMemory, value),
+            ErrorCode::NameUnresolvable => ResolveError::HostNotFound.into(),
+            ErrorCode::TemporaryResolverFailure => ResolveError::TemporaryFailure.into(),
+            _ => io::Error::other(value),
+        }
+    }
+}
+
+/// An owned `wasi:io/poll.pollable`.
+struct Pollable(i32);
+
+impl Drop for Pollable {
+    fn drop(&mut self) {
+        unsafe { drop_pollable(self.0) };
+    }
+}
+
+/// Waits for any of `pollables`, returning the indexes of those ready.
+fn poll_list(pollables: &[&Pollable]) -> Vec<u32> {
+    let handles: Vec<i32> = pollables.iter().map(|p| p.0).collect();
+    let mut ret = RetArea::new();
+    unsafe {
+        poll(handles.as_ptr(), handles.len(), &mut ret);
+        let (ptr, len) = (ret.i32(0) as *mut u32, ret.i32(4) as usize);
+        // allocated through `cabi_realloc`, that is the global allocator
+        Vec::from_raw_parts(ptr, len, len)
+    }
+}
+
+/// An owned `wasi:sockets/ip-name-lookup.resolve-address-stream`, canceled when dropped.
+struct StreamHandle(i32);
+
+impl Drop for StreamHandle {
+    fn drop(&mut self) {
+        unsafe { drop_stream(self.0) };
+    }
+}
+
+/// An in-progress lookup.
+struct AddressStream {
+    /// Declared first to be dropped before the stream, as child resources must be.
+    pollable: Pollable,
+    handle: StreamHandle,
+}
+
+impl AddressStream {
+    fn start(host: &str) -> io::Result<Self> {
+        let network = unsafe { instance_network() };
+        let mut ret = RetArea::new();
+        unsafe {
+            resolve_addresses(network, host.as_ptr(), host.len(), &mut ret);
+            drop_network(network);
+        }
+        match ret.u8(0) {
+            0 => {
+                let handle = ret.i32(4);
+                Ok(Self {
+                    pollable: Pollable(unsafe { subscribe_stream(handle) }),
+                    handle: StreamHandle(handle),
+                })
+            }
+            _ => Err(ErrorCode(ret.u8(4)).into()),
+        }
+    }
+
+    /// The next address, `Ok(None)` once exhausted or `WouldBlock` while not ready.
+    fn next(&self) -> Result<Option<IpAddr>, ErrorCode> {
+        let mut ret = RetArea::new();
+        unsafe { resolve_next_address(self.handle.0, &mut ret) };
+        if ret.u8(0) != 0 {
+            return Err(ErrorCode(ret.u8(2)));
+        }
+        if ret.u8(2) == 0 {
+            return Ok(None);
+        }
+        Ok(Some(match ret.u8(4) {
+            0 => IpAddr::V4(Ipv4Addr::new(ret.u8(6), ret.u8(7), ret.u8(8), ret.u8(9))),
+            _ => IpAddr::V6(Ipv6Addr::new(
+                ret.u16(6),
+                ret.u16(8),
+                ret.u16(10),
+                ret.u16(12),
+                ret.u16(14),
+                ret.u16(16),
+                ret.u16(18),
+                ret.u16(20),
+            )),
+        }))
+    }
+}
+
+/// In-flight lookup, driven by polling the address stream.
+///
+/// WASI has no reactor to wake the task when the stream becomes ready, so a pending lookup
+/// wakes itself right away and is polled again by the executor.
+pub(crate) struct Lookup {
+    stream: AddressStream,
+    port: u16,
+    addrs: Vec<SocketAddr>,
+}
+
+impl Future for Lookup {
+    type Output = io::Result<std::vec::IntoIter<SocketAddr>>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        loop {
+            match self.stream.next() {
+                Ok(Some(ip)) => {
+                    let addr = SocketAddr::new(ip, self.port);
+                    self.addrs.push(addr);
+                }
+                Ok(None) => return Poll::Ready(Ok(core::mem::take(&mut self.addrs).into_iter())),
+                Err(ErrorCode::WouldBlock) => {
+                    cx.waker().wake_by_ref();
+                    return Poll::Pending;
+                }
+                Err(err) => return Poll::Ready(Err(err.into())),
+            }
+        }
+    }
+}
+
+pub(crate) fn lookup(host: &str, port: u16, _timeout: Option<Duration>) -> io::Result<Lookup> {
+    Ok(Lookup {
+        stream: AddressStream::start(host)?,
+        port,
+        addrs: Vec::new(),
+    })
+}
+
+/// `resolve-addresses` cannot select an address family, so there is nothing to split.
+pub(crate) fn lookup_split(
+    host: &str,
+    port: u16,
+    timeout: Option<Duration>,
+) -> io::Result<Vec<Lookup>> {
+    Ok(vec![lookup(host, port, timeout)?])
+}
+
+/// The WASI resolver: `wasi:sockets/ip-name-lookup`, waited on together with a monotonic clock
+/// pollable for the timeout.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemResolver;
+
+impl ResolverBackend for SystemResolver {
+    fn resolve(
+        &self,
+        host: &str,
+        port: u16,
+        timeout: Duration,
+    ) -> io::Result<std::vec::IntoIter<SocketAddr>> {
+        let deadline = crate::deadline_after(timeout);
+        let stream = AddressStream::start(host)?;
+        let mut addrs = Vec::new();
+        loop {
+            match stream.next() {
+                Ok(Some(ip)) => addrs.push(SocketAddr::new(ip, port)),
+                Ok(None) => return Ok(addrs.into_iter()),
+                Err(ErrorCode::WouldBlock) => {
+                    let remaining = deadline.saturating_duration_since(Instant::now());
+                    let timer = Pollable(unsafe {
+                        subscribe_duration(remaining.as_nanos().min(u64::MAX as u128) as u64)
+                    });
+                    if !poll_list(&[&stream.pollable, &timer]).contains(&0) {
+                        return Err(io::ErrorKind::TimedOut.into());
+                    }
+                }
+                Err(err) => return Err(err.into()),
+            }
+        }
+    }
+}
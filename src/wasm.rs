@@ -0,0 +1,86 @@
+use std::{
+    future::Future,
+    io,
+    net::SocketAddr,
+    pin::Pin,
+    sync::{Arc, RwLock},
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use crate::ResolverBackend;
+
+type Provider = dyn Fn(&str, u16, Duration) -> io::Result<Vec<SocketAddr>> + Send + Sync + 'static;
+
+static PROVIDER: RwLock<Option<Arc<Provider>>> = RwLock::new(None);
+
+/// Registers the function resolving host names on `wasm32-unknown-unknown`, which has no
+/// resolver of its own, replacing any previous one.
+///
+/// It is called with the host name, the port and the timeout, and could for instance query a
+/// DNS-over-HTTPS endpoint through JavaScript. Until one is registered lookups fail with
+/// [`io::ErrorKind::Unsupported`].
+pub fn set_wasm_provider<F>(provider: F)
+where
+    F: Fn(&str, u16, Duration) -> io::Result<Vec<SocketAddr>> + Send + Sync + 'static,
+{
+    *PROVIDER.write().unwrap_or_else(|e| e.into_inner()) = Some(Arc::new(provider));
+}
+
+fn provide(host: &str, port: u16, timeout: Duration) -> io::Result<std::vec::IntoIter<SocketAddr>> {
+    let provider = PROVIDER.read().unwrap_or_else(|e| e.into_inner()).clone();
+    match provider {
+        Some(provider) => provider(host, port, timeout).map(Vec::into_iter),
+        None => Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "no wasm resolver provider registered",
+        )),
+    }
+}
+
+/// Lookup answered synchronously by the provider when started.
+pub(crate) struct Lookup(Option<io::Result<std::vec::IntoIter<SocketAddr>>>);
+
+impl Future for Lookup {
+    type Output = io::Result<std::vec::IntoIter<SocketAddr>>;
+
+    fn poll(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Poll::Ready(self.0.take().expect("lookup polled after completion"))
+    }
+}
+
+/// Lookups without a timeout still hand the provider one.
+const NO_TIMEOUT: Duration = Duration::from_secs(u32::MAX as u64);
+
+pub(crate) fn lookup(host: &str, port: u16, timeout: Option<Duration>) -> io::Result<Lookup> {
+    Ok(Lookup(Some(provide(
+        host,
+        port,
+        timeout.unwrap_or(NO_TIMEOUT),
+    ))))
+}
+
+/// The provider is asked for both families at once, so there is nothing to split.
+pub(crate) fn lookup_split(
+    host: &str,
+    port: u16,
+    timeout: Option<Duration>,
+) -> io::Result<Vec<Lookup>> {
+    Ok(vec![lookup(host, port, timeout)?])
+}
+
+/// The resolver on `wasm32-unknown-unknown`: the function registered with
+/// [`set_wasm_provider`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemResolver;
+
+impl ResolverBackend for SystemResolver {
+    fn resolve(
+        &self,
+        host: &str,
+        port: u16,
+        timeout: Duration,
+    ) -> io::Result<std::vec::IntoIter<SocketAddr>> {
+        provide(host, port, timeout)
+    }
+}
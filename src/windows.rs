@@ -1,28 +1,35 @@
 #![allow(clippy::missing_transmute_annotations)]
 
 use std::{
+    cell::UnsafeCell,
     ffi::OsStr,
+    future::Future,
     mem::offset_of,
     net::{Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6, ToSocketAddrs},
     os::windows::ffi::OsStrExt,
-    sync::OnceLock,
+    pin::Pin,
+    sync::{Arc, OnceLock},
+    task::{ready, Context as TaskContext, Poll},
     time::Duration,
 };
 
 use windows::Win32::{
-    Foundation::{CloseHandle, ERROR_SUCCESS, HANDLE},
+    Foundation::{ERROR_SUCCESS, HANDLE},
     Networking::WinSock::{
-        FreeAddrInfoExW, GetAddrInfoExW, ADDRINFOEXW, AF_INET, AF_INET6, AF_UNSPEC, NS_ALL,
-        SOCKADDR_IN, SOCKADDR_IN6, SOCKADDR_STORAGE, SOCK_STREAM, TIMEVAL, WSA_IO_PENDING,
-    },
-    System::{
-        Threading::{CreateEventW, SetEvent, WaitForSingleObject, INFINITE},
-        IO::OVERLAPPED,
+        FreeAddrInfoExW, GetAddrInfoExCancel, GetAddrInfoExW, ADDRINFOEXW, AF_INET, AF_INET6,
+        AF_UNSPEC, AI_ADDRCONFIG, AI_ALL, AI_CANONNAME, AI_DISABLE_IDN_ENCODING, AI_NUMERICHOST,
+        AI_NUMERICSERV, AI_PASSIVE, AI_V4MAPPED, IPPROTO_TCP, IPPROTO_UDP, NS_ALL, SOCKADDR_IN,
+        SOCKADDR_IN6, SOCKADDR_STORAGE, SOCK_DGRAM, SOCK_RAW, SOCK_STREAM, TIMEVAL, WSA_IO_PENDING,
     },
+    System::IO::OVERLAPPED,
 };
 use windows_core::PCWSTR;
 
-use crate::ToSocketAddrsTimeout;
+use crate::{
+    addrinfo, completion::Completion, future::block_on_deadline, AddrInfo, AddrInfoIter,
+    AddressInfoError, CanonicalAddrs, Family, HintFlags, Hints, Protocol, ResolverBackend,
+    SocketType,
+};
 
 static WSA_START: OnceLock<()> = OnceLock::new();
 
@@ -33,114 +40,290 @@ fn init() {
     });
 }
 
-struct Context {
-    pub query_overlapped: OVERLAPPED,
-    pub complete_event: HANDLE,
-    pub query_result: *mut ADDRINFOEXW,
-    pub result: std::io::Result<LookupHost>,
-}
-
-impl Context {
-    pub fn new() -> std::io::Result<Self> {
-        let complete_event = unsafe { CreateEventW(None, true, false, None)? };
-
-        Ok(Self {
-            query_overlapped: unsafe { core::mem::zeroed() },
-            complete_event,
-            query_result: unsafe { core::mem::zeroed() },
-            result: Err(std::io::ErrorKind::Other.into()),
-        })
+const AI_FLAGS: [(HintFlags, u32); 8] = [
+    (HintFlags::PASSIVE, AI_PASSIVE),
+    (HintFlags::CANONNAME, AI_CANONNAME),
+    (HintFlags::NUMERICHOST, AI_NUMERICHOST),
+    (HintFlags::NUMERICSERV, AI_NUMERICSERV),
+    (HintFlags::V4MAPPED, AI_V4MAPPED),
+    (HintFlags::ALL, AI_ALL),
+    (HintFlags::ADDRCONFIG, AI_ADDRCONFIG),
+    (HintFlags::DISABLE_IDN_ENCODING, AI_DISABLE_IDN_ENCODING),
+];
+
+fn addrinfo_hints(hints: &Hints) -> ADDRINFOEXW {
+    let mut ai: ADDRINFOEXW = unsafe { core::mem::zeroed() };
+    let mut flags = hints.flags;
+    // names are converted before they get here, those that could not be must not be by Windows
+    if cfg!(feature = "idna") {
+        flags |= HintFlags::DISABLE_IDN_ENCODING;
     }
+    ai.ai_flags = flags.to_native(&AI_FLAGS) as _;
+    ai.ai_family = match hints.family {
+        Family::Unspecified => AF_UNSPEC.0 as _,
+        Family::Ipv4 => AF_INET.0 as _,
+        Family::Ipv6 => AF_INET6.0 as _,
+    };
+    ai.ai_socktype = match hints.socktype {
+        SocketType::Any => 0,
+        SocketType::Stream => SOCK_STREAM.0,
+        SocketType::Datagram => SOCK_DGRAM.0,
+        SocketType::Raw => SOCK_RAW.0,
+    };
+    ai.ai_protocol = match hints.protocol {
+        Protocol::Any => 0,
+        Protocol::Tcp => IPPROTO_TCP.0,
+        Protocol::Udp => IPPROTO_UDP.0,
+    };
+    ai
+}
 
-    #[inline(always)]
-    pub fn set_event(&mut self) {
-        unsafe { SetEvent(self.complete_event) }.unwrap();
+/// `duration` as a `TIMEVAL`, saturating at the 32-bit seconds it holds.
+fn d2tv(duration: Duration) -> TIMEVAL {
+    TIMEVAL {
+        tv_sec: duration.as_secs().min(i32::MAX as u64) as _,
+        tv_usec: duration.subsec_micros() as _,
     }
 }
 
-impl Drop for Context {
-    fn drop(&mut self) {
-        unsafe { CloseHandle(self.complete_event) }.unwrap();
-    }
+/// Heap state of an overlapped query, shared with `async_query_complete_callback`, which owns
+/// a reference to it until the query completes or is canceled.
+struct AsyncQuery {
+    /// Written by the OS while the query runs.
+    overlapped: UnsafeCell<OVERLAPPED>,
+    name: Option<WideName>,
+    service: Option<WideName>,
+    query_result: UnsafeCell<*mut ADDRINFOEXW>,
+    cancel_handle: UnsafeCell<HANDLE>,
+    port: Option<u16>,
+    completion: Completion<std::io::Result<LookupHost>>,
 }
 
-unsafe extern "system" fn query_complete_callback(
+unsafe impl Sync for AsyncQuery {}
+unsafe impl Send for AsyncQuery {}
+
+unsafe extern "system" fn async_query_complete_callback(
     error: u32,
     _bytes: u32,
     overlapped: *const OVERLAPPED,
 ) {
-    let ctx = &mut *(overlapped
-        .cast::<u8>()
-        .sub(offset_of!(Context, query_overlapped)) as *mut Context);
+    let query = Arc::from_raw(
+        overlapped
+            .cast::<u8>()
+            .sub(offset_of!(AsyncQuery, overlapped))
+            .cast::<AsyncQuery>(),
+    );
 
+    let result = core::mem::replace(&mut *query.query_result.get(), core::ptr::null_mut());
     let lh = LookupHost {
-        original: ctx.query_result,
-        cur: ctx.query_result,
-        port: 0,
+        original: result,
+        cur: result,
+        port: query.port,
     };
-    ctx.query_result = core::ptr::null_mut();
-    ctx.result = if error == ERROR_SUCCESS.0 {
+    query.completion.complete(if error == ERROR_SUCCESS.0 {
         Ok(lh)
     } else {
         drop(lh);
-        Err(std::io::Error::from_raw_os_error(error as _))
-    };
+        Err(AddressInfoError::from_raw_code(error as _).into())
+    });
+}
 
-    ctx.set_event();
+/// In-flight lookup completed by `async_query_complete_callback`.
+pub(crate) struct Lookup {
+    query: Arc<AsyncQuery>,
+    done: bool,
 }
 
-fn getaddrinfo_timeout(name: &[u16], timeout: Duration) -> std::io::Result<LookupHost> {
-    init();
+impl Lookup {
+    fn poll_host(&mut self, cx: &mut TaskContext<'_>) -> Poll<std::io::Result<LookupHost>> {
+        let res = ready!(self.query.completion.poll(cx));
+        self.done = true;
+        Poll::Ready(res)
+    }
+}
 
-    let mut hints: ADDRINFOEXW = unsafe { core::mem::zeroed() };
-    hints.ai_family = AF_UNSPEC.0 as _;
-    hints.ai_socktype = SOCK_STREAM.0 as _;
+impl Future for Lookup {
+    type Output = std::io::Result<std::vec::IntoIter<SocketAddr>>;
 
-    let tv = TIMEVAL {
-        tv_sec: timeout.as_secs() as _,
-        tv_usec: timeout.subsec_micros() as _,
-    };
+    fn poll(mut self: Pin<&mut Self>, cx: &mut TaskContext<'_>) -> Poll<Self::Output> {
+        self.poll_host(cx)
+            .map(|res| res.and_then(resolve_socket_addr))
+    }
+}
+
+impl Drop for Lookup {
+    fn drop(&mut self) {
+        if !self.done {
+            // the callback still runs with WSA_E_CANCELLED and releases its reference
+            _ = unsafe { GetAddrInfoExCancel(self.query.cancel_handle.get()) };
+        }
+    }
+}
+
+/// Starts the query for `name` and `service`, either of which may be absent as with
+/// `getaddrinfo`, setting `port` on the addresses if given.
+fn start_lookup(
+    name: Option<WideName>,
+    service: Option<WideName>,
+    hints: &ADDRINFOEXW,
+    port: Option<u16>,
+    timeout: Option<Duration>,
+) -> std::io::Result<Lookup> {
+    init();
 
-    let mut ctx = Context::new()?;
+    let query = Arc::new(AsyncQuery {
+        overlapped: UnsafeCell::new(unsafe { core::mem::zeroed() }),
+        name,
+        service,
+        query_result: UnsafeCell::new(core::ptr::null_mut()),
+        cancel_handle: UnsafeCell::new(HANDLE::default()),
+        port,
+        completion: Completion::new(),
+    });
+
+    // no TIMEVAL at all for an infinite timeout
+    let tv = timeout.filter(|&t| t != crate::NO_TIMEOUT).map(d2tv);
 
+    let callback_ref = Arc::into_raw(query.clone());
     let ret = unsafe {
         GetAddrInfoExW(
-            PCWSTR(name.as_ptr().cast()),
-            None,
+            PCWSTR(
+                query
+                    .name
+                    .as_ref()
+                    .map_or(core::ptr::null(), |name| name.as_ptr()),
+            ),
+            PCWSTR(
+                query
+                    .service
+                    .as_ref()
+                    .map_or(core::ptr::null(), |service| service.as_ptr()),
+            ),
             NS_ALL,
             None,
-            Some(&hints),
-            &mut ctx.query_result,
-            Some(&tv),
-            Some(&ctx.query_overlapped),
-            Some(Some(query_complete_callback)),
-            None,
+            Some(hints),
+            query.query_result.get(),
+            tv.as_ref().map(|tv| tv as *const TIMEVAL),
+            Some((*callback_ref).overlapped.get()),
+            Some(Some(async_query_complete_callback)),
+            Some(query.cancel_handle.get()),
         )
     };
 
     if ret != WSA_IO_PENDING.0 {
-        unsafe { query_complete_callback(ret as _, 0, &ctx.query_overlapped) };
+        unsafe { async_query_complete_callback(ret as _, 0, (*callback_ref).overlapped.get()) };
     }
 
-    assert_eq!(
-        unsafe { WaitForSingleObject(ctx.complete_event, INFINITE).0 },
-        0
-    );
+    Ok(Lookup { query, done: false })
+}
 
-    let mut result = Err(std::io::ErrorKind::Other.into());
-    core::mem::swap(&mut ctx.result, &mut result);
-    result
+pub(crate) fn lookup(
+    hostname: &str,
+    port: u16,
+    timeout: Option<Duration>,
+) -> std::io::Result<Lookup> {
+    start_lookup(
+        Some(to_wide(hostname)?),
+        None,
+        &addrinfo_hints(&Hints::default()),
+        Some(port),
+        timeout,
+    )
+}
+
+/// Starts separate IPv6 and IPv4 lookups.
+pub(crate) fn lookup_split(
+    hostname: &str,
+    port: u16,
+    timeout: Option<Duration>,
+) -> std::io::Result<Vec<Lookup>> {
+    let name = to_wide(hostname)?;
+    let mut hints = addrinfo_hints(&Hints::default());
+    [AF_INET6, AF_INET]
+        .into_iter()
+        .map(|family| {
+            hints.ai_family = family.0 as _;
+            start_lookup(Some(name.clone()), None, &hints, Some(port), timeout)
+        })
+        .collect()
 }
 
-struct LookupHost {
+pub(crate) struct LookupHost {
     original: *mut ADDRINFOEXW,
     cur: *mut ADDRINFOEXW,
-    port: u16,
+    port: Option<u16>,
 }
 
 impl LookupHost {
-    pub fn port(&self) -> u16 {
-        self.port
+    /// The address of `ai` with the port of the lookup, `None` for families other than IPv4 and
+    /// IPv6.
+    fn addr_of(&self, ai: &ADDRINFOEXW) -> Option<SocketAddr> {
+        let mut addr = sockaddr_to_addr(
+            unsafe { &*(ai.ai_addr as *const SOCKADDR_STORAGE) },
+            ai.ai_addrlen,
+        )
+        .ok()?;
+        if let Some(port) = self.port {
+            addr.set_port(port);
+        }
+        Some(addr)
+    }
+
+    /// The addresses not returned yet, leaving them to be.
+    pub(crate) fn remaining(&self) -> impl Iterator<Item = SocketAddr> + '_ {
+        let mut cur = self.cur;
+        std::iter::from_fn(move || loop {
+            let ai = unsafe { cur.as_ref()? };
+            cur = ai.ai_next;
+            if let Some(addr) = self.addr_of(ai) {
+                return Some(addr);
+            }
+        })
+    }
+
+    /// The `ai_canonname` of the first entry, set when asked for with `AI_CANONNAME`.
+    fn canonical_name(&self) -> Option<String> {
+        let first = unsafe { self.original.as_ref()? };
+        if first.ai_canonname.is_null() {
+            return None;
+        }
+        Some(String::from_utf16_lossy(unsafe {
+            first.ai_canonname.as_wide()
+        }))
+    }
+
+    /// Every entry in full, skipping those of families other than IPv4 and IPv6.
+    fn records(&self) -> Vec<AddrInfo> {
+        let mut records = Vec::new();
+        let mut cur = self.original;
+        while let Some(ai) = unsafe { cur.as_ref() } {
+            cur = ai.ai_next;
+            let Ok(addr) = sockaddr_to_addr(
+                unsafe { &*(ai.ai_addr as *const SOCKADDR_STORAGE) },
+                ai.ai_addrlen,
+            ) else {
+                continue;
+            };
+            records.push(AddrInfo {
+                family: addrinfo::family_of(&addr),
+                socktype: match ai.ai_socktype {
+                    t if t == SOCK_STREAM.0 => SocketType::Stream,
+                    t if t == SOCK_DGRAM.0 => SocketType::Datagram,
+                    t if t == SOCK_RAW.0 => SocketType::Raw,
+                    _ => SocketType::Any,
+                },
+                protocol: match ai.ai_protocol {
+                    p if p == IPPROTO_TCP.0 => Protocol::Tcp,
+                    p if p == IPPROTO_UDP.0 => Protocol::Udp,
+                    _ => Protocol::Any,
+                },
+                flags: HintFlags::from_native(ai.ai_flags as u32, &AI_FLAGS),
+                canonical_name: (!ai.ai_canonname.is_null())
+                    .then(|| String::from_utf16_lossy(unsafe { ai.ai_canonname.as_wide() })),
+                addr,
+            });
+        }
+        records
     }
 }
 
@@ -173,14 +356,10 @@ impl Iterator for LookupHost {
 
     fn next(&mut self) -> Option<SocketAddr> {
         loop {
-            let cur = unsafe { self.cur.as_ref()? };
-            self.cur = cur.ai_next;
-            match sockaddr_to_addr(
-                unsafe { &*(cur.ai_addr as *const SOCKADDR_STORAGE) },
-                cur.ai_addrlen,
-            ) {
-                Ok(addr) => return Some(addr),
-                Err(_) => continue,
+            let ai = unsafe { self.cur.as_ref()? };
+            self.cur = ai.ai_next;
+            if let Some(addr) = self.addr_of(ai) {
+                return Some(addr);
             }
         }
     }
@@ -195,86 +374,188 @@ impl Drop for LookupHost {
 unsafe impl Sync for LookupHost {}
 unsafe impl Send for LookupHost {}
 
-fn to_wide<T: AsRef<OsStr>>(s: T) -> std::io::Result<Vec<u16>> {
-    if s.as_ref().as_encoded_bytes().contains(&b'\0') {
-        Err(std::io::Error::new(
-            std::io::ErrorKind::InvalidInput,
-            "host name contained an unexpected NUL byte",
-        ))
-    } else {
-        Ok(s.as_ref().encode_wide().chain(Some(0)).collect())
+/// Longest name kept inline, NUL included, room enough for any DNS name.
+const INLINE_NAME: usize = 256;
+
+/// A NUL-terminated UTF-16 host name or service, kept in the query itself when it fits so that
+/// lookups of ordinary names do not allocate for it.
+#[derive(Clone)]
+#[allow(clippy::large_enum_variant)]
+enum WideName {
+    Inline { buf: [u16; INLINE_NAME], len: usize },
+    Heap(Vec<u16>),
+}
+
+impl core::ops::Deref for WideName {
+    type Target = [u16];
+
+    /// The name with its NUL.
+    fn deref(&self) -> &[u16] {
+        match self {
+            Self::Inline { buf, len } => &buf[..*len],
+            Self::Heap(name) => name,
+        }
     }
 }
 
-impl TryFrom<(&str, Duration)> for LookupHost {
-    type Error = std::io::Error;
-
-    fn try_from((s, timeout): (&str, Duration)) -> Result<Self, Self::Error> {
-        let (host, port_str) = s.rsplit_once(':').ok_or_else(|| {
-            std::io::Error::new(std::io::ErrorKind::InvalidInput, "invalid socket address")
-        })?;
-        let port: u16 = port_str.parse().map_err(|_| {
-            std::io::Error::new(std::io::ErrorKind::InvalidInput, "invalid port value")
-        })?;
-        (host, port, timeout).try_into()
+fn to_wide<T: AsRef<OsStr>>(s: T) -> std::io::Result<WideName> {
+    let s = s.as_ref();
+    if s.as_encoded_bytes().contains(&b'\0') {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "host name contained an unexpected NUL byte",
+        ));
+    }
+    let mut buf = [0; INLINE_NAME];
+    let mut len = 0;
+    for unit in s.encode_wide() {
+        // one unit short of the end, for the NUL
+        if len == INLINE_NAME - 1 {
+            return Ok(WideName::Heap(s.encode_wide().chain(Some(0)).collect()));
+        }
+        buf[len] = unit;
+        len += 1;
     }
+    Ok(WideName::Inline { buf, len: len + 1 })
 }
 
-impl TryFrom<(&str, u16, Duration)> for LookupHost {
-    type Error = std::io::Error;
+fn resolve_timeout(
+    hostname: &str,
+    port: u16,
+    hints: &Hints,
+    timeout: Duration,
+) -> std::io::Result<LookupHost> {
+    getaddrinfo_timeout(
+        Some(to_wide(hostname)?),
+        None,
+        &addrinfo_hints(hints),
+        Some(port),
+        timeout,
+    )
+}
 
-    fn try_from((hostname, port, timeout): (&str, u16, Duration)) -> Result<Self, Self::Error> {
-        let hostname = to_wide(hostname)?;
-        let mut me = getaddrinfo_timeout(&hostname, timeout)?;
-        me.port = port;
-        Ok(me)
-    }
+fn getaddrinfo_timeout(
+    name: Option<WideName>,
+    service: Option<WideName>,
+    hints: &ADDRINFOEXW,
+    port: Option<u16>,
+    timeout: Duration,
+) -> std::io::Result<LookupHost> {
+    let mut lookup = start_lookup(name, service, hints, port, Some(timeout))?;
+    // providers may ignore the TIMEVAL, so the deadline is enforced here as well; dropping the
+    // unfinished lookup cancels it with GetAddrInfoExCancel
+    block_on_deadline(
+        &mut std::future::poll_fn(|cx| lookup.poll_host(cx)),
+        crate::deadline_after(timeout),
+    )
+    .unwrap_or_else(|| Err(std::io::ErrorKind::TimedOut.into()))
 }
 
 fn resolve_socket_addr(lh: LookupHost) -> std::io::Result<std::vec::IntoIter<SocketAddr>> {
-    let p = lh.port();
-    let v: Vec<_> = lh
-        .map(|mut a| {
-            a.set_port(p);
-            a
-        })
-        .collect();
-    Ok(v.into_iter())
+    Ok(lh.collect::<Vec<_>>().into_iter())
 }
 
-impl ToSocketAddrsTimeout for str {
-    type Iter = std::vec::IntoIter<SocketAddr>;
+/// The addresses of `host`, read off the `GetAddrInfoExW` list as they are iterated.
+pub(crate) fn resolve_iter(
+    host: &str,
+    port: u16,
+    timeout: Duration,
+) -> std::io::Result<AddrInfoIter> {
+    resolve_timeout(host, port, &Hints::default(), timeout).map(AddrInfoIter::native)
+}
 
-    fn to_socket_addrs_timeout(
+/// Wildcard addresses for `service`, from `GetAddrInfoExW` with `AI_PASSIVE` and no name.
+pub(crate) fn bind_addrs(
+    service: &str,
+    timeout: Duration,
+) -> std::io::Result<std::vec::IntoIter<SocketAddr>> {
+    let hints = Hints {
+        flags: HintFlags::PASSIVE,
+        ..Hints::default()
+    };
+    getaddrinfo_timeout(
+        None,
+        Some(to_wide(service)?),
+        &addrinfo_hints(&hints),
+        None,
+        timeout,
+    )
+    .and_then(resolve_socket_addr)
+}
+
+/// The `GetAddrInfoExW` entries for `host` and `service`.
+pub(crate) fn lookup_addrinfo(
+    host: Option<&str>,
+    service: Option<&str>,
+    hints: &Hints,
+    timeout: Duration,
+) -> std::io::Result<Vec<AddrInfo>> {
+    getaddrinfo_timeout(
+        host.map(to_wide).transpose()?,
+        service.map(to_wide).transpose()?,
+        &addrinfo_hints(hints),
+        None,
+        timeout,
+    )
+    .map(|lh| lh.records())
+}
+
+/// The platform resolver: `GetAddrInfoExW`, canceled with `GetAddrInfoExCancel` on timeout.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemResolver;
+
+impl ResolverBackend for SystemResolver {
+    fn resolve(
         &self,
+        host: &str,
+        port: u16,
         timeout: Duration,
     ) -> std::io::Result<std::vec::IntoIter<SocketAddr>> {
-        if let Ok(addr) = self.parse() {
-            return Ok(vec![addr].into_iter());
-        }
-
-        resolve_socket_addr((self, timeout).try_into()?)
+        resolve_timeout(host, port, &Hints::default(), timeout).and_then(resolve_socket_addr)
     }
-}
 
-impl ToSocketAddrsTimeout for (&str, u16) {
-    type Iter = std::vec::IntoIter<SocketAddr>;
-
-    fn to_socket_addrs_timeout(
+    fn resolve_with_hints(
         &self,
+        host: &str,
+        port: u16,
+        hints: &Hints,
         timeout: Duration,
-    ) -> ::std::io::Result<std::vec::IntoIter<SocketAddr>> {
-        let (host, port) = *self;
+    ) -> std::io::Result<std::vec::IntoIter<SocketAddr>> {
+        resolve_timeout(host, port, hints, timeout).and_then(resolve_socket_addr)
+    }
 
-        if let Ok(addr) = host.parse::<Ipv4Addr>() {
-            let addr = SocketAddrV4::new(addr, port);
-            return Ok(vec![SocketAddr::V4(addr)].into_iter());
-        }
-        if let Ok(addr) = host.parse::<Ipv6Addr>() {
-            let addr = SocketAddrV6::new(addr, port, 0, 0);
-            return Ok(vec![SocketAddr::V6(addr)].into_iter());
-        }
+    fn resolve_service(
+        &self,
+        host: &str,
+        service: &str,
+        hints: &Hints,
+        timeout: Duration,
+    ) -> std::io::Result<std::vec::IntoIter<SocketAddr>> {
+        getaddrinfo_timeout(
+            Some(to_wide(host)?),
+            Some(to_wide(service)?),
+            &addrinfo_hints(hints),
+            None,
+            timeout,
+        )
+        .and_then(resolve_socket_addr)
+    }
 
-        resolve_socket_addr((host, port, timeout).try_into()?)
+    fn resolve_canonical(
+        &self,
+        host: &str,
+        port: u16,
+        hints: &Hints,
+        timeout: Duration,
+    ) -> std::io::Result<CanonicalAddrs> {
+        let hints = Hints {
+            flags: hints.flags | HintFlags::CANONNAME,
+            ..*hints
+        };
+        let lh = resolve_timeout(host, port, &hints, timeout)?;
+        Ok(CanonicalAddrs {
+            name: lh.canonical_name().unwrap_or_else(|| host.to_owned()),
+            addrs: resolve_socket_addr(lh)?.collect(),
+        })
     }
 }
@@ -0,0 +1,30 @@
+/// The scope ID an IPv6 zone stands for, given as a number or as the name of a network
+/// interface.
+pub(crate) fn scope_id(zone: &str) -> Option<u32> {
+    if let Ok(id) = zone.parse() {
+        return Some(id);
+    }
+    let name = std::ffi::CString::new(zone).ok()?;
+    match interface_index(&name) {
+        0 => None,
+        index => Some(index),
+    }
+}
+
+#[cfg(unix)]
+fn interface_index(name: &std::ffi::CStr) -> u32 {
+    unsafe { libc::if_nametoindex(name.as_ptr()) }
+}
+
+#[cfg(windows)]
+fn interface_index(name: &std::ffi::CStr) -> u32 {
+    use windows::Win32::NetworkManagement::IpHelper::if_nametoindex;
+
+    unsafe { if_nametoindex(windows_core::PCSTR(name.as_ptr().cast())) }
+}
+
+/// No interfaces to name.
+#[cfg(not(any(unix, windows)))]
+fn interface_index(_: &std::ffi::CStr) -> u32 {
+    0
+}